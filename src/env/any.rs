@@ -0,0 +1,94 @@
+use std::env;
+use std::ffi;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+use env::{Env, NativeEnv, SimulatedEnv};
+
+/// Provides inspection and manipulation of either the process's environment (using a
+/// [`NativeEnv`](struct.NativeEnv.html)) or a [`SimulatedEnv`](struct.SimulatedEnv.html), chosen
+/// at runtime.
+///
+/// See [`AnyIo`](../struct.AnyIo.html) for more information.
+#[derive(Debug)]
+pub enum AnyEnv {
+    /// The process's environment.
+    Native(NativeEnv),
+    /// A simulated environment, boxed since it's much larger than a `NativeEnv`.
+    Simulated(Box<SimulatedEnv>),
+}
+
+/// Calls the same method on whichever provider `$env` contains.
+macro_rules! dispatch {
+    ($env:expr, $inner:ident => $call:expr) => {
+        match $env {
+            AnyEnv::Native($inner) => $call,
+            AnyEnv::Simulated($inner) => $call,
+        }
+    };
+}
+
+impl Env for AnyEnv {
+    type ArgsIter = vec::IntoIter<String>;
+    type ArgsOsIter = vec::IntoIter<ffi::OsString>;
+    type VarsIter = vec::IntoIter<(String, String)>;
+    type VarsOsIter = vec::IntoIter<(ffi::OsString, ffi::OsString)>;
+
+    fn args(&self) -> Self::ArgsIter {
+        dispatch!(self, env => env.args().collect::<Vec<_>>().into_iter())
+    }
+
+    fn args_os(&self) -> Self::ArgsOsIter {
+        dispatch!(self, env => env.args_os().collect::<Vec<_>>().into_iter())
+    }
+
+    fn available_parallelism(&self) -> usize {
+        dispatch!(self, env => env.available_parallelism())
+    }
+
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        dispatch!(self, env => env.current_dir())
+    }
+
+    fn current_exe(&self) -> io::Result<PathBuf> {
+        dispatch!(self, env => env.current_exe())
+    }
+
+    #[allow(deprecated)]
+    fn home_dir(&self) -> Option<PathBuf> {
+        dispatch!(self, env => env.home_dir())
+    }
+
+    fn remove_var<K: AsRef<ffi::OsStr>>(&mut self, k: K) {
+        dispatch!(self, env => env.remove_var(k))
+    }
+
+    fn set_current_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        dispatch!(self, env => env.set_current_dir(path))
+    }
+
+    fn set_var<K: AsRef<ffi::OsStr>, V: AsRef<ffi::OsStr>>(&mut self, k: K, v: V) {
+        dispatch!(self, env => env.set_var(k, v))
+    }
+
+    fn temp_dir(&self) -> PathBuf {
+        dispatch!(self, env => env.temp_dir())
+    }
+
+    fn var<K: AsRef<ffi::OsStr>>(&self, key: K) -> Result<String, env::VarError> {
+        dispatch!(self, env => env.var(key))
+    }
+
+    fn var_os<K: AsRef<ffi::OsStr>>(&self, key: K) -> Option<ffi::OsString> {
+        dispatch!(self, env => env.var_os(key))
+    }
+
+    fn vars(&self) -> Self::VarsIter {
+        dispatch!(self, env => env.vars().collect::<Vec<_>>().into_iter())
+    }
+
+    fn vars_os(&self) -> Self::VarsOsIter {
+        dispatch!(self, env => env.vars_os().collect::<Vec<_>>().into_iter())
+    }
+}
@@ -0,0 +1,988 @@
+use std::env;
+use std::ffi;
+use std::fmt;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use env::{check_var, push_normalized, Env};
+use fs::Fs;
+
+/// Higher-level environment operations, implemented in terms of [`Env`](trait.Env.html).
+///
+/// These are kept out of `Env` so that implementers of that trait only need to provide the core
+/// operations; every `Env` implementation gets these methods for free.
+///
+/// # Examples
+///
+/// ```
+/// extern crate io_providers;
+///
+/// use io_providers::{Env, EnvExt, SimulatedEnv};
+///
+/// fn main() {
+///     let mut env = SimulatedEnv::new();
+///     env.set_var("USER", "alice");
+///
+///     assert_eq!("Hello, alice!", env.expand_vars("Hello, ${USER}!"));
+///     assert_eq!("info", env.var_or("LOG_LEVEL", "info"));
+/// }
+/// ```
+pub trait EnvExt: Env {
+    /// Fetches the environment variable `key`, or returns `default` if it isn't set or isn't valid
+    /// Unicode.
+    fn var_or<K: AsRef<ffi::OsStr>, D: Into<String>>(&self, key: K, default: D) -> String {
+        self.var(key).unwrap_or_else(|_| default.into())
+    }
+
+    /// Fetches the environment variable `key`, or computes a value using `f` if it isn't set or
+    /// isn't valid Unicode.
+    fn var_or_else<K: AsRef<ffi::OsStr>, F: FnOnce() -> String>(&self, key: K, f: F) -> String {
+        self.var(key).unwrap_or_else(|_| f())
+    }
+
+    /// Fetches the environment variable `key`, comparing names case-insensitively (for ASCII
+    /// letters) on every platform. Returns `None` if no such variable is set or its value isn't
+    /// valid Unicode.
+    ///
+    /// A variable named exactly `key` is preferred; otherwise, if several names match, the one
+    /// which sorts first is used. Unless there's an exact match, this scans all of the variables,
+    /// so it takes time proportional to their number.
+    fn var_ignore_case(&self, key: &str) -> Option<String> {
+        if let Ok(value) = self.var(key) {
+            return Some(value);
+        }
+        self.vars_os()
+            .filter_map(|(name, value)| {
+                let name = name.into_string().ok()?;
+                if name.eq_ignore_ascii_case(key) {
+                    Some((name, value.into_string().ok()?))
+                } else {
+                    None
+                }
+            })
+            .min()
+            .map(|(_, value)| value)
+    }
+
+    /// Fetches the environment variable `key` as a boolean.
+    ///
+    /// `1`, `true` and `yes` are recognized as `true`, and `0`, `false` and `no` as `false`,
+    /// ignoring case. Returns `None` if the variable isn't set or has any other value.
+    fn var_bool<K: AsRef<ffi::OsStr>>(&self, key: K) -> Option<bool> {
+        let value = self.var(key).ok()?.to_ascii_lowercase();
+        match value.as_str() {
+            "1" | "true" | "yes" => Some(true),
+            "0" | "false" | "no" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Fetches the environment variable `key` and parses it as a `T`, returning `None` if it
+    /// isn't set or can't be parsed.
+    fn var_parsed<K: AsRef<ffi::OsStr>, T: FromStr>(&self, key: K) -> Option<T> {
+        self.var(key).ok()?.parse().ok()
+    }
+
+    /// Returns whether the program appears to be running under a continuous integration service,
+    /// based on the environment variables those services set.
+    ///
+    /// The variables checked are `CI`, `CONTINUOUS_INTEGRATION`, `GITHUB_ACTIONS`, `GITLAB_CI`,
+    /// `CIRCLECI`, `TRAVIS`, `BUILDKITE`, `TF_BUILD` and `TEAMCITY_VERSION`. Any of them being set
+    /// counts, unless it's empty or a value which [`var_bool()`](#method.var_bool) recognizes as
+    /// `false` (so `CI=false` doesn't count).
+    fn is_ci(&self) -> bool {
+        CI_VARS.iter().any(|key| match self.var_os(key) {
+            Some(ref value) if value.is_empty() => false,
+            Some(_) => self.var_bool(key) != Some(false),
+            None => false,
+        })
+    }
+
+    /// Sets the environment variable `key` to `value` like
+    /// [`Env::set_var()`](trait.Env.html#tymethod.set_var), but returns an error of kind
+    /// `InvalidInput` instead of panicking if `key` is empty or contains `=` or NUL, or if `value`
+    /// contains NUL.
+    fn try_set_var<K: AsRef<ffi::OsStr>, V: AsRef<ffi::OsStr>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> io::Result<()> {
+        check_var(key.as_ref(), value.as_ref())?;
+        self.set_var(key, value);
+        Ok(())
+    }
+
+    /// Reads environment variables from a dotenv-style file at `path` using `fs`, and sets them
+    /// using [`Env::set_var()`](trait.Env.html#tymethod.set_var). Returns the number of
+    /// variables set.
+    ///
+    /// Each line of the file is either blank, a comment starting with `#`, or a `KEY=VALUE`
+    /// assignment (optionally preceded by `export`). Values may be quoted: within double quotes,
+    /// `\n`, `\"` and `\\` are unescaped, while single-quoted values are taken literally. An
+    /// unquoted value ends at a ` #` comment, and surrounding whitespace is trimmed.
+    ///
+    /// If any line is invalid, an error of kind `InvalidData` is returned and no variables are set.
+    fn load_dotenv<F: Fs, P: AsRef<Path>>(&mut self, fs: &F, path: P) -> io::Result<usize> {
+        let contents = fs.read_to_string(path)?;
+        let mut vars = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let invalid = |message: &dyn fmt::Display| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", i + 1, message),
+                )
+            };
+            let (key, value) = match parse_dotenv_line(line) {
+                Some(parsed) => parsed.map_err(|message| invalid(&message))?,
+                None => continue,
+            };
+            check_var(key.as_ref(), value.as_ref()).map_err(|e| invalid(&e))?;
+            vars.push((key, value));
+        }
+
+        for (key, value) in &vars {
+            self.set_var(key, value);
+        }
+        Ok(vars.len())
+    }
+
+    /// Sets the environment variable `key` to `value` until the returned guard is dropped, at
+    /// which point the variable is restored to its previous value (or unset, if it wasn't set).
+    ///
+    /// The environment can still be used through the guard while it's alive.
+    fn scoped_var<K: AsRef<ffi::OsStr>, V: AsRef<ffi::OsStr>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> VarGuard<'_, Self> {
+        let key = key.as_ref().to_os_string();
+        let previous = self.var_os(&key);
+        self.set_var(&key, value);
+        VarGuard {
+            env: self,
+            key,
+            previous,
+        }
+    }
+
+    /// Returns all of the environment variables, sorted by name.
+    ///
+    /// The order of [`Env::vars()`](trait.Env.html#tymethod.vars) is unspecified, so this is
+    /// useful when displaying the variables or comparing them in tests.
+    fn vars_sorted(&self) -> Vec<(String, String)> {
+        let mut vars: Vec<(String, String)> = self.vars().collect();
+        vars.sort();
+        vars
+    }
+
+    /// Expands references to environment variables in `input`, in the style of a Unix shell.
+    ///
+    /// Both `$NAME` and `${NAME}` forms are supported, where `NAME` consists of ASCII letters,
+    /// digits and underscores. References to unset variables expand to an empty string; a `$`
+    /// which doesn't begin a reference is left as-is.
+    fn expand_vars(&self, input: &str) -> String {
+        let mut result = String::with_capacity(input.len());
+        let mut rest = input;
+
+        while let Some(start) = rest.find('$') {
+            result.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+
+            let (name, consumed) = if after.starts_with('{') {
+                match after.find('}') {
+                    Some(end) => (&after[1..end], end + 1),
+                    None => ("", 0),
+                }
+            } else {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            };
+
+            if name.is_empty() {
+                result.push('$');
+            } else if let Some(value) = self.var_os(name) {
+                result.push_str(&value.to_string_lossy());
+            }
+            rest = &after[consumed..];
+        }
+
+        result.push_str(rest);
+        result
+    }
+
+    /// Returns the path of the current user's configuration directory, if known.
+    ///
+    /// On Windows this is `%APPDATA%`; on macOS it is `~/Library/Application Support`. Elsewhere
+    /// it is `$XDG_CONFIG_HOME` if set to an absolute path, or `~/.config` otherwise.
+    fn config_dir(&self) -> Option<PathBuf> {
+        #[allow(deprecated)]
+        let home_dir = self.home_dir();
+
+        if cfg!(windows) {
+            self.var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            home_dir.map(|home| home.join("Library/Application Support"))
+        } else {
+            self.var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .filter(|path| path.is_absolute())
+                .or_else(|| home_dir.map(|home| home.join(".config")))
+        }
+    }
+
+    /// Returns the path of the user's shell, if known.
+    ///
+    /// On Windows this is `%ComSpec%` (usually `cmd.exe`); elsewhere it is `$SHELL`. `None` is
+    /// returned if the variable isn't set or is empty.
+    fn shell(&self) -> Option<PathBuf> {
+        let key = if cfg!(windows) { "ComSpec" } else { "SHELL" };
+        self.var_os(key)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    }
+
+    /// Expands a leading `~` component of `path` to the current user's home directory, in the
+    /// style of a Unix shell.
+    ///
+    /// Only `~` on its own is expanded, so `~user` is left as-is. `path` is returned unchanged if
+    /// it doesn't start with `~` or the home directory isn't known.
+    fn expand_tilde<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        #[allow(deprecated)]
+        match (path.strip_prefix("~"), self.home_dir()) {
+            (Ok(rest), Some(home)) if rest.as_os_str().is_empty() => home,
+            (Ok(rest), Some(home)) => home.join(rest),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Resolves `path` against the current directory, returning it as an absolute path.
+    ///
+    /// A relative `path` is joined onto [`Env::current_dir()`](trait.Env.html#tymethod.current_dir),
+    /// while an absolute one is used as-is. Either way, `.` and `..` components are then resolved
+    /// lexically, without touching the filesystem, so `..` after a symbolic link leads back to the
+    /// link's parent rather than to its target's parent.
+    fn absolute_path<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let path = path.as_ref();
+        let mut resolved = if path.is_absolute() {
+            PathBuf::new()
+        } else {
+            self.current_dir()?
+        };
+        push_normalized(&mut resolved, path);
+        Ok(resolved)
+    }
+
+    /// Splits the environment variable `key` into paths using the platform's path separator
+    /// (e.g. `:` for `PATH` on Unix), returning an empty list if it isn't set.
+    ///
+    /// See [`std::env::split_paths`](https://doc.rust-lang.org/std/env/fn.split_paths.html) for
+    /// more information.
+    fn split_paths<K: AsRef<ffi::OsStr>>(&self, key: K) -> Vec<PathBuf> {
+        self.var_os(key)
+            .map(|value| env::split_paths(&value).collect())
+            .unwrap_or_default()
+    }
+
+    /// Splits a list-valued environment variable `key`, such as `MANPATH`, into paths like
+    /// [`split_paths()`](#method.split_paths), but skipping empty entries.
+    ///
+    /// Empty entries (e.g. from `a::b` or a trailing separator) are usually accidental, or have a
+    /// meaning specific to the variable, such as `MANPATH` falling back to the default path.
+    fn var_list<K: AsRef<ffi::OsStr>>(&self, key: K) -> Vec<PathBuf> {
+        self.split_paths(key)
+            .into_iter()
+            .filter(|path| !path.as_os_str().is_empty())
+            .collect()
+    }
+
+    /// Finds the executable file which running `program` would run, by searching the directories
+    /// in the `PATH` environment variable in order, using `fs` to check each candidate.
+    ///
+    /// On Windows, if `program` has no extension, each extension in the `PATHEXT` environment
+    /// variable (or `.COM;.EXE;.BAT;.CMD` if it's unset) is tried in turn, in lowercase. If
+    /// `program` has more than one component (e.g. `./run`), `PATH` isn't searched and it's only
+    /// checked directly.
+    ///
+    /// On Unix, a file must have an execute permission bit set to count, if `fs` can report its
+    /// permissions; otherwise any regular file counts.
+    fn which<F: Fs, P: AsRef<Path>>(&self, fs: &F, program: P) -> Option<PathBuf> {
+        let program = program.as_ref();
+        let candidates = program_candidates(self, program);
+
+        if program.components().count() > 1 {
+            return candidates.into_iter().find(|path| is_executable(fs, path));
+        }
+        self.split_paths("PATH")
+            .into_iter()
+            .flat_map(|dir| candidates.iter().map(move |candidate| dir.join(candidate)))
+            .find(|path| is_executable(fs, path))
+    }
+
+    /// Adds `dir` to the start of the `PATH` environment variable, so that it's searched first.
+    ///
+    /// If `dir` is already present in `PATH`, it's left unchanged. Returns an error if `dir`
+    /// contains the platform's path separator.
+    fn prepend_path<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), env::JoinPathsError> {
+        add_to_path(self, dir.as_ref(), true)
+    }
+
+    /// Adds `dir` to the end of the `PATH` environment variable, so that it's searched last.
+    ///
+    /// If `dir` is already present in `PATH`, it's left unchanged. Returns an error if `dir`
+    /// contains the platform's path separator.
+    fn append_path<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), env::JoinPathsError> {
+        add_to_path(self, dir.as_ref(), false)
+    }
+
+    /// Returns the arguments before the first `--` separator, excluding the separator itself.
+    ///
+    /// If there is no separator, this is all of the arguments (including the program name).
+    fn args_before_separator(&self) -> Vec<String> {
+        self.args().take_while(|arg| arg != "--").collect()
+    }
+
+    /// Returns the arguments after the first `--` separator, which are typically passed through to
+    /// another program. If there is no separator, this is empty.
+    fn args_after_separator(&self) -> Vec<String> {
+        self.args().skip_while(|arg| arg != "--").skip(1).collect()
+    }
+
+    /// Returns the `n`th argument which this program was started with, where the 0th is normally
+    /// the program itself, without requiring it to be valid Unicode.
+    fn nth_arg_os(&self, n: usize) -> Option<ffi::OsString> {
+        self.args_os().nth(n)
+    }
+
+    /// Returns the file name of the running program, as given by the first argument it was
+    /// started with.
+    fn program_name(&self) -> Option<String> {
+        self.args_os().next().and_then(|arg| {
+            Path::new(&arg)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+    }
+}
+
+impl<T: Env> EnvExt for T {}
+
+/// A guard which restores an environment variable to its previous state when dropped, returned by
+/// [`EnvExt::scoped_var()`](trait.EnvExt.html#method.scoped_var).
+pub struct VarGuard<'a, E: Env + ?Sized + 'a> {
+    env: &'a mut E,
+    key: ffi::OsString,
+    previous: Option<ffi::OsString>,
+}
+
+impl<'a, E: Env + ?Sized> Deref for VarGuard<'a, E> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        self.env
+    }
+}
+
+impl<'a, E: Env + ?Sized> DerefMut for VarGuard<'a, E> {
+    fn deref_mut(&mut self) -> &mut E {
+        self.env
+    }
+}
+
+impl<'a, E: Env + ?Sized> Drop for VarGuard<'a, E> {
+    fn drop(&mut self) {
+        match self.previous.take() {
+            Some(previous) => self.env.set_var(&self.key, previous),
+            None => self.env.remove_var(&self.key),
+        }
+    }
+}
+
+/// Environment variables set by common continuous integration services, checked by
+/// [`EnvExt::is_ci()`](trait.EnvExt.html#method.is_ci).
+const CI_VARS: &[&str] = &[
+    "CI",
+    "CONTINUOUS_INTEGRATION",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "CIRCLECI",
+    "TRAVIS",
+    "BUILDKITE",
+    "TF_BUILD",
+    "TEAMCITY_VERSION",
+];
+
+/// Parses a line of a dotenv file into a key and value, or returns `None` if it's blank or a
+/// comment.
+fn parse_dotenv_line(line: &str) -> Option<Result<(String, String), &'static str>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let line = line.strip_prefix("export ").unwrap_or(line);
+
+    let (key, value) = match line.split_once('=') {
+        Some((key, value)) => (key.trim(), value.trim()),
+        None => return Some(Err("expected KEY=VALUE")),
+    };
+    Some(parse_dotenv_value(value).map(|value| (key.to_owned(), value)))
+}
+
+fn parse_dotenv_value(value: &str) -> Result<String, &'static str> {
+    if let Some(rest) = value.strip_prefix('\'') {
+        return match rest.split_once('\'') {
+            Some((quoted, trailing)) if is_comment_or_empty(trailing) => Ok(quoted.to_owned()),
+            Some(_) => Err("unexpected characters after single-quoted value"),
+            None => Err("unterminated single-quoted value"),
+        };
+    }
+
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut result = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    return if is_comment_or_empty(chars.as_str()) {
+                        Ok(result)
+                    } else {
+                        Err("unexpected characters after double-quoted value")
+                    };
+                }
+                '\\' => match chars.next() {
+                    Some('n') => result.push('\n'),
+                    Some(escaped @ ('"' | '\\')) => result.push(escaped),
+                    Some(other) => {
+                        result.push('\\');
+                        result.push(other);
+                    }
+                    None => break,
+                },
+                c => result.push(c),
+            }
+        }
+        return Err("unterminated double-quoted value");
+    }
+
+    let value = match value.find(" #") {
+        Some(comment) => &value[..comment],
+        None => value,
+    };
+    Ok(value.trim_end().to_owned())
+}
+
+fn is_comment_or_empty(s: &str) -> bool {
+    let s = s.trim_start();
+    s.is_empty() || s.starts_with('#')
+}
+
+/// Returns the file names which running `program` could refer to, in the order they're tried.
+fn program_candidates<E: Env + ?Sized>(env: &E, program: &Path) -> Vec<PathBuf> {
+    if !cfg!(windows) || program.extension().is_some() {
+        return vec![program.to_path_buf()];
+    }
+    let extensions = env
+        .var("PATHEXT")
+        .unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_owned());
+    extensions
+        .split(';')
+        .filter(|extension| !extension.is_empty())
+        .map(|extension| {
+            let mut name = program.as_os_str().to_owned();
+            name.push(extension.to_ascii_lowercase());
+            PathBuf::from(name)
+        })
+        .collect()
+}
+
+/// Returns whether `path` is an executable file, as far as `fs` can tell.
+fn is_executable<F: Fs + ?Sized>(fs: &F, path: &Path) -> bool {
+    if !fs.stat(path).map(|m| m.is_file()).unwrap_or(false) {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = fs.metadata(path) {
+            return metadata.permissions().mode() & 0o111 != 0;
+        }
+    }
+    true
+}
+
+fn add_to_path<E: Env + ?Sized>(
+    env: &mut E,
+    dir: &Path,
+    prepend: bool,
+) -> Result<(), env::JoinPathsError> {
+    let mut paths: Vec<PathBuf> = env
+        .var_os("PATH")
+        .map(|value| env::split_paths(&value).collect())
+        .unwrap_or_default();
+    if paths.iter().any(|path| path == dir) {
+        return Ok(());
+    }
+
+    if prepend {
+        paths.insert(0, dir.to_path_buf());
+    } else {
+        paths.push(dir.to_path_buf());
+    }
+    env.set_var("PATH", env::join_paths(paths)?);
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use std::env;
+    use std::io;
+    use std::path::PathBuf;
+
+    use super::EnvExt;
+    use env::{Env, SimulatedEnv};
+    use fs::{Fs, MemoryFs};
+
+    #[test]
+    fn var_or__defined_and_undefined__returns_value_or_default() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("FOO", "bar");
+
+        assert_eq!("bar", provider.var_or("FOO", "default"));
+        assert_eq!("default", provider.var_or("BAZ", "default"));
+    }
+
+    #[test]
+    fn var_or_else__defined_and_undefined__returns_value_or_computed() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("FOO", "bar");
+
+        assert_eq!("bar", provider.var_or_else("FOO", || unreachable!()));
+        assert_eq!(
+            "computed",
+            provider.var_or_else("BAZ", || "computed".to_owned())
+        );
+    }
+
+    #[test]
+    fn var_ignore_case__differently_cased_keys__found() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("MyVar", "value");
+
+        assert_eq!(Some("value".to_owned()), provider.var_ignore_case("MYVAR"));
+        assert_eq!(Some("value".to_owned()), provider.var_ignore_case("myvar"));
+        assert_eq!(None, provider.var_ignore_case("OTHER"));
+    }
+
+    #[test]
+    fn var_ignore_case__several_matches__exact_then_first_sorted() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("path", "lower");
+        provider.set_var("Path", "title");
+        provider.set_var("PATH", "upper");
+
+        assert_eq!(Some("title".to_owned()), provider.var_ignore_case("Path"));
+        assert_eq!(Some("upper".to_owned()), provider.var_ignore_case("pAtH"));
+    }
+
+    #[test]
+    fn var_bool__recognized_forms__parsed() {
+        let mut provider = SimulatedEnv::new();
+        let forms = [
+            ("1", true),
+            ("true", true),
+            ("TRUE", true),
+            ("yes", true),
+            ("Yes", true),
+            ("0", false),
+            ("false", false),
+            ("False", false),
+            ("no", false),
+            ("NO", false),
+        ];
+
+        for &(value, expected) in forms.iter() {
+            provider.set_var("FLAG", value);
+            assert_eq!(Some(expected), provider.var_bool("FLAG"), "{}", value);
+        }
+    }
+
+    #[test]
+    fn var_bool__unset_or_unrecognized__none() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("FLAG", "maybe");
+
+        assert_eq!(None, provider.var_bool("FLAG"));
+        assert_eq!(None, provider.var_bool("UNSET"));
+    }
+
+    #[test]
+    fn var_parsed__u16__parsed_or_none() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("PORT", "8080");
+        provider.set_var("BAD_PORT", "70000");
+
+        assert_eq!(Some(8080u16), provider.var_parsed("PORT"));
+        assert_eq!(None, provider.var_parsed::<_, u16>("BAD_PORT"));
+        assert_eq!(None, provider.var_parsed::<_, u16>("UNSET"));
+    }
+
+    #[test]
+    fn is_ci__ci_true_false_and_unset__detected_only_when_true() {
+        let mut provider = SimulatedEnv::new();
+        assert!(!provider.is_ci());
+
+        provider.set_var("CI", "true");
+        assert!(provider.is_ci());
+
+        provider.set_var("CI", "false");
+        assert!(!provider.is_ci());
+
+        provider.set_var("GITHUB_ACTIONS", "true");
+        assert!(provider.is_ci());
+    }
+
+    #[test]
+    fn try_set_var__valid_key__sets_var() {
+        let mut provider = SimulatedEnv::new();
+
+        provider.try_set_var("FOO", "bar").unwrap();
+
+        assert_eq!(Ok("bar".to_owned()), provider.var("FOO"));
+    }
+
+    #[test]
+    fn try_set_var__invalid_keys_and_value__invalid_input() {
+        let mut provider = SimulatedEnv::new();
+
+        for &(key, value) in &[
+            ("", "bar"),
+            ("A=B", "bar"),
+            ("A\0B", "bar"),
+            ("FOO", "b\0r"),
+        ] {
+            let result = provider.try_set_var(key, value);
+            assert_eq!(io::ErrorKind::InvalidInput, result.unwrap_err().kind());
+        }
+        assert_eq!(0, provider.vars().count());
+    }
+
+    #[test]
+    fn load_dotenv__comments_quotes_and_blank_lines__vars_set() {
+        let mut fs = MemoryFs::new();
+        fs.write(
+            "/.env",
+            concat!(
+                "# Database settings\n",
+                "DB_HOST=localhost\n",
+                "\n",
+                "export DB_PORT = 5432 # default port\n",
+                "GREETING=\"hello \\\"world\\\"\\nbye\" # quoted\n",
+                "LITERAL='no \\n escapes # here'\n",
+                "EMPTY=\n",
+            ),
+        )
+        .unwrap();
+        let mut provider = SimulatedEnv::new();
+
+        let count = provider.load_dotenv(&fs, "/.env").unwrap();
+
+        assert_eq!(5, count);
+        assert_eq!(
+            vec![
+                ("DB_HOST".to_owned(), "localhost".to_owned()),
+                ("DB_PORT".to_owned(), "5432".to_owned()),
+                ("EMPTY".to_owned(), "".to_owned()),
+                ("GREETING".to_owned(), "hello \"world\"\nbye".to_owned()),
+                ("LITERAL".to_owned(), "no \\n escapes # here".to_owned()),
+            ],
+            provider.vars_sorted()
+        );
+    }
+
+    #[test]
+    fn load_dotenv__invalid_line__invalid_data_and_nothing_set() {
+        let mut fs = MemoryFs::new();
+        fs.write("/.env", "FOO=bar\nnot an assignment\n").unwrap();
+        let mut provider = SimulatedEnv::new();
+
+        let result = provider.load_dotenv(&fs, "/.env");
+
+        let error = result.unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        assert!(error.to_string().starts_with("line 2: "));
+        assert_eq!(0, provider.vars().count());
+    }
+
+    #[test]
+    fn scoped_var__previously_set_and_unset__restored_on_drop() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("FOO", "old");
+
+        {
+            let guard = provider.scoped_var("FOO", "new");
+            assert_eq!(Ok("new".to_owned()), guard.var("FOO"));
+        }
+        assert_eq!(Ok("old".to_owned()), provider.var("FOO"));
+
+        {
+            let mut guard = provider.scoped_var("BAR", "new");
+            assert_eq!(Ok("new".to_owned()), guard.var("BAR"));
+            guard.set_var("OTHER", "kept");
+        }
+        assert_eq!(None, provider.var_os("BAR"));
+        assert_eq!(Ok("kept".to_owned()), provider.var("OTHER"));
+    }
+
+    #[test]
+    fn vars_sorted__set_out_of_order__sorted_by_name() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("B", "2");
+        provider.set_var("C", "3");
+        provider.set_var("A", "1");
+
+        let result = provider.vars_sorted();
+
+        assert_eq!(
+            vec![
+                ("A".to_owned(), "1".to_owned()),
+                ("B".to_owned(), "2".to_owned()),
+                ("C".to_owned(), "3".to_owned()),
+            ],
+            result
+        );
+    }
+
+    #[test]
+    fn expand_vars__both_forms__expanded() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("FOO", "foo");
+        provider.set_var("BAR_2", "bar");
+
+        let result = provider.expand_vars("$FOO/${BAR_2}x/$UNSET/$/${");
+
+        assert_eq!("foo/barx//$/${", result);
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn config_dir__xdg_set_or_unset__prefers_xdg() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_home_dir(Some("/home/user"));
+
+        assert_eq!(
+            Some(PathBuf::from("/home/user/.config")),
+            provider.config_dir()
+        );
+
+        provider.set_var("XDG_CONFIG_HOME", "/xdg");
+        assert_eq!(Some(PathBuf::from("/xdg")), provider.config_dir());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn shell__shell_set__returns_path() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("SHELL", "/bin/zsh");
+
+        assert_eq!(Some(PathBuf::from("/bin/zsh")), provider.shell());
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn shell__comspec_set__returns_path() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("ComSpec", r"C:\Windows\system32\cmd.exe");
+
+        assert_eq!(
+            Some(PathBuf::from(r"C:\Windows\system32\cmd.exe")),
+            provider.shell()
+        );
+    }
+
+    #[test]
+    fn shell__unset_or_empty__none() {
+        let mut provider = SimulatedEnv::new();
+        assert_eq!(None, provider.shell());
+
+        provider.set_var("SHELL", "");
+        provider.set_var("ComSpec", "");
+        assert_eq!(None, provider.shell());
+    }
+
+    #[test]
+    fn expand_tilde__home_set__expands_only_leading_tilde() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_home_dir(Some("/home/user"));
+
+        assert_eq!(PathBuf::from("/home/user"), provider.expand_tilde("~"));
+        assert_eq!(
+            PathBuf::from("/home/user/foo"),
+            provider.expand_tilde("~/foo")
+        );
+        assert_eq!(PathBuf::from("/abs"), provider.expand_tilde("/abs"));
+        assert_eq!(
+            PathBuf::from("~user/foo"),
+            provider.expand_tilde("~user/foo")
+        );
+    }
+
+    #[test]
+    fn expand_tilde__home_unset__unchanged() {
+        let provider = SimulatedEnv::new();
+
+        assert_eq!(PathBuf::from("~/foo"), provider.expand_tilde("~/foo"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn absolute_path__absolute_relative_and_parent_components__resolved() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_current_dir("/work/project").unwrap();
+
+        assert_eq!(
+            PathBuf::from("/etc/hosts"),
+            provider.absolute_path("/etc/hosts").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("/work/project/src/main.rs"),
+            provider.absolute_path("src/main.rs").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("/work/other/file.txt"),
+            provider.absolute_path("./../other/./file.txt").unwrap()
+        );
+    }
+
+    #[test]
+    fn split_paths__multiple_entries__returns_each() {
+        let mut provider = SimulatedEnv::new();
+        let joined = env::join_paths(["/bin", "/usr/bin"]).unwrap();
+        provider.set_var("PATH", joined);
+
+        let result = provider.split_paths("PATH");
+
+        assert_eq!(
+            vec![PathBuf::from("/bin"), PathBuf::from("/usr/bin")],
+            result
+        );
+        assert!(provider.split_paths("UNSET").is_empty());
+    }
+
+    #[test]
+    fn var_list__multiple_and_empty_entries__non_empty_entries_returned() {
+        let mut provider = SimulatedEnv::new();
+        let joined = env::join_paths(["", "/opt/app", "", "/etc/app", ""]).unwrap();
+        provider.set_var("APP_DIRS", joined);
+
+        let result = provider.var_list("APP_DIRS");
+
+        assert_eq!(
+            vec![PathBuf::from("/opt/app"), PathBuf::from("/etc/app")],
+            result
+        );
+        assert!(provider.var_list("UNSET").is_empty());
+    }
+
+    #[test]
+    fn prepend_path_and_append_path__new_dirs__added_at_correct_ends() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("PATH", env::join_paths(["/bin"]).unwrap());
+
+        provider.prepend_path("/first").unwrap();
+        provider.append_path("/last").unwrap();
+
+        assert_eq!(
+            vec![
+                PathBuf::from("/first"),
+                PathBuf::from("/bin"),
+                PathBuf::from("/last"),
+            ],
+            provider.split_paths("PATH")
+        );
+    }
+
+    #[test]
+    fn prepend_path_and_append_path__existing_dirs__not_duplicated() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("PATH", env::join_paths(["/a", "/b"]).unwrap());
+
+        provider.prepend_path("/b").unwrap();
+        provider.append_path("/a").unwrap();
+
+        assert_eq!(
+            vec![PathBuf::from("/a"), PathBuf::from("/b")],
+            provider.split_paths("PATH")
+        );
+    }
+
+    #[test]
+    fn prepend_path__path_unset__path_contains_only_dir() {
+        let mut provider = SimulatedEnv::new();
+
+        provider.prepend_path("/bin").unwrap();
+
+        assert_eq!(vec![PathBuf::from("/bin")], provider.split_paths("PATH"));
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn args_separator__separator_present__split_around_it() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_args(args(&["app", "-v", "--", "cmd", "--"]));
+
+        assert_eq!(args(&["app", "-v"]), provider.args_before_separator());
+        assert_eq!(args(&["cmd", "--"]), provider.args_after_separator());
+    }
+
+    #[test]
+    fn args_separator__separator_absent__all_before() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_args(args(&["app", "-v"]));
+
+        assert_eq!(args(&["app", "-v"]), provider.args_before_separator());
+        assert!(provider.args_after_separator().is_empty());
+    }
+
+    #[test]
+    fn args_separator__separator_last__empty_after() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_args(args(&["app", "-v", "--"]));
+
+        assert_eq!(args(&["app", "-v"]), provider.args_before_separator());
+        assert!(provider.args_after_separator().is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn nth_arg_os__non_utf8_arg__round_trips_unchanged() {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+
+        let mut provider = SimulatedEnv::new();
+        let arg = OsString::from_vec(vec![b'a', 0xFF, b'b']);
+        provider.set_args_os(vec![OsString::from("app"), arg.clone()]);
+
+        assert_eq!(
+            vec![OsString::from("app"), arg.clone()],
+            provider.args_os().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(arg), provider.nth_arg_os(1));
+        assert_eq!(None, provider.nth_arg_os(2));
+    }
+
+    #[test]
+    fn program_name__args_set__returns_file_name() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_args(vec!["/usr/bin/app".to_owned(), "arg1".to_owned()]);
+
+        assert_eq!(Some("app".to_owned()), provider.program_name());
+    }
+}
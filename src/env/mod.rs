@@ -1,16 +1,22 @@
 //! Defines traits and implementations for the inspection and manipulation of the process's
 //! environment.
 
+mod any;
+mod ext;
 mod native;
 mod simulated;
 
+pub use self::any::AnyEnv;
+pub use self::ext::{EnvExt, VarGuard};
 pub use self::native::NativeEnv;
-pub use self::simulated::SimulatedEnv;
+pub use self::simulated::{SimulatedEnv, SimulatedEnvBuilder};
 
 use std::env;
 use std::ffi;
 use std::io;
-use std::path::{Path, PathBuf};
+use std::num::NonZeroUsize;
+use std::path::{Component, Path, PathBuf};
+use std::thread;
 
 /// Provides inspection and manipulation of the process's environment.
 ///
@@ -74,6 +80,18 @@ pub trait Env {
     /// information.
     fn args_os(&self) -> Self::ArgsOsIter;
 
+    /// Returns an estimate of the number of threads the program can usefully run in parallel,
+    /// which is usually the number of logical CPUs available to it. This is always at least 1.
+    ///
+    /// Returns 1 if the estimate can't be determined. See
+    /// [`std::thread::available_parallelism`](https://doc.rust-lang.org/std/thread/fn.available_parallelism.html)
+    /// for more information.
+    ///
+    /// The default implementation returns the estimate for the real process.
+    fn available_parallelism(&self) -> usize {
+        thread::available_parallelism().map_or(1, NonZeroUsize::get)
+    }
+
     /// Returns the current working directory as a `PathBuf`.
     ///
     /// See [`std::env::current_dir`](https://doc.rust-lang.org/std/env/fn.current_dir.html) for
@@ -112,6 +130,9 @@ pub trait Env {
 
     /// Sets the environment variable `k` to the value `v` for the currently running process.
     ///
+    /// Panics if `k` is empty or contains `=` or NUL, or if `v` contains NUL; see
+    /// [`EnvExt::try_set_var()`](trait.EnvExt.html#method.try_set_var) for a fallible version.
+    ///
     /// See [`std::env::set_var`](https://doc.rust-lang.org/std/env/fn.set_var.html) for more
     /// information.
     fn set_var<K: AsRef<ffi::OsStr>, V: AsRef<ffi::OsStr>>(&mut self, k: K, v: V);
@@ -144,3 +165,36 @@ pub trait Env {
     /// See [`std::env::vars_os`](https://doc.rust-lang.org/std/env/fn.vars_os.html) for more information.
     fn vars_os(&self) -> Self::VarsOsIter;
 }
+
+/// Checks that `key` and `value` are valid for an environment variable, like `std::env::set_var()`
+/// does: the key must be non-empty and not contain `=` or NUL, and the value must not contain NUL.
+fn check_var(key: &ffi::OsStr, value: &ffi::OsStr) -> io::Result<()> {
+    let key_bytes = key.as_encoded_bytes();
+    let message = if key_bytes.is_empty() {
+        "empty key"
+    } else if key_bytes.contains(&b'=') {
+        "key contains '='"
+    } else if key_bytes.contains(&0) || value.as_encoded_bytes().contains(&0) {
+        "key or value contains NUL"
+    } else {
+        return Ok(());
+    };
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid environment variable {:?}: {}", key, message),
+    ))
+}
+
+/// Pushes the components of `path` onto `base`, resolving `.` and `..` components lexically (so
+/// `..` removes the last component of `base`, without regard for symbolic links).
+fn push_normalized(base: &mut PathBuf, path: &Path) {
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                base.pop();
+            }
+            component => base.push(component),
+        }
+    }
+}
@@ -3,20 +3,29 @@ use std::env;
 use std::ffi;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::vec;
 
-use env::Env;
+use env::{check_var, push_normalized, Env};
 
 /// Provides inspection and manipulation of a simulated process environment.
-#[derive(Default)]
+///
+/// By default, calling an `Env` method whose simulated value hasn't been set panics; see
+/// [`set_strict()`](#method.set_strict) to use default values instead.
+#[derive(Debug, Default)]
 pub struct SimulatedEnv {
     args: Option<Vec<String>>,
     args_os: Option<Vec<ffi::OsString>>,
     current_dir: Option<PathBuf>,
     current_exe: Option<PathBuf>,
     home_dir: Option<PathBuf>,
+    parallelism: Option<usize>,
     temp_dir: Option<PathBuf>,
     vars: HashMap<ffi::OsString, ffi::OsString>,
+    /// The keys of the variables which have been read, in the order they were first read.
+    var_reads: Mutex<Vec<ffi::OsString>>,
+    lenient: bool,
+    strict_current_dir: bool,
 }
 
 impl SimulatedEnv {
@@ -28,26 +37,92 @@ impl SimulatedEnv {
             current_dir: None,
             current_exe: None,
             home_dir: None,
+            parallelism: None,
             temp_dir: None,
             vars: HashMap::new(),
+            var_reads: Mutex::new(Vec::new()),
+            lenient: false,
+            strict_current_dir: false,
         }
     }
 
+    /// Returns a builder for configuring a new simulated environment.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use io_providers::env::{Env, SimulatedEnv};
+    ///
+    /// let env = SimulatedEnv::builder()
+    ///     .arg("program")
+    ///     .var("LOG_LEVEL", "debug")
+    ///     .current_dir("/work")
+    ///     .build();
+    ///
+    /// assert_eq!(vec!["program".to_owned()], env.args().collect::<Vec<_>>());
+    /// assert_eq!("debug", env.var("LOG_LEVEL").unwrap());
+    /// ```
+    pub fn builder() -> SimulatedEnvBuilder {
+        SimulatedEnvBuilder {
+            env: SimulatedEnv::new(),
+            args: None,
+        }
+    }
+
+    /// Sets whether this environment is strict about values which haven't been set (the default).
+    ///
+    /// When strict, calling an `Env` method whose simulated value hasn't been set panics. When not
+    /// strict, such methods instead return a default value:
+    ///
+    /// * `args()` and `args_os()` return no arguments
+    /// * `current_dir()` returns `/`
+    /// * `available_parallelism()` returns 1
+    /// * `current_exe()` returns `/simulated-exe`
+    /// * `temp_dir()` returns `/tmp`
+    pub fn set_strict(&mut self, strict: bool) {
+        self.lenient = !strict;
+    }
+
+    /// Sets whether `Env::set_current_dir()` keeps the current directory absolute (by default, it
+    /// stores whatever path it's given).
+    ///
+    /// When enabled, a relative path is resolved against the existing current directory, like it
+    /// would be by a real process, and `.` and `..` components are resolved lexically. Setting a
+    /// relative path fails with `ErrorKind::InvalidInput` if the current directory hasn't been set
+    /// and this environment is strict (see [`set_strict()`](#method.set_strict)).
+    pub fn set_strict_current_dir(&mut self, strict: bool) {
+        self.strict_current_dir = strict;
+    }
+
     /// Sets the arguments which this program was started with (normally passed via the command
     /// line).
+    ///
+    /// This also sets the value returned by `Env::args_os()`.
     pub fn set_args(&mut self, args: Vec<String>) {
+        self.args_os = Some(args.iter().map(ffi::OsString::from).collect());
         self.args = Some(args);
     }
 
     /// Sets the arguments which this program was started with (normally passed via the command
     /// line).
+    ///
+    /// This also sets the value returned by `Env::args()`; any arguments which are not valid
+    /// Unicode are converted lossily, replacing invalid sequences with `U+FFFD`.
     pub fn set_args_os(&mut self, args: Vec<ffi::OsString>) {
+        self.args = Some(
+            args.iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+        );
         self.args_os = Some(args);
     }
 
     /// Sets the path to be returned by `Env::current_exe()`.
-    pub fn set_current_exe<P: AsRef<Path>>(&mut self, path: P) {
-        self.current_dir = Some(PathBuf::from(path.as_ref()));
+    ///
+    /// Passing `None` makes it unset again, as it is in a new environment (see
+    /// [`set_strict()`](#method.set_strict)).
+    pub fn set_current_exe<P: AsRef<Path>>(&mut self, path: Option<P>) {
+        self.current_exe = path.map(|p| PathBuf::from(p.as_ref()));
     }
 
     /// Sets the path to be returned by `Env::home_dir()`.
@@ -55,10 +130,149 @@ impl SimulatedEnv {
         self.home_dir = path.map(|p| PathBuf::from(p.as_ref()));
     }
 
+    /// Sets the value to be returned by `Env::available_parallelism()`.
+    ///
+    /// Panics if `n` is 0, since a real process can always run at least one thread.
+    pub fn set_parallelism(&mut self, n: usize) {
+        assert!(n > 0, "parallelism must be at least 1");
+        self.parallelism = Some(n);
+    }
+
     /// Sets the path to be returned by `Env::temp_dir()`.
     pub fn set_temp_dir<P: AsRef<Path>>(&mut self, path: P) {
         self.temp_dir = Some(PathBuf::from(path.as_ref()));
     }
+
+    /// Returns the names of the environment variables which have been read with `Env::var()` or
+    /// `Env::var_os()`, whether or not they were set, in the order they were first read.
+    ///
+    /// This includes reads made through [`EnvExt`](trait.EnvExt.html) methods which look up
+    /// individual variables, but not `Env::vars()` or `Env::vars_os()`. Names which aren't valid
+    /// Unicode are converted lossily.
+    pub fn var_reads(&self) -> Vec<String> {
+        self.var_reads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .map(|key| key.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Records that the variable `key` has been read.
+    fn record_read(&self, key: &ffi::OsStr) {
+        let mut var_reads = self
+            .var_reads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !var_reads.iter().any(|read| read == key) {
+            var_reads.push(key.to_os_string());
+        }
+    }
+
+    /// Returns the simulated value for `Env::<method>()`, falling back to `default` if it's unset
+    /// and this environment isn't strict.
+    fn get_or_default<T: Clone, F: FnOnce() -> T>(
+        &self,
+        value: &Option<T>,
+        method: &str,
+        default: F,
+    ) -> T {
+        match *value {
+            Some(ref value) => value.clone(),
+            None if self.lenient => default(),
+            None => panic!(
+                "Env::{}() was called before a simulated value was set",
+                method
+            ),
+        }
+    }
+}
+
+/// A builder for a [`SimulatedEnv`](struct.SimulatedEnv.html), returned by
+/// [`SimulatedEnv::builder()`](struct.SimulatedEnv.html#method.builder).
+///
+/// Values which aren't configured are left unset, as they are in `SimulatedEnv::new()`.
+pub struct SimulatedEnvBuilder {
+    env: SimulatedEnv,
+    args: Option<Vec<ffi::OsString>>,
+}
+
+impl SimulatedEnvBuilder {
+    /// Adds an argument to those which the program was started with.
+    pub fn arg<S: Into<ffi::OsString>>(mut self, arg: S) -> SimulatedEnvBuilder {
+        self.args.get_or_insert_with(Vec::new).push(arg.into());
+        self
+    }
+
+    /// Adds several arguments to those which the program was started with.
+    pub fn args<I: IntoIterator<Item = S>, S: Into<ffi::OsString>>(
+        mut self,
+        args: I,
+    ) -> SimulatedEnvBuilder {
+        self.args
+            .get_or_insert_with(Vec::new)
+            .extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets an environment variable.
+    ///
+    /// Panics under the same conditions as [`Env::set_var()`](trait.Env.html#tymethod.set_var).
+    pub fn var<K: AsRef<ffi::OsStr>, V: AsRef<ffi::OsStr>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> SimulatedEnvBuilder {
+        self.env.set_var(key, value);
+        self
+    }
+
+    /// Sets the current working directory. The path is stored as given.
+    pub fn current_dir<P: AsRef<Path>>(mut self, path: P) -> SimulatedEnvBuilder {
+        self.env.current_dir = Some(PathBuf::from(path.as_ref()));
+        self
+    }
+
+    /// Sets the path to be returned by `Env::current_exe()`.
+    pub fn current_exe<P: AsRef<Path>>(mut self, path: P) -> SimulatedEnvBuilder {
+        self.env.set_current_exe(Some(path));
+        self
+    }
+
+    /// Sets the path to be returned by `Env::home_dir()`.
+    pub fn home_dir<P: AsRef<Path>>(mut self, path: P) -> SimulatedEnvBuilder {
+        self.env.set_home_dir(Some(path));
+        self
+    }
+
+    /// Sets the value to be returned by `Env::available_parallelism()`.
+    ///
+    /// Panics if `n` is 0.
+    pub fn parallelism(mut self, n: usize) -> SimulatedEnvBuilder {
+        self.env.set_parallelism(n);
+        self
+    }
+
+    /// Sets the path to be returned by `Env::temp_dir()`.
+    pub fn temp_dir<P: AsRef<Path>>(mut self, path: P) -> SimulatedEnvBuilder {
+        self.env.set_temp_dir(path);
+        self
+    }
+
+    /// Sets whether the environment is strict about values which haven't been set; see
+    /// [`SimulatedEnv::set_strict()`](struct.SimulatedEnv.html#method.set_strict).
+    pub fn strict(mut self, strict: bool) -> SimulatedEnvBuilder {
+        self.env.set_strict(strict);
+        self
+    }
+
+    /// Creates the configured environment.
+    pub fn build(mut self) -> SimulatedEnv {
+        if let Some(args) = self.args {
+            self.env.set_args_os(args);
+        }
+        self.env
+    }
 }
 
 impl Env for SimulatedEnv {
@@ -68,31 +282,27 @@ impl Env for SimulatedEnv {
     type VarsOsIter = vec::IntoIter<(ffi::OsString, ffi::OsString)>;
 
     fn args(&self) -> Self::ArgsIter {
-        self.args
-            .clone()
-            .expect("Env::args() was called before a simulated value was set")
+        self.get_or_default(&self.args, "args", Vec::new)
             .into_iter()
     }
 
     fn args_os(&self) -> Self::ArgsOsIter {
-        self.args_os
-            .clone()
-            .expect("Env::args_os() was called before a simulated value was set")
+        self.get_or_default(&self.args_os, "args_os", Vec::new)
             .into_iter()
     }
 
+    fn available_parallelism(&self) -> usize {
+        self.get_or_default(&self.parallelism, "available_parallelism", || 1)
+    }
+
     fn current_dir(&self) -> io::Result<PathBuf> {
-        Ok(self
-            .current_dir
-            .clone()
-            .expect("Env::current_dir() was called before a simulated value was set"))
+        Ok(self.get_or_default(&self.current_dir, "current_dir", || PathBuf::from("/")))
     }
 
     fn current_exe(&self) -> io::Result<PathBuf> {
-        Ok(self
-            .current_exe
-            .clone()
-            .expect("Env::current_exe() was called before a simulated value was set"))
+        Ok(self.get_or_default(&self.current_exe, "current_exe", || {
+            PathBuf::from("/simulated-exe")
+        }))
     }
 
     fn home_dir(&self) -> Option<PathBuf> {
@@ -104,23 +314,52 @@ impl Env for SimulatedEnv {
     }
 
     fn set_current_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        self.current_dir = Some(PathBuf::from(path.as_ref()));
+        let path = path.as_ref();
+        if path.as_os_str().as_encoded_bytes().contains(&0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "path contains NUL",
+            ));
+        }
+        if !self.strict_current_dir {
+            self.current_dir = Some(PathBuf::from(path));
+            return Ok(());
+        }
+
+        let mut resolved = if path.has_root() {
+            PathBuf::new()
+        } else {
+            match (self.current_dir.clone(), self.lenient) {
+                (Some(current_dir), _) => current_dir,
+                (None, true) => PathBuf::from("/"),
+                (None, false) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "relative path given before the current directory was set",
+                    ));
+                }
+            }
+        };
+        push_normalized(&mut resolved, path);
+        self.current_dir = Some(resolved);
         Ok(())
     }
 
     fn set_var<K: AsRef<ffi::OsStr>, V: AsRef<ffi::OsStr>>(&mut self, k: K, v: V) {
+        if let Err(e) = check_var(k.as_ref(), v.as_ref()) {
+            panic!("failed to set environment variable: {}", e);
+        }
         let _ = self
             .vars
             .insert(k.as_ref().to_os_string(), v.as_ref().to_os_string());
     }
 
     fn temp_dir(&self) -> PathBuf {
-        self.temp_dir
-            .clone()
-            .expect("Env::temp_dir() was called before a simulated value was set")
+        self.get_or_default(&self.temp_dir, "temp_dir", || PathBuf::from("/tmp"))
     }
 
     fn var<K: AsRef<ffi::OsStr>>(&self, key: K) -> Result<String, env::VarError> {
+        self.record_read(key.as_ref());
         self.vars
             .get(&key.as_ref().to_os_string())
             .ok_or(env::VarError::NotPresent)
@@ -128,6 +367,7 @@ impl Env for SimulatedEnv {
     }
 
     fn var_os<K: AsRef<ffi::OsStr>>(&self, key: K) -> Option<ffi::OsString> {
+        self.record_read(key.as_ref());
         self.vars.get(&key.as_ref().to_os_string()).cloned()
     }
 
@@ -157,6 +397,7 @@ impl Env for SimulatedEnv {
 mod tests {
     use std::env;
     use std::ffi::OsString;
+    use std::io;
     use std::path::Path;
 
     use super::SimulatedEnv;
@@ -202,6 +443,76 @@ mod tests {
         assert_eq!(args, result);
     }
 
+    #[test]
+    fn args_os__set_via_set_args__success() {
+        let mut provider = SimulatedEnv::new();
+        let args = vec!["app".to_string(), "arg1".to_string()];
+
+        provider.set_args(args);
+        let result: Vec<OsString> = provider.args_os().collect();
+
+        assert_eq!(vec![OsString::from("app"), OsString::from("arg1")], result);
+    }
+
+    #[test]
+    fn args__set_via_set_args_os__success() {
+        let mut provider = SimulatedEnv::new();
+        let args = vec![OsString::from("app"), OsString::from("arg1")];
+
+        provider.set_args_os(args);
+        let result: Vec<String> = provider.args().collect();
+
+        assert_eq!(vec!["app".to_string(), "arg1".to_string()], result);
+    }
+
+    /// Splits `items` into one contiguous chunk per available thread, as a parallel tool might.
+    fn chunk_sizes<E: Env>(env: &E, items: usize) -> Vec<usize> {
+        let threads = env.available_parallelism().min(items).max(1);
+        (0..threads)
+            .map(|i| items / threads + if i < items % threads { 1 } else { 0 })
+            .collect()
+    }
+
+    #[test]
+    #[should_panic]
+    fn available_parallelism__called_before_set__panics() {
+        let provider = SimulatedEnv::new();
+        let _ = provider.available_parallelism();
+    }
+
+    #[test]
+    fn available_parallelism__not_strict__one() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_strict(false);
+
+        assert_eq!(1, provider.available_parallelism());
+    }
+
+    #[test]
+    fn available_parallelism__set_to_1__work_in_one_chunk() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_parallelism(1);
+
+        assert_eq!(1, provider.available_parallelism());
+        assert_eq!(vec![20], chunk_sizes(&provider, 20));
+    }
+
+    #[test]
+    fn available_parallelism__set_to_8__work_split_evenly() {
+        let provider = SimulatedEnv::builder().parallelism(8).build();
+
+        assert_eq!(8, provider.available_parallelism());
+        assert_eq!(vec![3, 3, 3, 3, 2, 2, 2, 2], chunk_sizes(&provider, 20));
+        assert_eq!(vec![1, 1, 1], chunk_sizes(&provider, 3));
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_parallelism__zero__panics() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_parallelism(0);
+    }
+
     #[test]
     #[should_panic]
     fn current_dir__called_before_set__panics() {
@@ -220,6 +531,38 @@ mod tests {
         assert_eq!(path, result.as_path());
     }
 
+    #[test]
+    fn current_dir__path_containing_nul__invalid_input() {
+        let mut provider = SimulatedEnv::new();
+
+        let result = provider.set_current_dir("/foo\0bar");
+
+        assert_eq!(io::ErrorKind::InvalidInput, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn set_strict_current_dir__relative_path__resolved_against_current_dir() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_strict_current_dir(true);
+        provider.set_current_dir("/a").unwrap();
+
+        provider.set_current_dir("b").unwrap();
+        assert_eq!(Path::new("/a/b"), provider.current_dir().unwrap());
+
+        provider.set_current_dir("../c/./d").unwrap();
+        assert_eq!(Path::new("/a/c/d"), provider.current_dir().unwrap());
+    }
+
+    #[test]
+    fn set_strict_current_dir__relative_path_and_no_current_dir__invalid_input() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_strict_current_dir(true);
+
+        let result = provider.set_current_dir("b");
+
+        assert_eq!(io::ErrorKind::InvalidInput, result.unwrap_err().kind());
+    }
+
     #[test]
     #[should_panic]
     fn current_exe__called_before_set__panics() {
@@ -232,10 +575,21 @@ mod tests {
         let mut provider = SimulatedEnv::new();
         let path = Path::new("/foo/bar");
 
-        provider.set_current_exe(path);
-        let result = provider.current_dir().unwrap();
+        provider.set_current_exe(Some(path));
+        let result = provider.current_exe().unwrap();
 
         assert_eq!(path, result.as_path());
+        assert!(provider.current_dir.is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn current_exe__set_then_cleared__panics() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_current_exe(Some("/foo/bar"));
+
+        provider.set_current_exe(None::<&Path>);
+        let _ = provider.current_exe();
     }
 
     #[test]
@@ -277,6 +631,36 @@ mod tests {
         assert_eq!(path, result.as_path());
     }
 
+    #[test]
+    #[should_panic(expected = "Env::current_dir() was called before a simulated value was set")]
+    fn set_strict__strict_and_value_unset__panics() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_strict(true);
+
+        let _ = provider.current_dir();
+    }
+
+    #[test]
+    fn set_strict__lenient_and_values_unset__returns_defaults() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_strict(false);
+
+        assert_eq!(0, provider.args().count());
+        assert_eq!(0, provider.args_os().count());
+        assert_eq!(Path::new("/"), provider.current_dir().unwrap());
+        assert_eq!(Path::new("/simulated-exe"), provider.current_exe().unwrap());
+        assert_eq!(Path::new("/tmp"), provider.temp_dir());
+    }
+
+    #[test]
+    fn set_strict__lenient_and_value_set__returns_value() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_strict(false);
+        provider.set_current_dir("/foo").unwrap();
+
+        assert_eq!(Path::new("/foo"), provider.current_dir().unwrap());
+    }
+
     #[test]
     fn var__get_undefined_var__returns_not_present() {
         let provider = SimulatedEnv::new();
@@ -315,6 +699,27 @@ mod tests {
         assert_eq!(Some(OsString::from("bar".to_owned())), result);
     }
 
+    #[test]
+    #[should_panic(expected = "failed to set environment variable")]
+    fn set_var__empty_key__panics() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("", "bar");
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to set environment variable")]
+    fn set_var__key_containing_equals__panics() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("A=B", "bar");
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to set environment variable")]
+    fn set_var__key_containing_nul__panics() {
+        let mut provider = SimulatedEnv::new();
+        provider.set_var("A\0B", "bar");
+    }
+
     #[test]
     fn remove_var__value_previously_defined__value_is_removed() {
         let mut provider = SimulatedEnv::new();
@@ -357,4 +762,46 @@ mod tests {
             OsString::from("123".to_owned())
         )));
     }
+
+    #[test]
+    fn builder__all_values_configured__set_on_env() {
+        let provider = SimulatedEnv::builder()
+            .arg("program")
+            .args(vec!["--verbose", "x"])
+            .var("A", "1")
+            .var("B", "2")
+            .current_dir("/w")
+            .current_exe("/bin/program")
+            .home_dir("/home/u")
+            .temp_dir("/scratch")
+            .build();
+
+        assert_eq!(
+            vec!["program", "--verbose", "x"],
+            provider.args().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                OsString::from("program"),
+                OsString::from("--verbose"),
+                OsString::from("x")
+            ],
+            provider.args_os().collect::<Vec<_>>()
+        );
+        assert_eq!("1", provider.var("A").unwrap());
+        assert_eq!("2", provider.var("B").unwrap());
+        assert_eq!(Path::new("/w"), provider.current_dir().unwrap());
+        assert_eq!(Path::new("/bin/program"), provider.current_exe().unwrap());
+        #[allow(deprecated)]
+        let home_dir = provider.home_dir();
+        assert_eq!(Some(Path::new("/home/u").to_path_buf()), home_dir);
+        assert_eq!(Path::new("/scratch"), provider.temp_dir());
+    }
+
+    #[test]
+    #[should_panic]
+    fn builder__args_not_configured__args_unset() {
+        let provider = SimulatedEnv::builder().var("A", "1").build();
+        let _ = provider.args();
+    }
 }
@@ -0,0 +1,212 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use fs::{DirEntry, FileKind, Fs, Lines, Metadata, NativeFs, OpenOptions, TempFs};
+
+/// Provides file I/O using either a [`NativeFs`](struct.NativeFs.html) or a
+/// [`TempFs`](struct.TempFs.html), chosen at runtime.
+///
+/// See [`AnyIo`](../struct.AnyIo.html) for more information.
+#[derive(Debug)]
+pub enum AnyFs {
+    /// Native file I/O.
+    Native(NativeFs),
+    /// File I/O in a temporary sandbox.
+    Simulated(TempFs),
+}
+
+/// Calls the same method on whichever provider `$fs` contains.
+macro_rules! dispatch {
+    ($fs:expr, $inner:ident => $call:expr) => {
+        match $fs {
+            AnyFs::Native($inner) => $call,
+            AnyFs::Simulated($inner) => $call,
+        }
+    };
+}
+
+impl Fs for AnyFs {
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        open_options: &OpenOptions,
+    ) -> io::Result<fs::File> {
+        dispatch!(self, fs => fs.open(path, open_options))
+    }
+
+    fn open_reporting<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        open_options: &OpenOptions,
+    ) -> io::Result<(PathBuf, fs::File)> {
+        dispatch!(self, fs => fs.open_reporting(path, open_options))
+    }
+
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        dispatch!(self, fs => fs.canonicalize(path))
+    }
+
+    fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<u64> {
+        dispatch!(self, fs => fs.copy(from, to))
+    }
+
+    fn copy_streaming<P: AsRef<Path>, Q: AsRef<Path>, F: FnMut(u64) -> bool>(
+        &mut self,
+        from: P,
+        to: Q,
+        on_progress: F,
+    ) -> io::Result<u64> {
+        dispatch!(self, fs => fs.copy_streaming(from, to, on_progress))
+    }
+
+    fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        dispatch!(self, fs => fs.create_dir(path))
+    }
+
+    fn ensure_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        dispatch!(self, fs => fs.ensure_dir(path))
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        dispatch!(self, fs => fs.create_dir_all(path))
+    }
+
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q) -> io::Result<()> {
+        dispatch!(self, fs => fs.hard_link(src, dst))
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata> {
+        dispatch!(self, fs => fs.metadata(path))
+    }
+
+    fn stat<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        dispatch!(self, fs => fs.stat(path))
+    }
+
+    #[cfg(unix)]
+    fn nlink<P: AsRef<Path>>(&self, path: P) -> io::Result<u64> {
+        dispatch!(self, fs => fs.nlink(path))
+    }
+
+    fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        dispatch!(self, fs => fs.read(path))
+    }
+
+    fn read_prefix<P: AsRef<Path>>(&self, path: P, n: usize) -> io::Result<Vec<u8>> {
+        dispatch!(self, fs => fs.read_prefix(path, n))
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::ReadDir> {
+        dispatch!(self, fs => fs.read_dir(path))
+    }
+
+    fn list_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<DirEntry>> {
+        dispatch!(self, fs => fs.list_dir(path))
+    }
+
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        dispatch!(self, fs => fs.read_link(path))
+    }
+
+    fn read_lines<P: AsRef<Path>>(&self, path: P) -> io::Result<Lines> {
+        dispatch!(self, fs => fs.read_lines(path))
+    }
+
+    fn read_lines_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_line_bytes: usize,
+    ) -> io::Result<Lines> {
+        dispatch!(self, fs => fs.read_lines_limited(path, max_line_bytes))
+    }
+
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        dispatch!(self, fs => fs.read_to_string(path))
+    }
+
+    fn read_to_string_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_bytes: usize,
+    ) -> io::Result<String> {
+        dispatch!(self, fs => fs.read_to_string_limited(path, max_bytes))
+    }
+
+    fn remove_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        dispatch!(self, fs => fs.remove_dir(path))
+    }
+
+    fn remove_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        dispatch!(self, fs => fs.remove_dir_all(path))
+    }
+
+    fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        dispatch!(self, fs => fs.remove_file(path))
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        dispatch!(self, fs => fs.rename(from, to))
+    }
+
+    fn swap<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, a: P, b: Q) -> io::Result<()> {
+        dispatch!(self, fs => fs.swap(a, b))
+    }
+
+    fn set_permissions<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        perm: fs::Permissions,
+    ) -> io::Result<()> {
+        dispatch!(self, fs => fs.set_permissions(path, perm))
+    }
+
+    fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata> {
+        dispatch!(self, fs => fs.symlink_metadata(path))
+    }
+
+    fn truncate<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        dispatch!(self, fs => fs.truncate(path))
+    }
+
+    fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&mut self, path: P, contents: C) -> io::Result<()> {
+        dispatch!(self, fs => fs.write(path, contents))
+    }
+
+    fn write_counted<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<u64> {
+        dispatch!(self, fs => fs.write_counted(path, contents))
+    }
+
+    fn write_at<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        offset: u64,
+        data: C,
+    ) -> io::Result<()> {
+        dispatch!(self, fs => fs.write_at(path, offset, data))
+    }
+
+    fn append_locked<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<()> {
+        dispatch!(self, fs => fs.append_locked(path, contents))
+    }
+
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        dispatch!(self, fs => fs.exists(path))
+    }
+
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        dispatch!(self, fs => fs.try_exists(path))
+    }
+
+    fn kind<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<FileKind>> {
+        dispatch!(self, fs => fs.kind(path))
+    }
+}
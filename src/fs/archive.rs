@@ -0,0 +1,280 @@
+use std::fs;
+use std::io;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+use zip::ZipArchive;
+
+use fs::{DirEntry, FileKind, Fs, Lines, MemoryFs, Metadata, OpenOptions};
+
+/// The most memory which is allocated up front for an entry, whatever size the archive claims it
+/// has.
+const MAX_PREALLOCATION: u64 = 1 << 20;
+
+/// Provides read-only access to the files in a ZIP archive.
+///
+/// The archive is unpacked into memory when the `ArchiveFs` is created, and is then accessed like
+/// a [`MemoryFs`](struct.MemoryFs.html): the archive's top level is the root directory, relative
+/// paths are resolved against it, and the operations which `MemoryFs` doesn't support aren't
+/// supported here either (use `stat()` and `list_dir()` instead of `metadata()` and
+/// `read_dir()`).
+///
+/// Details to be aware of:
+///   * Every operation which would modify the filesystem fails with
+///     `ErrorKind::PermissionDenied`, as does opening a file with any option which could modify
+///     it.
+///   * Directories which contain files are present even if the archive has no entries for them.
+///   * Entries whose names would place them outside of the root (e.g. `../file.txt`) are
+///     skipped.
+///
+/// This requires the `zip` feature.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::fs::File;
+/// use io_providers::fs::{ArchiveFs, Fs};
+///
+/// let fs = ArchiveFs::new(File::open("fixtures.zip").unwrap()).unwrap();
+/// let config = fs.read_to_string("/config/app.toml").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ArchiveFs {
+    files: MemoryFs,
+}
+
+impl ArchiveFs {
+    /// Creates a new `ArchiveFs` containing the files in the ZIP archive read from `reader`.
+    ///
+    /// Fails with `ErrorKind::InvalidData` if the archive is malformed.
+    pub fn new<R: Read + Seek>(reader: R) -> io::Result<ArchiveFs> {
+        let mut archive = ZipArchive::new(reader)?;
+        let mut files = MemoryFs::new();
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let path = match entry.enclosed_name() {
+                Some(name) => Path::new("/").join(name),
+                None => continue,
+            };
+            if entry.is_dir() {
+                files.create_dir_all(&path)?;
+            } else {
+                // The size comes from the archive, so it's only trusted up to a point
+                let capacity = entry.size().min(MAX_PREALLOCATION) as usize;
+                let mut contents = Vec::with_capacity(capacity);
+                entry.read_to_end(&mut contents)?;
+                if let Some(parent) = path.parent() {
+                    files.create_dir_all(parent)?;
+                }
+                files.write(&path, contents)?;
+            }
+        }
+        Ok(ArchiveFs { files })
+    }
+}
+
+impl Fs for ArchiveFs {
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        open_options: &OpenOptions,
+    ) -> io::Result<fs::File> {
+        check_read_only(open_options)?;
+        self.files.open(path, open_options)
+    }
+
+    fn open_reporting<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        open_options: &OpenOptions,
+    ) -> io::Result<(PathBuf, fs::File)> {
+        check_read_only(open_options)?;
+        self.files.open_reporting(path, open_options)
+    }
+
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        self.files.canonicalize(path)
+    }
+
+    fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, _from: P, _to: Q) -> io::Result<u64> {
+        Err(read_only())
+    }
+
+    fn copy_streaming<P: AsRef<Path>, Q: AsRef<Path>, F: FnMut(u64) -> bool>(
+        &mut self,
+        _from: P,
+        _to: Q,
+        _on_progress: F,
+    ) -> io::Result<u64> {
+        Err(read_only())
+    }
+
+    fn create_dir<P: AsRef<Path>>(&mut self, _path: P) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn ensure_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        match self.files.kind(path)? {
+            Some(FileKind::Dir) => Ok(false),
+            _ => Err(read_only()),
+        }
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&mut self, _path: P) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, _src: P, _dst: Q) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata> {
+        self.files.metadata(path)
+    }
+
+    fn stat<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        self.files.stat(path)
+    }
+
+    #[cfg(unix)]
+    fn nlink<P: AsRef<Path>>(&self, path: P) -> io::Result<u64> {
+        self.files.nlink(path)
+    }
+
+    fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        self.files.read(path)
+    }
+
+    fn read_prefix<P: AsRef<Path>>(&self, path: P, n: usize) -> io::Result<Vec<u8>> {
+        self.files.read_prefix(path, n)
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::ReadDir> {
+        self.files.read_dir(path)
+    }
+
+    fn list_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<DirEntry>> {
+        self.files.list_dir(path)
+    }
+
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        self.files.read_link(path)
+    }
+
+    fn read_lines<P: AsRef<Path>>(&self, path: P) -> io::Result<Lines> {
+        self.files.read_lines(path)
+    }
+
+    fn read_lines_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_line_bytes: usize,
+    ) -> io::Result<Lines> {
+        self.files.read_lines_limited(path, max_line_bytes)
+    }
+
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        self.files.read_to_string(path)
+    }
+
+    fn read_to_string_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_bytes: usize,
+    ) -> io::Result<String> {
+        self.files.read_to_string_limited(path, max_bytes)
+    }
+
+    fn remove_dir<P: AsRef<Path>>(&mut self, _path: P) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn remove_dir_all<P: AsRef<Path>>(&mut self, _path: P) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn remove_file<P: AsRef<Path>>(&mut self, _path: P) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, _from: P, _to: Q) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn swap<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, _a: P, _b: Q) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn set_permissions<P: AsRef<Path>>(
+        &mut self,
+        _path: P,
+        _perm: fs::Permissions,
+    ) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata> {
+        self.files.symlink_metadata(path)
+    }
+
+    fn truncate<P: AsRef<Path>>(&mut self, _path: P) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&mut self, _path: P, _contents: C) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn write_counted<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        _path: P,
+        _contents: C,
+    ) -> io::Result<u64> {
+        Err(read_only())
+    }
+
+    fn write_at<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        _path: P,
+        _offset: u64,
+        _data: C,
+    ) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn append_locked<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        _path: P,
+        _contents: C,
+    ) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.files.exists(path)
+    }
+
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        self.files.try_exists(path)
+    }
+
+    fn kind<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<FileKind>> {
+        self.files.kind(path)
+    }
+}
+
+/// Checks that opening a file with `open_options` can't modify it.
+fn check_read_only(open_options: &OpenOptions) -> io::Result<()> {
+    if open_options.is_read_only() {
+        Ok(())
+    } else {
+        Err(read_only())
+    }
+}
+
+fn read_only() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "archive filesystems are read-only",
+    )
+}
@@ -0,0 +1,369 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use fs::{copy_cancelled, DirEntry, FileKind, Fs, FsEvent, Lines, Metadata, OpenOptions};
+
+/// Provides access to another filesystem in "dry run" mode: reads are passed through to it, but
+/// changes are only recorded rather than made.
+///
+/// This is useful for implementing a `--dry-run` option, by reporting the
+/// [`planned_operations()`](#method.planned_operations) instead of performing them.
+///
+/// Details to be aware of:
+///   * Operations which would make a change always succeed, without checking whether the change
+///     could actually be made. Since nothing changes, later reads don't see the effects of
+///     earlier changes either.
+///   * Recorded paths are as given, rather than being made absolute.
+///   * Files can only be opened (using [`open()`] or `open_reporting()`) for reading; opening a
+///     file with any option which could modify it fails with `ErrorKind::Unsupported`.
+///   * `copy()` and `copy_streaming()` return the length of the source file, which must exist;
+///     `copy_streaming()` reports progress once, with that length, and records nothing if that
+///     cancels it.
+///
+/// [`open()`]: fs/trait.Fs.html#tymethod.open
+///
+/// ## Example
+///
+/// ```
+/// use io_providers::fs::{DryRunFs, Fs, FsEvent, MemoryFs};
+/// use std::path::PathBuf;
+///
+/// let mut fs = DryRunFs::new(MemoryFs::new());
+/// fs.write("/file.txt", "contents").unwrap();
+///
+/// assert!(!fs.exists("/file.txt"));
+/// assert_eq!(
+///     &[FsEvent::Write(PathBuf::from("/file.txt"))],
+///     fs.planned_operations()
+/// );
+/// ```
+#[derive(Debug)]
+pub struct DryRunFs<F: Fs> {
+    inner: F,
+    planned: Vec<FsEvent>,
+}
+
+impl<F: Fs> DryRunFs<F> {
+    /// Creates a new `DryRunFs` which reads from `inner`.
+    pub fn new(inner: F) -> DryRunFs<F> {
+        DryRunFs {
+            inner,
+            planned: Vec::new(),
+        }
+    }
+
+    /// Returns the underlying filesystem.
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    /// Returns the changes which would have been made, in the order they were requested.
+    pub fn planned_operations(&self) -> &[FsEvent] {
+        &self.planned
+    }
+
+    /// Records a change, instead of making it.
+    fn plan(&mut self, event: FsEvent) {
+        self.planned.push(event);
+    }
+
+    /// Checks that opening a file with `open_options` can't modify it.
+    fn check_read_only(open_options: &OpenOptions) -> io::Result<()> {
+        if open_options.is_read_only() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "files can only be opened for reading in a dry run",
+            ))
+        }
+    }
+}
+
+impl<F: Fs> Fs for DryRunFs<F> {
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        open_options: &OpenOptions,
+    ) -> io::Result<fs::File> {
+        Self::check_read_only(open_options)?;
+        self.inner.open(path, open_options)
+    }
+
+    fn open_reporting<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        open_options: &OpenOptions,
+    ) -> io::Result<(PathBuf, fs::File)> {
+        Self::check_read_only(open_options)?;
+        self.inner.open_reporting(path, open_options)
+    }
+
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        self.inner.canonicalize(path)
+    }
+
+    fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<u64> {
+        let len = self.inner.stat(from)?.len();
+        self.plan(FsEvent::Write(to.as_ref().to_path_buf()));
+        Ok(len)
+    }
+
+    fn copy_streaming<P: AsRef<Path>, Q: AsRef<Path>, C: FnMut(u64) -> bool>(
+        &mut self,
+        from: P,
+        to: Q,
+        mut on_progress: C,
+    ) -> io::Result<u64> {
+        let len = self.inner.stat(from)?.len();
+        if !on_progress(len) {
+            return Err(copy_cancelled());
+        }
+        self.plan(FsEvent::Write(to.as_ref().to_path_buf()));
+        Ok(len)
+    }
+
+    fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.plan(FsEvent::CreateDir(path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    fn ensure_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        if self.inner.stat(&path).map(|m| m.is_dir()).unwrap_or(false) {
+            return Ok(false);
+        }
+        self.create_dir(path)?;
+        Ok(true)
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.plan(FsEvent::CreateDir(path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q) -> io::Result<()> {
+        self.plan(FsEvent::Link {
+            src: src.as_ref().to_path_buf(),
+            dst: dst.as_ref().to_path_buf(),
+        });
+        Ok(())
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata> {
+        self.inner.metadata(path)
+    }
+
+    fn stat<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        self.inner.stat(path)
+    }
+
+    #[cfg(unix)]
+    fn nlink<P: AsRef<Path>>(&self, path: P) -> io::Result<u64> {
+        self.inner.nlink(path)
+    }
+
+    fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        self.inner.read(path)
+    }
+
+    fn read_prefix<P: AsRef<Path>>(&self, path: P, n: usize) -> io::Result<Vec<u8>> {
+        self.inner.read_prefix(path, n)
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::ReadDir> {
+        self.inner.read_dir(path)
+    }
+
+    fn list_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<DirEntry>> {
+        self.inner.list_dir(path)
+    }
+
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        self.inner.read_link(path)
+    }
+
+    fn read_lines<P: AsRef<Path>>(&self, path: P) -> io::Result<Lines> {
+        self.inner.read_lines(path)
+    }
+
+    fn read_lines_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_line_bytes: usize,
+    ) -> io::Result<Lines> {
+        self.inner.read_lines_limited(path, max_line_bytes)
+    }
+
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        self.inner.read_to_string(path)
+    }
+
+    fn read_to_string_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_bytes: usize,
+    ) -> io::Result<String> {
+        self.inner.read_to_string_limited(path, max_bytes)
+    }
+
+    fn remove_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.plan(FsEvent::Remove(path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    fn remove_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.plan(FsEvent::Remove(path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.plan(FsEvent::Remove(path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        self.plan(FsEvent::Rename {
+            from: from.as_ref().to_path_buf(),
+            to: to.as_ref().to_path_buf(),
+        });
+        Ok(())
+    }
+
+    fn swap<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, a: P, b: Q) -> io::Result<()> {
+        self.plan(FsEvent::Swap {
+            a: a.as_ref().to_path_buf(),
+            b: b.as_ref().to_path_buf(),
+        });
+        Ok(())
+    }
+
+    fn set_permissions<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        _perm: fs::Permissions,
+    ) -> io::Result<()> {
+        self.plan(FsEvent::SetPermissions(path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata> {
+        self.inner.symlink_metadata(path)
+    }
+
+    fn truncate<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.plan(FsEvent::Write(path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&mut self, path: P, _contents: C) -> io::Result<()> {
+        self.plan(FsEvent::Write(path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    fn write_counted<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<u64> {
+        self.plan(FsEvent::Write(path.as_ref().to_path_buf()));
+        Ok(contents.as_ref().len() as u64)
+    }
+
+    fn write_at<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        _offset: u64,
+        _data: C,
+    ) -> io::Result<()> {
+        self.plan(FsEvent::Write(path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    fn append_locked<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        _contents: C,
+    ) -> io::Result<()> {
+        self.plan(FsEvent::Write(path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        self.inner.try_exists(path)
+    }
+
+    fn kind<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<FileKind>> {
+        self.inner.kind(path)
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use std::io;
+    use std::path::PathBuf;
+
+    use super::DryRunFs;
+    use fs::{Fs, FsEvent, OpenOptions, TempFs};
+
+    #[test]
+    fn write_and_remove__planned_but_not_made() {
+        let mut inner = TempFs::new().expect("Failed to create new TempFs");
+        inner.write("/existing.txt", "contents").unwrap();
+        let mut fs = DryRunFs::new(inner);
+
+        fs.write("/new.txt", "contents").unwrap();
+        fs.remove_file("/existing.txt").unwrap();
+        let open_result = fs.open("/new.txt", OpenOptions::new().write(true).create(true));
+
+        assert!(!fs.exists("/new.txt"));
+        assert_eq!("contents", fs.read_to_string("/existing.txt").unwrap());
+        assert_eq!(io::ErrorKind::Unsupported, open_result.unwrap_err().kind());
+        assert_eq!(
+            &[
+                FsEvent::Write(PathBuf::from("/new.txt")),
+                FsEvent::Remove(PathBuf::from("/existing.txt")),
+            ][..],
+            fs.planned_operations()
+        );
+        assert_eq!(1, fs.inner().operations().len());
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn open__read_with_access_mode__unsupported() {
+        const GENERIC_WRITE: u32 = 0x4000_0000;
+        let mut inner = TempFs::new().expect("Failed to create new TempFs");
+        inner.write("/file.txt", "contents").unwrap();
+        let mut fs = DryRunFs::new(inner);
+
+        let result = fs.open(
+            "/file.txt",
+            OpenOptions::new().read(true).access_mode(GENERIC_WRITE),
+        );
+
+        assert_eq!(io::ErrorKind::Unsupported, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn copy_streaming__existing_source__progress_reported_once_with_length() {
+        let mut inner = TempFs::new().expect("Failed to create new TempFs");
+        inner.write("/src.txt", "contents").unwrap();
+        let mut fs = DryRunFs::new(inner);
+        let mut progress = Vec::new();
+
+        let result = fs.copy_streaming("/src.txt", "/dst.txt", |copied| {
+            progress.push(copied);
+            true
+        });
+
+        assert_eq!(8, result.unwrap());
+        assert_eq!(vec![8], progress);
+        assert!(!fs.exists("/dst.txt"));
+    }
+}
@@ -0,0 +1,110 @@
+use std::error::Error;
+use std::io;
+
+/// A text encoding which files can be read and written with, using
+/// [`FsExt::read_to_string_encoding()`](trait.FsExt.html#method.read_to_string_encoding) and
+/// [`FsExt::write_encoding()`](trait.FsExt.html#method.write_encoding).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8, written without a byte order mark.
+    Utf8,
+    /// Little-endian UTF-16, written with a byte order mark.
+    Utf16Le,
+    /// Big-endian UTF-16, written with a byte order mark.
+    Utf16Be,
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+
+impl Encoding {
+    /// Decodes `data`, which is assumed to be in this encoding unless it starts with a byte order
+    /// mark identifying another one. Any byte order mark is removed.
+    pub(crate) fn decode(self, data: &[u8]) -> io::Result<String> {
+        let (encoding, data) = if let Some(rest) = data.strip_prefix(UTF8_BOM) {
+            (Encoding::Utf8, rest)
+        } else if let Some(rest) = data.strip_prefix(UTF16LE_BOM) {
+            (Encoding::Utf16Le, rest)
+        } else if let Some(rest) = data.strip_prefix(UTF16BE_BOM) {
+            (Encoding::Utf16Be, rest)
+        } else {
+            (self, data)
+        };
+
+        let from_bytes: fn([u8; 2]) -> u16 = match encoding {
+            Encoding::Utf8 => {
+                return String::from_utf8(data.to_vec()).map_err(invalid_data);
+            }
+            Encoding::Utf16Le => u16::from_le_bytes,
+            Encoding::Utf16Be => u16::from_be_bytes,
+        };
+        if data.len() % 2 != 0 {
+            return Err(invalid_data("UTF-16 data has an odd number of bytes"));
+        }
+        let units: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|pair| from_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16(&units).map_err(invalid_data)
+    }
+
+    /// Encodes `text` in this encoding, with a byte order mark for UTF-16.
+    pub(crate) fn encode(self, text: &str) -> Vec<u8> {
+        let mut data = match self {
+            Encoding::Utf8 => return text.as_bytes().to_vec(),
+            Encoding::Utf16Le => UTF16LE_BOM.to_vec(),
+            Encoding::Utf16Be => UTF16BE_BOM.to_vec(),
+        };
+        for unit in text.encode_utf16() {
+            let bytes = match self {
+                Encoding::Utf16Be => unit.to_be_bytes(),
+                _ => unit.to_le_bytes(),
+            };
+            data.extend_from_slice(&bytes);
+        }
+        data
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::Encoding;
+    use std::io;
+
+    #[test]
+    fn encode__utf16le__bom_then_little_endian_units() {
+        let result = Encoding::Utf16Le.encode("hé");
+
+        assert_eq!(b"\xFF\xFEh\x00\xE9\x00", &result[..]);
+    }
+
+    #[test]
+    fn decode__bom_for_other_encoding__bom_wins() {
+        let utf16be = b"\xFE\xFF\x00h\x00i";
+        let utf8 = b"\xEF\xBB\xBFhi";
+
+        assert_eq!("hi", Encoding::Utf16Le.decode(utf16be).unwrap());
+        assert_eq!("hi", Encoding::Utf16Be.decode(utf8).unwrap());
+    }
+
+    #[test]
+    fn decode__no_bom__given_encoding_used() {
+        assert_eq!("hi", Encoding::Utf16Be.decode(b"\x00h\x00i").unwrap());
+        assert_eq!("hi", Encoding::Utf8.decode(b"hi").unwrap());
+    }
+
+    #[test]
+    fn decode__odd_length_or_unpaired_surrogate__invalid_data() {
+        let odd = Encoding::Utf16Le.decode(b"h\x00i");
+        let unpaired = Encoding::Utf16Le.decode(b"\x00\xD8");
+
+        assert_eq!(io::ErrorKind::InvalidData, odd.unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, unpaired.unwrap_err().kind());
+    }
+}
@@ -0,0 +1,571 @@
+use std::fs;
+use std::io;
+use std::io::{BufReader, BufWriter};
+use std::path::{Component, Path, PathBuf};
+use std::str;
+use std::time::SystemTime;
+
+#[cfg(feature = "encoding")]
+use fs::Encoding;
+use fs::{temp_sibling, DirEntry, FileKind, Fs, Metadata, OpenOptions, WalkDir};
+#[cfg(feature = "hash")]
+use sha2::{Digest, Sha256};
+
+/// Higher-level filesystem operations, implemented in terms of [`Fs`](trait.Fs.html).
+///
+/// These are kept out of `Fs` so that implementers of that trait only need to provide the core
+/// operations; every `Fs` implementation gets these methods for free.
+///
+/// # Examples
+///
+/// ```
+/// extern crate io_providers;
+///
+/// use io_providers::{Fs, FsExt, TempFs};
+///
+/// fn main() {
+///     let mut fs = TempFs::new().unwrap();
+///     fs.touch("empty.txt").unwrap();
+///
+///     assert!(fs.metadata_opt("empty.txt").unwrap().is_some());
+///     assert!(fs.remove_file_if_exists("empty.txt").unwrap());
+///     assert!(fs.metadata_opt("empty.txt").unwrap().is_none());
+/// }
+/// ```
+pub trait FsExt: Fs {
+    /// Creates an empty file at `path` if it doesn't exist, otherwise updates its modification
+    /// time to the current time. The contents of an existing file are left untouched.
+    fn touch<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let file = self.open(path, OpenOptions::new().write(true).create(true))?;
+        file.set_modified(SystemTime::now())
+    }
+
+    /// Opens a file for reading like [`Fs::open()`](trait.Fs.html#tymethod.open), wrapped in a
+    /// `BufReader`.
+    fn open_buffered_read<P: AsRef<Path>>(&mut self, path: P) -> io::Result<BufReader<fs::File>> {
+        let file = self.open(path, OpenOptions::new().read(true))?;
+        Ok(BufReader::new(file))
+    }
+
+    /// Opens a file for writing like [`Fs::open()`](trait.Fs.html#tymethod.open), wrapped in a
+    /// `BufWriter`. The file is created if it doesn't exist, and truncated if it does.
+    ///
+    /// Data is only written to the file when the writer's buffer fills, it's flushed or it's
+    /// dropped; flush it explicitly to observe any errors.
+    fn open_buffered_write<P: AsRef<Path>>(&mut self, path: P) -> io::Result<BufWriter<fs::File>> {
+        let file = self.open(
+            path,
+            OpenOptions::new().write(true).create(true).truncate(true),
+        )?;
+        Ok(BufWriter::new(file))
+    }
+
+    /// Recursively copies the directory `from` and all of its contents to `to`, which must not
+    /// already exist.
+    ///
+    /// Symbolic links are followed, so the copy contains the files they point to rather than the
+    /// links themselves.
+    fn copy_dir_all<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.create_dir(to)?;
+
+        for entry in self.list_dir(from)? {
+            let (src, dst) = (from.join(entry.file_name()), to.join(entry.file_name()));
+
+            if self.stat(&src)?.is_dir() {
+                self.copy_dir_all(&src, &dst)?;
+            } else {
+                self.copy(&src, &dst)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renames a file or directory like [`Fs::rename()`](trait.Fs.html#tymethod.rename), first
+    /// creating any missing parent directories of `to`.
+    fn rename_create<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        if let Some(parent) = to.as_ref().parent() {
+            self.create_dir_all(parent)?;
+        }
+        self.rename(from, to)
+    }
+
+    /// Moves a file or directory like [`Fs::rename()`](trait.Fs.html#tymethod.rename), but if
+    /// `from` and `to` are on different filesystems, falls back to copying `from` (recursively,
+    /// for a directory) and then removing it.
+    ///
+    /// The fallback isn't atomic: if it fails part-way through, `to` may be left partially
+    /// written.
+    fn move_path<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        let result = self.rename(&from, &to);
+        move_after_rename(self, from.as_ref(), to.as_ref(), result)
+    }
+
+    /// Writes `contents` as the entire contents of a file like
+    /// [`Fs::write()`](trait.Fs.html#tymethod.write), but atomically: the file either keeps its
+    /// old contents or has all of the new ones, even if the write is interrupted.
+    ///
+    /// This works by writing to a temporary file alongside `path`, then renaming it over `path`.
+    fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let temp_path = temp_sibling(path, "tmp")?;
+
+        let result = self
+            .write(&temp_path, contents)
+            .and_then(|()| self.rename(&temp_path, path));
+        if result.is_err() {
+            let _ = self.remove_file(&temp_path);
+        }
+        result
+    }
+
+    /// Writes `contents` as the entire contents of a file like
+    /// [`Fs::write()`](trait.Fs.html#tymethod.write), unless the file already has exactly those
+    /// contents. Returns whether the file was written.
+    ///
+    /// Leaving an unchanged file alone preserves its modification time, so tools which compare
+    /// modification times (such as build systems) don't see a spurious change.
+    fn write_if_changed<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<bool> {
+        let (path, contents) = (path.as_ref(), contents.as_ref());
+        match self.read(path) {
+            Ok(ref existing) if existing[..] == contents[..] => return Ok(false),
+            Ok(_) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        self.write(path, contents).map(|()| true)
+    }
+
+    /// Classifies the contents of a file as text or binary by looking at its first few kilobytes,
+    /// using the same heuristic as git: a file containing a NUL byte is binary.
+    ///
+    /// A file which is otherwise invalid UTF-8 is also classified as binary. A multi-byte
+    /// character cut off at the end of the bytes looked at doesn't count as invalid.
+    fn sniff<P: AsRef<Path>>(&self, path: P) -> io::Result<ContentKind> {
+        let prefix = self.read_prefix(path, SNIFF_LEN)?;
+        if prefix.is_empty() {
+            return Ok(ContentKind::Empty);
+        }
+        if prefix.contains(&0) {
+            return Ok(ContentKind::Binary);
+        }
+        match str::from_utf8(&prefix) {
+            Ok(_) => Ok(ContentKind::Utf8Text),
+            Err(ref e) if e.error_len().is_none() && prefix.len() == SNIFF_LEN => {
+                Ok(ContentKind::Utf8Text)
+            }
+            Err(_) => Ok(ContentKind::Binary),
+        }
+    }
+
+    /// Reads the entire contents of a file into a string, replacing any invalid UTF-8 sequences
+    /// with `U+FFFD`.
+    fn read_to_string_lossy<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        Ok(String::from_utf8_lossy(&self.read(path)?).into_owned())
+    }
+
+    /// Reads the entire contents of a file into a string, decoding it from `encoding`.
+    ///
+    /// If the file starts with a byte order mark for UTF-8 or UTF-16, the encoding it identifies
+    /// is used instead of `encoding`. The byte order mark isn't included in the result.
+    ///
+    /// This requires the `encoding` feature.
+    #[cfg(feature = "encoding")]
+    fn read_to_string_encoding<P: AsRef<Path>>(
+        &self,
+        path: P,
+        encoding: Encoding,
+    ) -> io::Result<String> {
+        encoding.decode(&self.read(path)?)
+    }
+
+    /// Writes `contents` as the entire contents of a file like
+    /// [`Fs::write()`](trait.Fs.html#tymethod.write), encoded in `encoding`. UTF-16 is written
+    /// with a byte order mark, while UTF-8 isn't.
+    ///
+    /// This requires the `encoding` feature.
+    #[cfg(feature = "encoding")]
+    fn write_encoding<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        contents: &str,
+        encoding: Encoding,
+    ) -> io::Result<()> {
+        self.write(path, encoding.encode(contents))
+    }
+
+    /// Computes the SHA-256 digest of the contents of a file.
+    ///
+    /// The file is opened with [`Fs::open()`](trait.Fs.html#tymethod.open) and hashed in
+    /// fixed-size chunks. Providers which don't support `open()` have the contents read through
+    /// [`Fs::read()`](trait.Fs.html#tymethod.read) instead, holding the whole file in memory.
+    ///
+    /// This requires the `hash` feature.
+    #[cfg(feature = "hash")]
+    fn digest<P: AsRef<Path>>(&mut self, path: P) -> io::Result<[u8; 32]> {
+        let mut hasher = Sha256::new();
+        match self.open(&path, OpenOptions::new().read(true)) {
+            Ok(mut file) => {
+                io::copy(&mut file, &mut hasher)?;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Unsupported => hasher.update(self.read(path)?),
+            Err(e) => return Err(e),
+        }
+        Ok(hasher.finalize().into())
+    }
+
+    /// Returns whether two files have identical contents.
+    ///
+    /// Files of different lengths are reported as differing without reading either of them.
+    fn same_contents<P: AsRef<Path>, Q: AsRef<Path>>(&self, a: P, b: Q) -> io::Result<bool> {
+        if self.stat(a.as_ref())?.len() != self.stat(b.as_ref())?.len() {
+            return Ok(false);
+        }
+        Ok(self.read(a)? == self.read(b)?)
+    }
+
+    /// Returns the paths matching a glob `pattern`, in sorted order.
+    ///
+    /// Within a path component, `*` matches any sequence of characters and `?` matches any single
+    /// character. A component consisting only of `**` matches zero or more directories. Relative
+    /// patterns produce relative paths.
+    fn glob(&self, pattern: &str) -> io::Result<Vec<PathBuf>> {
+        let components: Vec<Component> = Path::new(pattern).components().collect();
+        let mut results = Vec::new();
+        glob_walk(self, PathBuf::new(), &components, &mut results)?;
+        results.sort();
+        results.dedup();
+        Ok(results)
+    }
+
+    /// Returns the paths of the subdirectories of the directory at `path`, in sorted order.
+    ///
+    /// Paths are relative to `path` as given, like those returned by
+    /// [`Fs::list_dir()`](trait.Fs.html#method.list_dir). Symbolic links to directories are
+    /// included.
+    fn read_dirs_only<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<PathBuf>> {
+        list_dir_filtered(self, path.as_ref(), Metadata::is_dir)
+    }
+
+    /// Returns the paths of the regular files in the directory at `path`, in sorted order.
+    ///
+    /// Paths are relative to `path` as given, like those returned by
+    /// [`Fs::list_dir()`](trait.Fs.html#method.list_dir). Symbolic links to regular files are
+    /// included.
+    fn read_files_only<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<PathBuf>> {
+        list_dir_filtered(self, path.as_ref(), Metadata::is_file)
+    }
+
+    /// Returns all of the entries in the directory tree under `path`, not including `path` itself.
+    ///
+    /// Entries are listed like [`Fs::list_dir()`](trait.Fs.html#method.list_dir), so their paths
+    /// are relative to `path` as given. Symbolic links are not followed; see
+    /// [`walk_dir_with()`](#method.walk_dir_with) to follow them.
+    fn walk_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<WalkDir> {
+        self.walk_dir_depth(path, usize::MAX)
+    }
+
+    /// Returns the entries in the directory tree under `path` like
+    /// [`walk_dir()`](#method.walk_dir), but only recursing `max_depth` levels into it: with a
+    /// `max_depth` of 0 only the immediate entries of `path` are returned, with 1 the entries of
+    /// its subdirectories are returned too, and so on.
+    fn walk_dir_depth<P: AsRef<Path>>(&self, path: P, max_depth: usize) -> io::Result<WalkDir> {
+        let mut entries = Vec::new();
+        walk(self, path.as_ref(), max_depth, None, &mut entries)?;
+        Ok(WalkDir::new(entries))
+    }
+
+    /// Returns all of the entries in the directory tree under `path` like
+    /// [`walk_dir()`](#method.walk_dir), but also descending into symbolic links to directories
+    /// if `follow_symlinks` is `true`.
+    ///
+    /// Links are still yielded as symbolic links, and the entries under them have paths through
+    /// the link. A link to a directory which is already being walked (such as one pointing back
+    /// up the tree) isn't descended into, so cycles can't make the walk go on forever. Broken
+    /// links are yielded but not descended into.
+    fn walk_dir_with<P: AsRef<Path>>(&self, path: P, follow_symlinks: bool) -> io::Result<WalkDir> {
+        let path = path.as_ref();
+        let mut entries = Vec::new();
+        if follow_symlinks {
+            let mut ancestors = vec![self.canonicalize(path)?];
+            walk(self, path, usize::MAX, Some(&mut ancestors), &mut entries)?;
+        } else {
+            walk(self, path, usize::MAX, None, &mut entries)?;
+        }
+        Ok(WalkDir::new(entries))
+    }
+
+    /// Returns the total size in bytes of all of the regular files in the directory tree under
+    /// `path`.
+    ///
+    /// Symbolic links are neither followed nor counted, so files outside of the tree (or a
+    /// sandbox) aren't included and no file is counted twice through a link.
+    fn dir_size<P: AsRef<Path>>(&self, path: P) -> io::Result<u64> {
+        let mut size = 0;
+        for entry in self.walk_dir(path)? {
+            if entry.file_type()? == FileKind::File {
+                size += self.stat(entry.path())?.len();
+            }
+        }
+        Ok(size)
+    }
+
+    /// Queries the metadata of `path` like [`Fs::metadata()`](trait.Fs.html#tymethod.metadata),
+    /// but returns `None` instead of an error if nothing exists at `path`.
+    fn metadata_opt<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<fs::Metadata>> {
+        match self.metadata(path) {
+            Ok(metadata) => Ok(Some(metadata)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Queries the metadata of `path`, following a symbolic link at `path` like
+    /// [`Fs::metadata()`](trait.Fs.html#tymethod.metadata) if `follow_symlinks` is `true`, or
+    /// describing the link itself like
+    /// [`Fs::symlink_metadata()`](trait.Fs.html#tymethod.symlink_metadata) otherwise.
+    ///
+    /// This is the flag-driven form of `stat` found in other APIs; it's named differently to avoid
+    /// clashing with [`Fs::stat()`](trait.Fs.html#method.stat).
+    fn metadata_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        follow_symlinks: bool,
+    ) -> io::Result<fs::Metadata> {
+        if follow_symlinks {
+            self.metadata(path)
+        } else {
+            self.symlink_metadata(path)
+        }
+    }
+
+    /// Removes a file from the filesystem, returning `false` instead of an error if it didn't
+    /// exist.
+    fn remove_file_if_exists<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        match self.remove_file(path) {
+            Ok(()) => Ok(true),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<T: Fs> FsExt for T {}
+
+/// The kind of contents a file has, as classified by
+/// [`FsExt::sniff()`](trait.FsExt.html#method.sniff).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentKind {
+    /// The file is empty.
+    Empty,
+    /// The file appears to contain UTF-8 text.
+    Utf8Text,
+    /// The file appears to contain binary data.
+    Binary,
+}
+
+/// The number of bytes at the start of a file which `sniff()` looks at, as git does.
+const SNIFF_LEN: usize = 8000;
+
+/// Completes `move_path()` given the result of attempting to rename `from` to `to`.
+fn move_after_rename<F: FsExt + ?Sized>(
+    fs: &mut F,
+    from: &Path,
+    to: &Path,
+    rename_result: io::Result<()>,
+) -> io::Result<()> {
+    match rename_result {
+        Err(ref e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            if fs.metadata(from)?.is_dir() {
+                fs.copy_dir_all(from, to)?;
+                fs.remove_dir_all(from)
+            } else {
+                fs.copy(from, to)?;
+                fs.remove_file(from)
+            }
+        }
+        result => result,
+    }
+}
+
+/// Lists the directory at `dir`, keeping the paths whose metadata (following symbolic links)
+/// satisfies `keep`. Entries which no longer exist, or are dangling links, are skipped.
+fn list_dir_filtered<F: Fs + ?Sized>(
+    fs: &F,
+    dir: &Path,
+    keep: fn(&Metadata) -> bool,
+) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs.list_dir(dir)? {
+        match fs.stat(entry.path()) {
+            Ok(ref metadata) if keep(metadata) => paths.push(entry.path().to_path_buf()),
+            Ok(_) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(paths)
+}
+
+/// Appends the entries of the tree under `dir` to `entries`, recursing at most `max_depth` levels.
+///
+/// Symbolic links to directories are followed if `ancestors` is given, in which case it holds the
+/// canonical paths of the directories currently being walked, to detect cycles.
+fn walk<F: Fs + ?Sized>(
+    fs: &F,
+    dir: &Path,
+    max_depth: usize,
+    mut ancestors: Option<&mut Vec<PathBuf>>,
+    entries: &mut Vec<DirEntry>,
+) -> io::Result<()> {
+    for entry in fs.list_dir(dir)? {
+        let descend = max_depth > 0
+            && match entry.file_type()? {
+                FileKind::Dir => true,
+                FileKind::Symlink if ancestors.is_some() => fs
+                    .stat(entry.path())
+                    .map(|metadata| metadata.is_dir())
+                    .unwrap_or(false),
+                _ => false,
+            };
+        let subdir = entry.path().to_path_buf();
+        entries.push(entry);
+        if !descend {
+            continue;
+        }
+
+        match ancestors {
+            Some(ref mut ancestors) => {
+                let canonical = fs.canonicalize(&subdir)?;
+                if ancestors.contains(&canonical) {
+                    continue;
+                }
+                ancestors.push(canonical);
+                walk(fs, &subdir, max_depth - 1, Some(ancestors), entries)?;
+                ancestors.pop();
+            }
+            None => walk(fs, &subdir, max_depth - 1, None, entries)?,
+        }
+    }
+    Ok(())
+}
+
+fn glob_walk<F: Fs + ?Sized>(
+    fs: &F,
+    base: PathBuf,
+    components: &[Component],
+    results: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let (first, rest) = match components.split_first() {
+        Some(split) => split,
+        None => {
+            if fs.exists(&base) {
+                results.push(base);
+            }
+            return Ok(());
+        }
+    };
+
+    let pattern = match *first {
+        Component::Normal(name) => name.to_string_lossy(),
+        _ => return glob_walk(fs, base.join(first.as_os_str()), rest, results),
+    };
+    if !pattern.contains(['*', '?']) {
+        return glob_walk(fs, base.join(&*pattern), rest, results);
+    }
+
+    let dir = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base.as_path()
+    };
+    let entries = match fs.list_dir(dir) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    if pattern == "**" {
+        glob_walk(fs, base.clone(), rest, results)?;
+    }
+    for entry in entries {
+        let path = base.join(entry.file_name());
+        if pattern == "**" {
+            if entry.file_type()? == FileKind::Dir {
+                glob_walk(fs, path, components, results)?;
+            }
+        } else if wildcard_match(&pattern, &entry.file_name().to_string_lossy()) {
+            glob_walk(fs, path, rest, results)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Matches `name` against a pattern containing `*` and `?` wildcards.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use std::io;
+    use std::path::Path;
+
+    use super::move_after_rename;
+    use fs::{Fs, TempFs};
+
+    #[test]
+    fn move_after_rename__crosses_devices__copies_then_removes() {
+        let mut fs = TempFs::new().unwrap();
+        fs.create_dir("dir").unwrap();
+        fs.write("dir/file.txt", "contents").unwrap();
+        let error = io::Error::from(io::ErrorKind::CrossesDevices);
+
+        move_after_rename(&mut fs, Path::new("dir"), Path::new("moved"), Err(error)).unwrap();
+
+        assert!(!fs.exists("dir"));
+        assert_eq!("contents", fs.read_to_string("moved/file.txt").unwrap());
+    }
+
+    #[test]
+    fn move_after_rename__other_error__returned_unchanged() {
+        let mut fs = TempFs::new().unwrap();
+        let error = io::Error::from(io::ErrorKind::PermissionDenied);
+
+        let result = move_after_rename(&mut fs, Path::new("a"), Path::new("b"), Err(error));
+
+        assert_eq!(io::ErrorKind::PermissionDenied, result.unwrap_err().kind());
+    }
+}
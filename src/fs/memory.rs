@@ -0,0 +1,769 @@
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use fs::{
+    copy_cancelled, file_too_large, link_dir_error, DirEntry, FileKind, Fs, FsEvent, FsOp,
+    Latencies, Lines, Metadata, OpenOptions, COPY_CHUNK_SIZE,
+};
+
+/// Provides access to a filesystem which exists only in memory.
+///
+/// The filesystem initially contains only an empty root directory, and relative paths are
+/// resolved against it. `..` components are resolved lexically.
+///
+/// Several handles may share the same filesystem: [`clone_handle()`](#method.clone_handle) returns
+/// a new handle to the same files, rather than an independent copy of them, so that a change
+/// made through one handle is visible through all of the others. This is useful when several
+/// components under test each need their own `Fs`.
+///
+/// Details to be aware of:
+///   * Symbolic links and permissions aren't supported.
+///   * Operations which return `std::fs` types ([`open()`], `open_reporting()`, [`metadata()`],
+///     [`read_dir()`], [`set_permissions()`] and [`symlink_metadata()`]) can't be simulated, so
+///     they fail with `ErrorKind::Unsupported`. Use [`stat()`] instead of [`metadata()`], and
+///     [`list_dir()`] instead of [`read_dir()`].
+///   * Modification times are only recorded for files.
+///
+/// [`open()`]: fs/trait.Fs.html#tymethod.open
+/// [`metadata()`]: fs/trait.Fs.html#tymethod.metadata
+/// [`read_dir()`]: fs/trait.Fs.html#tymethod.read_dir
+/// [`list_dir()`]: fs/trait.Fs.html#method.list_dir
+/// [`stat()`]: fs/trait.Fs.html#method.stat
+/// [`set_permissions()`]: fs/trait.Fs.html#tymethod.set_permissions
+/// [`symlink_metadata()`]: fs/trait.Fs.html#tymethod.symlink_metadata
+///
+/// ## Example
+///
+/// ```
+/// use io_providers::fs::{Fs, MemoryFs};
+///
+/// let mut writer = MemoryFs::new();
+/// let reader = writer.clone_handle();
+///
+/// writer.write("/greeting.txt", "hello").unwrap();
+/// assert_eq!("hello", reader.read_to_string("/greeting.txt").unwrap());
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    tree: Arc<Mutex<Tree>>,
+    latencies: Latencies,
+    events: Vec<FsEvent>,
+}
+
+impl MemoryFs {
+    /// Creates a new, empty `MemoryFs`.
+    pub fn new() -> MemoryFs {
+        Default::default()
+    }
+
+    /// Creates a new handle to the same filesystem as this one.
+    pub fn clone_handle(&self) -> MemoryFs {
+        MemoryFs {
+            tree: Arc::clone(&self.tree),
+            latencies: Latencies::default(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Sets a delay to wait for before each operation of the kind `op` made through this handle,
+    /// simulating a slow disk; `Duration::ZERO` removes the delay.
+    ///
+    /// Latencies aren't shared with other handles, including those created by
+    /// [`clone_handle()`](#method.clone_handle).
+    pub fn set_latency(&mut self, op: FsOp, delay: Duration) {
+        self.latencies.set(op, delay);
+    }
+
+    /// Returns the changes which have been made through this handle, in the order they were made.
+    ///
+    /// Changes made through other handles to the same filesystem aren't included.
+    pub fn operations(&self) -> &[FsEvent] {
+        &self.events
+    }
+
+    /// Locks the filesystem for the duration of an operation.
+    fn lock(&self) -> MutexGuard<'_, Tree> {
+        // A panic while the lock was held can't have left the tree inconsistent, since every
+        // operation validates before it modifies anything
+        self.tree.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// An entry in the filesystem.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Entry {
+    Dir,
+    /// A file, identified by the key of its contents in `Tree::files`.
+    File(u64),
+}
+
+/// The contents of a `MemoryFs`.
+#[derive(Debug)]
+struct Tree {
+    /// Every file and directory, keyed by normalized absolute path.
+    entries: BTreeMap<PathBuf, Entry>,
+    /// Every file. Hard links share the same `FileData`.
+    files: HashMap<u64, FileData>,
+    next_file: u64,
+}
+
+/// The contents of a file, and when they were last modified.
+#[derive(Debug)]
+struct FileData {
+    contents: Vec<u8>,
+    modified: SystemTime,
+}
+
+impl FileData {
+    fn new(contents: Vec<u8>) -> FileData {
+        FileData {
+            contents,
+            modified: SystemTime::now(),
+        }
+    }
+}
+
+impl Default for Tree {
+    fn default() -> Tree {
+        let mut entries = BTreeMap::new();
+        entries.insert(PathBuf::from("/"), Entry::Dir);
+        Tree {
+            entries,
+            files: HashMap::new(),
+            next_file: 0,
+        }
+    }
+}
+
+impl Tree {
+    /// Gets the entry at `path`, failing if there is none.
+    fn entry(&self, path: &Path) -> io::Result<Entry> {
+        self.entries
+            .get(path)
+            .cloned()
+            .ok_or_else(|| error(io::ErrorKind::NotFound, path))
+    }
+
+    /// Gets the key of the file at `path`, failing if it's missing or a directory.
+    fn file(&self, path: &Path) -> io::Result<u64> {
+        match self.entry(path)? {
+            Entry::File(id) => Ok(id),
+            Entry::Dir => Err(error(io::ErrorKind::IsADirectory, path)),
+        }
+    }
+
+    /// Fails unless the parent of `path` is an existing directory.
+    fn check_parent(&self, path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) => match self.entry(parent)? {
+                Entry::Dir => Ok(()),
+                Entry::File(_) => Err(error(io::ErrorKind::NotADirectory, parent)),
+            },
+            None => Err(error(io::ErrorKind::InvalidInput, path)),
+        }
+    }
+
+    /// Returns the paths of everything within the directory `path`, at any depth.
+    fn descendants(&self, path: &Path) -> Vec<PathBuf> {
+        self.entries
+            .keys()
+            .filter(|p| p.starts_with(path) && *p != path)
+            .cloned()
+            .collect()
+    }
+
+    /// Removes the entry at `path`, along with the file's contents if it was the last link to
+    /// them.
+    fn remove(&mut self, path: &Path) {
+        if let Some(Entry::File(id)) = self.entries.remove(path) {
+            if self.nlink(id) == 0 {
+                self.files.remove(&id);
+            }
+        }
+    }
+
+    /// Returns the number of entries which link to the file `id`.
+    fn nlink(&self, id: u64) -> u64 {
+        self.entries
+            .values()
+            .filter(|e| **e == Entry::File(id))
+            .count() as u64
+    }
+
+    /// Moves the entry at `from`, along with any descendants, to `to`.
+    fn move_entry(&mut self, from: &Path, to: &Path) {
+        let moved = self.take_entry(from);
+        self.put_entry(to, moved);
+    }
+
+    /// Removes the entry at `path` along with any descendants, returning them with paths relative
+    /// to `path`.
+    fn take_entry(&mut self, path: &Path) -> Vec<(PathBuf, Entry)> {
+        let mut taken = self.descendants(path);
+        taken.push(path.to_path_buf());
+        taken
+            .into_iter()
+            .map(|p| {
+                let entry = self.entries.remove(&p).unwrap();
+                (p.strip_prefix(path).unwrap().to_path_buf(), entry)
+            })
+            .collect()
+    }
+
+    /// Adds entries taken by `take_entry()` at `path`.
+    fn put_entry(&mut self, path: &Path, entries: Vec<(PathBuf, Entry)>) {
+        self.entries.extend(
+            entries
+                .into_iter()
+                .map(|(relative, entry)| (path.join(relative), entry)),
+        );
+    }
+
+    /// Sets the contents of the file at `path`, creating it if necessary.
+    fn write(&mut self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        match self.entries.get(path).cloned() {
+            Some(Entry::File(id)) => {
+                self.files.insert(id, FileData::new(contents));
+            }
+            Some(Entry::Dir) => return Err(error(io::ErrorKind::IsADirectory, path)),
+            None => {
+                self.check_parent(path)?;
+                let id = self.next_file;
+                self.next_file += 1;
+                self.files.insert(id, FileData::new(contents));
+                self.entries.insert(path.to_path_buf(), Entry::File(id));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Fs for MemoryFs {
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        _path: P,
+        _open_options: &OpenOptions,
+    ) -> io::Result<fs::File> {
+        Err(unsupported("open"))
+    }
+
+    fn open_reporting<P: AsRef<Path>>(
+        &mut self,
+        _path: P,
+        _open_options: &OpenOptions,
+    ) -> io::Result<(PathBuf, fs::File)> {
+        Err(unsupported("open_reporting"))
+    }
+
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        self.latencies.apply(FsOp::Metadata);
+        let path = normalize(path);
+        self.lock().entry(&path)?;
+        Ok(path)
+    }
+
+    fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<u64> {
+        self.latencies.apply(FsOp::Copy);
+        let (from, to) = (normalize(from), normalize(to));
+        let mut tree = self.lock();
+        let contents = tree.files[&tree.file(&from)?].contents.clone();
+        let len = contents.len() as u64;
+        tree.write(&to, contents)?;
+        drop(tree);
+        self.events.push(FsEvent::Write(to));
+        Ok(len)
+    }
+
+    fn copy_streaming<P: AsRef<Path>, Q: AsRef<Path>, F: FnMut(u64) -> bool>(
+        &mut self,
+        from: P,
+        to: Q,
+        mut on_progress: F,
+    ) -> io::Result<u64> {
+        self.latencies.apply(FsOp::Copy);
+        let (from, to) = (normalize(from), normalize(to));
+        let contents = {
+            let tree = self.lock();
+            tree.check_parent(&to)?;
+            tree.files[&tree.file(&from)?].contents.clone()
+        };
+
+        // The filesystem isn't locked while `on_progress` runs, so that it may use it, and the
+        // destination is only written once every chunk has been "copied"
+        let mut copied = 0;
+        for chunk in contents.chunks(COPY_CHUNK_SIZE) {
+            copied += chunk.len() as u64;
+            if !on_progress(copied) {
+                return Err(copy_cancelled());
+            }
+        }
+        self.lock().write(&to, contents)?;
+        self.events.push(FsEvent::Write(to));
+        Ok(copied)
+    }
+
+    fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.latencies.apply(FsOp::CreateDir);
+        let path = normalize(path);
+        let mut tree = self.lock();
+        if tree.entries.contains_key(&path) {
+            return Err(error(io::ErrorKind::AlreadyExists, &path));
+        }
+        tree.check_parent(&path)?;
+        tree.entries.insert(path.clone(), Entry::Dir);
+        drop(tree);
+        self.events.push(FsEvent::CreateDir(path));
+        Ok(())
+    }
+
+    fn ensure_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        self.latencies.apply(FsOp::CreateDir);
+        let path = normalize(path);
+        let mut tree = self.lock();
+        match tree.entries.get(&path) {
+            Some(Entry::Dir) => Ok(false),
+            Some(Entry::File(_)) => Err(error(io::ErrorKind::NotADirectory, &path)),
+            None => {
+                tree.check_parent(&path)?;
+                tree.entries.insert(path.clone(), Entry::Dir);
+                drop(tree);
+                self.events.push(FsEvent::CreateDir(path));
+                Ok(true)
+            }
+        }
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.latencies.apply(FsOp::CreateDir);
+        let path = normalize(path);
+        let mut tree = self.lock();
+        let mut missing = Vec::new();
+        for ancestor in path.ancestors() {
+            match tree.entries.get(ancestor) {
+                Some(Entry::Dir) => {}
+                Some(Entry::File(_)) => return Err(error(io::ErrorKind::AlreadyExists, ancestor)),
+                None => missing.push(ancestor.to_path_buf()),
+            }
+        }
+
+        // Nothing is created until every ancestor has been checked, so a file in the way leaves
+        // the tree unchanged
+        let mut created = Vec::new();
+        for dir in missing.into_iter().rev() {
+            tree.entries.insert(dir.clone(), Entry::Dir);
+            created.push(FsEvent::CreateDir(dir));
+        }
+        drop(tree);
+        self.events.extend(created);
+        Ok(())
+    }
+
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q) -> io::Result<()> {
+        self.latencies.apply(FsOp::Link);
+        let (src, dst) = (normalize(src), normalize(dst));
+        let mut tree = self.lock();
+        let id = match tree.entry(&src)? {
+            Entry::File(id) => id,
+            Entry::Dir => return Err(link_dir_error()),
+        };
+        if tree.entries.contains_key(&dst) {
+            return Err(error(io::ErrorKind::AlreadyExists, &dst));
+        }
+        tree.check_parent(&dst)?;
+        tree.entries.insert(dst.clone(), Entry::File(id));
+        drop(tree);
+        self.events.push(FsEvent::Link { src, dst });
+        Ok(())
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, _path: P) -> io::Result<fs::Metadata> {
+        Err(unsupported("metadata"))
+    }
+
+    fn stat<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        self.latencies.apply(FsOp::Metadata);
+        let path = normalize(path);
+        let tree = self.lock();
+        let (len, file_type, modified) = match tree.entry(&path)? {
+            Entry::Dir => (0, FileKind::Dir, None),
+            Entry::File(id) => {
+                let file = &tree.files[&id];
+                (
+                    file.contents.len() as u64,
+                    FileKind::File,
+                    Some(file.modified),
+                )
+            }
+        };
+        Ok(Metadata {
+            len,
+            file_type,
+            readonly: false,
+            modified,
+        })
+    }
+
+    #[cfg(unix)]
+    fn nlink<P: AsRef<Path>>(&self, path: P) -> io::Result<u64> {
+        self.latencies.apply(FsOp::Metadata);
+        let path = normalize(path);
+        let tree = self.lock();
+        match tree.entry(&path)? {
+            Entry::File(id) => Ok(tree.nlink(id)),
+            // Like on Unix, a directory is linked to by its parent, itself and its subdirectories
+            Entry::Dir => Ok(2 + tree
+                .descendants(&path)
+                .iter()
+                .filter(|p| p.parent() == Some(&path) && tree.entries[*p] == Entry::Dir)
+                .count() as u64),
+        }
+    }
+
+    fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        self.latencies.apply(FsOp::Read);
+        let path = normalize(path);
+        let tree = self.lock();
+        Ok(tree.files[&tree.file(&path)?].contents.clone())
+    }
+
+    fn read_prefix<P: AsRef<Path>>(&self, path: P, n: usize) -> io::Result<Vec<u8>> {
+        self.latencies.apply(FsOp::Read);
+        let path = normalize(path);
+        let tree = self.lock();
+        let contents = &tree.files[&tree.file(&path)?].contents;
+        Ok(contents[..n.min(contents.len())].to_vec())
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, _path: P) -> io::Result<fs::ReadDir> {
+        Err(unsupported("read_dir"))
+    }
+
+    fn list_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<DirEntry>> {
+        self.latencies.apply(FsOp::ReadDir);
+        let dir = normalize(&path);
+        let tree = self.lock();
+        if tree.entry(&dir)? != Entry::Dir {
+            return Err(error(io::ErrorKind::NotADirectory, &dir));
+        }
+
+        // Entries are kept sorted by path, so the children are already sorted by name
+        Ok(tree
+            .entries
+            .range(dir.clone()..)
+            .skip(1)
+            .take_while(|&(p, _)| p.starts_with(&dir))
+            .filter(|&(p, _)| p.parent() == Some(&dir))
+            .map(|(p, entry)| {
+                let file_type = match *entry {
+                    Entry::Dir => FileKind::Dir,
+                    Entry::File(_) => FileKind::File,
+                };
+                DirEntry::new(
+                    path.as_ref(),
+                    p.file_name().unwrap().to_os_string(),
+                    file_type,
+                )
+            })
+            .collect())
+    }
+
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        self.latencies.apply(FsOp::Read);
+        // Symbolic links aren't supported, so nothing is one
+        let path = normalize(path);
+        self.lock().entry(&path)?;
+        Err(error(io::ErrorKind::InvalidInput, &path))
+    }
+
+    fn read_lines<P: AsRef<Path>>(&self, path: P) -> io::Result<Lines> {
+        Ok(Lines::new(io::Cursor::new(self.read(path)?)))
+    }
+
+    fn read_lines_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_line_bytes: usize,
+    ) -> io::Result<Lines> {
+        Ok(Lines::limited(
+            io::Cursor::new(self.read(path)?),
+            max_line_bytes,
+        ))
+    }
+
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn read_to_string_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_bytes: usize,
+    ) -> io::Result<String> {
+        self.latencies.apply(FsOp::Read);
+        let path = normalize(path);
+        let contents = {
+            let tree = self.lock();
+            let contents = &tree.files[&tree.file(&path)?].contents;
+            if contents.len() > max_bytes {
+                return Err(file_too_large(max_bytes));
+            }
+            contents.clone()
+        };
+        String::from_utf8(contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn remove_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.latencies.apply(FsOp::Remove);
+        let path = normalize(path);
+        let mut tree = self.lock();
+        match tree.entry(&path)? {
+            Entry::File(_) => Err(error(io::ErrorKind::NotADirectory, &path)),
+            Entry::Dir if !tree.descendants(&path).is_empty() => {
+                Err(error(io::ErrorKind::DirectoryNotEmpty, &path))
+            }
+            Entry::Dir if path.parent().is_none() => {
+                Err(error(io::ErrorKind::PermissionDenied, &path))
+            }
+            Entry::Dir => {
+                tree.remove(&path);
+                drop(tree);
+                self.events.push(FsEvent::Remove(path));
+                Ok(())
+            }
+        }
+    }
+
+    fn remove_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.latencies.apply(FsOp::Remove);
+        let path = normalize(path);
+        let mut tree = self.lock();
+        match tree.entry(&path)? {
+            Entry::File(_) => Err(error(io::ErrorKind::NotADirectory, &path)),
+            Entry::Dir if path.parent().is_none() => {
+                Err(error(io::ErrorKind::PermissionDenied, &path))
+            }
+            Entry::Dir => {
+                for descendant in tree.descendants(&path) {
+                    tree.remove(&descendant);
+                }
+                tree.remove(&path);
+                drop(tree);
+                self.events.push(FsEvent::Remove(path));
+                Ok(())
+            }
+        }
+    }
+
+    fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.latencies.apply(FsOp::Remove);
+        let path = normalize(path);
+        let mut tree = self.lock();
+        tree.file(&path)?;
+        tree.remove(&path);
+        drop(tree);
+        self.events.push(FsEvent::Remove(path));
+        Ok(())
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        self.latencies.apply(FsOp::Rename);
+        let (from, to) = (normalize(from), normalize(to));
+        let mut tree = self.lock();
+        let entry = tree.entry(&from)?;
+        if from == to {
+            return Ok(());
+        }
+        if from.parent().is_none() || to.starts_with(&from) {
+            return Err(error(io::ErrorKind::InvalidInput, &to));
+        }
+        tree.check_parent(&to)?;
+
+        match (entry, tree.entries.get(&to).cloned()) {
+            (_, None) => {}
+            (Entry::File(_), Some(Entry::File(_))) => tree.remove(&to),
+            (Entry::File(_), Some(Entry::Dir)) => {
+                return Err(error(io::ErrorKind::IsADirectory, &to));
+            }
+            (Entry::Dir, Some(Entry::File(_))) => {
+                return Err(error(io::ErrorKind::NotADirectory, &to));
+            }
+            (Entry::Dir, Some(Entry::Dir)) => {
+                if !tree.descendants(&to).is_empty() {
+                    return Err(error(io::ErrorKind::DirectoryNotEmpty, &to));
+                }
+                tree.remove(&to);
+            }
+        }
+        tree.move_entry(&from, &to);
+        drop(tree);
+        self.events.push(FsEvent::Rename { from, to });
+        Ok(())
+    }
+
+    fn swap<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, a: P, b: Q) -> io::Result<()> {
+        self.latencies.apply(FsOp::Rename);
+        let (a, b) = (normalize(a), normalize(b));
+        let mut tree = self.lock();
+        tree.entry(&a)?;
+        tree.entry(&b)?;
+        if a == b {
+            return Ok(());
+        }
+        if a.parent().is_none() || b.parent().is_none() || a.starts_with(&b) || b.starts_with(&a) {
+            return Err(error(io::ErrorKind::InvalidInput, &b));
+        }
+
+        let (taken_a, taken_b) = (tree.take_entry(&a), tree.take_entry(&b));
+        tree.put_entry(&a, taken_b);
+        tree.put_entry(&b, taken_a);
+        drop(tree);
+        self.events.push(FsEvent::Swap { a, b });
+        Ok(())
+    }
+
+    fn set_permissions<P: AsRef<Path>>(
+        &mut self,
+        _path: P,
+        _perm: fs::Permissions,
+    ) -> io::Result<()> {
+        Err(unsupported("set_permissions"))
+    }
+
+    fn symlink_metadata<P: AsRef<Path>>(&self, _path: P) -> io::Result<fs::Metadata> {
+        Err(unsupported("symlink_metadata"))
+    }
+
+    fn truncate<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.latencies.apply(FsOp::Write);
+        let path = normalize(path);
+        let mut tree = self.lock();
+        let id = tree.file(&path)?;
+        let file = tree.files.get_mut(&id).unwrap();
+        file.contents.clear();
+        file.modified = SystemTime::now();
+        drop(tree);
+        self.events.push(FsEvent::Write(path));
+        Ok(())
+    }
+
+    fn append_locked<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<()> {
+        self.latencies.apply(FsOp::Write);
+        let path = normalize(path);
+        let mut tree = self.lock();
+        if tree.entries.contains_key(&path) {
+            let id = tree.file(&path)?;
+            let file = tree.files.get_mut(&id).unwrap();
+            file.contents.extend_from_slice(contents.as_ref());
+            file.modified = SystemTime::now();
+        } else {
+            tree.write(&path, contents.as_ref().to_vec())?;
+        }
+        drop(tree);
+        self.events.push(FsEvent::Write(path));
+        Ok(())
+    }
+
+    fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&mut self, path: P, contents: C) -> io::Result<()> {
+        self.latencies.apply(FsOp::Write);
+        let path = normalize(path);
+        self.lock().write(&path, contents.as_ref().to_vec())?;
+        self.events.push(FsEvent::Write(path));
+        Ok(())
+    }
+
+    fn write_counted<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<u64> {
+        let len = contents.as_ref().len() as u64;
+        self.write(path, contents).map(|()| len)
+    }
+
+    fn write_at<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        offset: u64,
+        data: C,
+    ) -> io::Result<()> {
+        self.latencies.apply(FsOp::Write);
+        let path = normalize(path);
+        let data = data.as_ref();
+        let too_large = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "write would extend the file beyond the maximum size",
+            )
+        };
+        let start = usize::try_from(offset).map_err(|_| too_large())?;
+        let end = start.checked_add(data.len()).ok_or_else(too_large)?;
+        let mut tree = self.lock();
+        let id = tree.file(&path)?;
+        let file = tree.files.get_mut(&id).unwrap();
+
+        if file.contents.len() < end {
+            file.contents.resize(end, 0);
+        }
+        file.contents[start..end].copy_from_slice(data);
+        file.modified = SystemTime::now();
+        drop(tree);
+        self.events.push(FsEvent::Write(path));
+        Ok(())
+    }
+
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.latencies.apply(FsOp::Metadata);
+        let path = normalize(path);
+        self.lock().entries.contains_key(&path)
+    }
+
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        Ok(self.exists(path))
+    }
+
+    fn kind<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<FileKind>> {
+        self.latencies.apply(FsOp::Metadata);
+        match self.lock().entry(&normalize(path)) {
+            Ok(Entry::Dir) => Ok(Some(FileKind::Dir)),
+            Ok(Entry::File(_)) => Ok(Some(FileKind::File)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Converts `path` to an absolute path without any `.` or `..` components.
+fn normalize<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut result = PathBuf::from("/");
+    for component in path.as_ref().components() {
+        match component {
+            Component::Normal(name) => result.push(name),
+            Component::ParentDir => {
+                result.pop();
+            }
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+        }
+    }
+    result
+}
+
+fn error(kind: io::ErrorKind, path: &Path) -> io::Error {
+    io::Error::new(kind, format!("{}: {}", path.display(), kind))
+}
+
+fn unsupported(operation: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("MemoryFs doesn't support {}()", operation),
+    )
+}
@@ -1,14 +1,40 @@
 //! Defines traits and implementations for filesystem manipulation operations.
 
+mod any;
+#[cfg(feature = "zip")]
+mod archive;
+mod dry_run;
+#[cfg(feature = "encoding")]
+mod encoding;
+mod ext;
+mod memory;
 mod native;
 mod temp;
+mod transfer;
 
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
 use std::fs;
 use std::io;
-use std::path::{Path, PathBuf};
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use std::vec;
 
+pub use self::any::AnyFs;
+#[cfg(feature = "zip")]
+pub use self::archive::ArchiveFs;
+pub use self::dry_run::DryRunFs;
+#[cfg(feature = "encoding")]
+pub use self::encoding::Encoding;
+pub use self::ext::{ContentKind, FsExt};
+pub use self::memory::MemoryFs;
 pub use self::native::NativeFs;
-pub use self::temp::TempFs;
+pub use self::temp::{SandboxFile, TempFs};
+pub use self::transfer::{transfer, transfer_all};
 
 /// Options and flags which can be used to configure how a file is opened.
 ///
@@ -21,6 +47,10 @@ pub struct OpenOptions {
     truncate: bool,
     create: bool,
     create_new: bool,
+    #[cfg(windows)]
+    share_mode: Option<u32>,
+    #[cfg(windows)]
+    access_mode: Option<u32>,
 }
 
 impl OpenOptions {
@@ -101,6 +131,47 @@ impl OpenOptions {
         self
     }
 
+    /// Sets the sharing mode, which controls what other handles may do with the file while it's
+    /// open (a combination of the Windows `FILE_SHARE_*` flags).
+    ///
+    /// This is only available on Windows, and only affects files opened on a real filesystem.
+    ///
+    /// See [std::os::windows::fs::OpenOptionsExt](https://doc.rust-lang.org/std/os/windows/fs/trait.OpenOptionsExt.html#tymethod.share_mode)
+    /// for more information.
+    #[cfg(windows)]
+    pub fn share_mode(&mut self, share_mode: u32) -> &mut OpenOptions {
+        self.share_mode = Some(share_mode);
+        self
+    }
+
+    /// Overrides the desired access rights which the file is opened with (a combination of the
+    /// Windows `GENERIC_*` and `FILE_*` access flags), instead of deriving them from the other
+    /// options.
+    ///
+    /// This is only available on Windows, and only affects files opened on a real filesystem.
+    ///
+    /// See [std::os::windows::fs::OpenOptionsExt](https://doc.rust-lang.org/std/os/windows/fs/trait.OpenOptionsExt.html#tymethod.access_mode)
+    /// for more information.
+    #[cfg(windows)]
+    pub fn access_mode(&mut self, access_mode: u32) -> &mut OpenOptions {
+        self.access_mode = Some(access_mode);
+        self
+    }
+
+    /// Returns whether a file opened with these options can't be modified through its handle.
+    ///
+    /// Explicit Windows access rights could grant write access whatever the other options are, so
+    /// any options which set them count as not read-only.
+    fn is_read_only(&self) -> bool {
+        #[cfg(windows)]
+        {
+            if self.access_mode.is_some() {
+                return false;
+            }
+        }
+        !(self.write || self.append || self.truncate || self.create || self.create_new)
+    }
+
     fn as_std(&self) -> fs::OpenOptions {
         let mut open_options = fs::OpenOptions::new();
         open_options
@@ -110,10 +181,761 @@ impl OpenOptions {
             .truncate(self.truncate)
             .create(self.create)
             .create_new(self.create_new);
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+            if let Some(share_mode) = self.share_mode {
+                open_options.share_mode(share_mode);
+            }
+            if let Some(access_mode) = self.access_mode {
+                open_options.access_mode(access_mode);
+            }
+        }
         open_options
     }
 }
 
+/// An iterator over the lines of a file, returned by
+/// [`Fs::read_lines()`](trait.Fs.html#method.read_lines) and
+/// [`Fs::read_lines_limited()`](trait.Fs.html#method.read_lines_limited).
+///
+/// Each line is yielded without its trailing newline (`\n` or `\r\n`).
+pub struct Lines {
+    reader: Box<dyn io::BufRead + Send>,
+    max_line_bytes: Option<usize>,
+}
+
+impl Lines {
+    fn new<R: io::Read + Send + 'static>(reader: R) -> Lines {
+        Lines {
+            reader: Box::new(io::BufReader::new(reader)),
+            max_line_bytes: None,
+        }
+    }
+
+    fn limited<R: io::Read + Send + 'static>(reader: R, max_line_bytes: usize) -> Lines {
+        Lines {
+            max_line_bytes: Some(max_line_bytes),
+            ..Lines::new(reader)
+        }
+    }
+
+    /// Reads the next line like `BufRead::lines()` does.
+    fn next_unlimited(&mut self) -> Option<io::Result<String>> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                Some(Ok(line))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    /// Reads the next line, keeping at most `max_line_bytes` of it (plus a `\r`) in memory; the
+    /// rest of a longer line is skipped, and an error returned in its place.
+    fn next_limited(&mut self, max_line_bytes: usize) -> Option<io::Result<String>> {
+        let mut line = Vec::new();
+        let (mut read_any, mut too_long) = (false, false);
+        loop {
+            let (used, done) = {
+                let available = match self.reader.fill_buf() {
+                    Ok(available) => available,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Some(Err(e)),
+                };
+                let (chunk, used, done) = match available.iter().position(|&b| b == b'\n') {
+                    Some(i) => (&available[..i], i + 1, true),
+                    None => (available, available.len(), available.is_empty()),
+                };
+                if line.len() + chunk.len() > max_line_bytes.saturating_add(1) {
+                    too_long = true;
+                    line.clear();
+                } else if !too_long {
+                    line.extend_from_slice(chunk);
+                }
+                (used, done)
+            };
+            self.reader.consume(used);
+            read_any |= used > 0;
+            if done {
+                break;
+            }
+        }
+
+        if !read_any {
+            return None;
+        }
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+        if too_long || line.len() > max_line_bytes {
+            return Some(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line longer than {} bytes", max_line_bytes),
+            )));
+        }
+        Some(String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+}
+
+impl Iterator for Lines {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        match self.max_line_bytes {
+            Some(max_line_bytes) => self.next_limited(max_line_bytes),
+            None => self.next_unlimited(),
+        }
+    }
+}
+
+/// The type of a file, as reported by
+/// [`DirEntry::file_type()`](struct.DirEntry.html#method.file_type).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    /// A regular file.
+    File,
+    /// A directory.
+    Dir,
+    /// A symbolic link.
+    Symlink,
+    /// Anything else, such as a device, FIFO or socket.
+    Other,
+}
+
+impl From<fs::FileType> for FileKind {
+    fn from(file_type: fs::FileType) -> FileKind {
+        if file_type.is_symlink() {
+            FileKind::Symlink
+        } else if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        }
+    }
+}
+
+/// Metadata about a file, returned by [`Fs::stat()`](trait.Fs.html#method.stat).
+///
+/// Unlike `std::fs::Metadata`, this can be produced by any `Fs` implementation, not just those
+/// backed by the real filesystem.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    len: u64,
+    file_type: FileKind,
+    readonly: bool,
+    modified: Option<SystemTime>,
+}
+
+impl Metadata {
+    /// Returns the size of the file, in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns whether the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the type of the file.
+    pub fn file_type(&self) -> FileKind {
+        self.file_type
+    }
+
+    /// Returns whether this is the metadata of a directory.
+    pub fn is_dir(&self) -> bool {
+        self.file_type == FileKind::Dir
+    }
+
+    /// Returns whether this is the metadata of a regular file.
+    pub fn is_file(&self) -> bool {
+        self.file_type == FileKind::File
+    }
+
+    /// Returns whether the file is read-only.
+    pub fn readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Returns the time the file was last modified, if known.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+}
+
+impl From<fs::Metadata> for Metadata {
+    fn from(metadata: fs::Metadata) -> Metadata {
+        Metadata {
+            len: metadata.len(),
+            file_type: FileKind::from(metadata.file_type()),
+            readonly: metadata.permissions().readonly(),
+            modified: metadata.modified().ok(),
+        }
+    }
+}
+
+/// An entry in a directory, returned by [`Fs::list_dir()`](trait.Fs.html#method.list_dir).
+#[derive(Debug)]
+pub struct DirEntry {
+    path: PathBuf,
+    file_name: OsString,
+    file_type: LazyFileKind,
+}
+
+/// The type of a directory entry, which is only fetched from the OS when it's first needed.
+#[derive(Debug)]
+enum LazyFileKind {
+    Known(FileKind),
+    Native(Box<fs::DirEntry>),
+}
+
+impl DirEntry {
+    /// Creates a `DirEntry` for `name` in the directory `dir`, whose type is known.
+    fn new(dir: &Path, file_name: OsString, file_type: FileKind) -> DirEntry {
+        DirEntry {
+            path: dir.join(&file_name),
+            file_name,
+            file_type: LazyFileKind::Known(file_type),
+        }
+    }
+
+    /// Returns the path of this entry: the path of its directory, as given to `list_dir()`, joined
+    /// with its file name.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the file name of this entry.
+    pub fn file_name(&self) -> &OsStr {
+        &self.file_name
+    }
+
+    /// Returns the type of this entry. Symbolic links are not followed.
+    pub fn file_type(&self) -> io::Result<FileKind> {
+        match self.file_type {
+            LazyFileKind::Known(file_type) => Ok(file_type),
+            LazyFileKind::Native(ref entry) => entry.file_type().map(FileKind::from),
+        }
+    }
+}
+
+/// An iterator over the entries of a directory tree, returned by
+/// [`FsExt::walk_dir()`](trait.FsExt.html#method.walk_dir).
+///
+/// Entries are yielded in depth-first order, with each directory's entries sorted by name and each
+/// subdirectory yielded before its contents.
+#[derive(Debug)]
+pub struct WalkDir {
+    inner: vec::IntoIter<DirEntry>,
+}
+
+impl WalkDir {
+    fn new(entries: Vec<DirEntry>) -> WalkDir {
+        WalkDir {
+            inner: entries.into_iter(),
+        }
+    }
+}
+
+impl Iterator for WalkDir {
+    type Item = DirEntry;
+
+    fn next(&mut self) -> Option<DirEntry> {
+        self.inner.next()
+    }
+}
+
+/// Lists the entries of the directory `dir` on the host, giving them paths within `display_dir`.
+fn list_dir_at(dir: &Path, display_dir: &Path) -> io::Result<Vec<DirEntry>> {
+    sorted_dir_entries(fs::read_dir(dir)?, display_dir)
+}
+
+/// Collects the entries read by `read_dir`, sorted by file name and given paths within
+/// `display_dir`.
+fn sorted_dir_entries(read_dir: fs::ReadDir, display_dir: &Path) -> io::Result<Vec<DirEntry>> {
+    let mut entries = read_dir
+        .map(|entry| {
+            entry.map(|entry| DirEntry {
+                path: display_dir.join(entry.file_name()),
+                file_name: entry.file_name(),
+                file_type: LazyFileKind::Native(Box::new(entry)),
+            })
+        })
+        .collect::<io::Result<Vec<DirEntry>>>()?;
+    entries.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(entries)
+}
+
+/// A kind of filesystem operation, used to configure simulated latency with
+/// [`TempFs::set_latency()`](struct.TempFs.html#method.set_latency) and
+/// [`MemoryFs::set_latency()`](struct.MemoryFs.html#method.set_latency).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FsOp {
+    /// `open()`.
+    Open,
+    /// `read()`, `read_prefix()`, `read_lines()`, `read_lines_limited()`, `read_link()`,
+    /// `read_to_string()` and `read_to_string_limited()`.
+    Read,
+    /// `write()`, `write_at()` and `truncate()`.
+    Write,
+    /// `copy()`.
+    Copy,
+    /// `create_dir()`, `create_dir_all()` and `ensure_dir()`.
+    CreateDir,
+    /// `hard_link()`.
+    Link,
+    /// `metadata()`, `stat()`, `symlink_metadata()`, `nlink()`, `exists()`, `try_exists()`,
+    /// `kind()` and `canonicalize()`.
+    Metadata,
+    /// `read_dir()` and `list_dir()`.
+    ReadDir,
+    /// `remove_dir()`, `remove_dir_all()` and `remove_file()`.
+    Remove,
+    /// `rename()` and `swap()`.
+    Rename,
+    /// `set_permissions()`.
+    SetPermissions,
+}
+
+/// A change made to a simulated filesystem, as recorded by
+/// [`TempFs::operations()`](struct.TempFs.html#method.operations) and
+/// [`MemoryFs::operations()`](struct.MemoryFs.html#method.operations).
+///
+/// Paths are absolute paths within the simulated filesystem.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FsEvent {
+    /// A directory was created.
+    CreateDir(PathBuf),
+    /// A file's contents were written, creating it if it didn't exist. Recorded by `write()`,
+    /// `write_at()`, `truncate()`, `open()` when it creates the file and, for the destination,
+    /// `copy()` and `copy_streaming()`.
+    Write(PathBuf),
+    /// A hard link to `src` was created at `dst`.
+    Link {
+        /// The existing file.
+        src: PathBuf,
+        /// The new link.
+        dst: PathBuf,
+    },
+    /// A file or directory (including its contents) was removed.
+    Remove(PathBuf),
+    /// A file or directory was renamed.
+    Rename {
+        /// The old path.
+        from: PathBuf,
+        /// The new path.
+        to: PathBuf,
+    },
+    /// Two files or directories were swapped.
+    Swap {
+        /// The first path.
+        a: PathBuf,
+        /// The second path.
+        b: PathBuf,
+    },
+    /// The permissions of a file or directory were changed.
+    SetPermissions(PathBuf),
+}
+
+/// Simulated latencies of filesystem operations.
+#[derive(Debug, Default)]
+struct Latencies {
+    delays: HashMap<FsOp, Duration>,
+}
+
+impl Latencies {
+    /// Sets the latency of `op`; a zero `delay` removes it.
+    fn set(&mut self, op: FsOp, delay: Duration) {
+        if delay == Duration::ZERO {
+            self.delays.remove(&op);
+        } else {
+            self.delays.insert(op, delay);
+        }
+    }
+
+    /// Sleeps for the latency of `op`, if any.
+    fn apply(&self, op: FsOp) {
+        if let Some(&delay) = self.delays.get(&op) {
+            thread::sleep(delay);
+        }
+    }
+}
+
+/// Reads up to `n` bytes from the start of the host file at `path`.
+fn read_prefix_at(path: &Path, n: usize) -> io::Result<Vec<u8>> {
+    let mut prefix = Vec::new();
+    fs::File::open(path)?
+        .take(n as u64)
+        .read_to_end(&mut prefix)?;
+    Ok(prefix)
+}
+
+/// Reads the file at `path` into a string, failing if it's longer than `max_bytes`.
+///
+/// The file's size is checked up front where the metadata reports it, but since some files (such
+/// as those in `/proc`) report a size of zero, the read itself is also capped.
+fn read_to_string_limited_at(path: &Path, max_bytes: usize) -> io::Result<String> {
+    let file = fs::File::open(path)?;
+    let metadata = file.metadata()?;
+    if metadata.is_file() && metadata.len() > max_bytes as u64 {
+        return Err(file_too_large(max_bytes));
+    }
+
+    let mut contents = String::new();
+    file.take((max_bytes as u64).saturating_add(1))
+        .read_to_string(&mut contents)?;
+    if contents.len() > max_bytes {
+        return Err(file_too_large(max_bytes));
+    }
+    Ok(contents)
+}
+
+/// Returns the error for a file which is longer than the `max_bytes` it may be read with.
+fn file_too_large(max_bytes: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("file is larger than {} bytes", max_bytes),
+    )
+}
+
+/// The number of symbolic links which the default `Fs::canonicalize()` follows before giving up, as
+/// on Linux.
+const MAX_SYMLINKS: usize = 40;
+
+/// The size of the chunks copied by `Fs::copy_streaming()`.
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Returns the error for a copy which was cancelled by its progress callback.
+fn copy_cancelled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "copy cancelled")
+}
+
+/// Copies the host file `from` to `to` in chunks, through a temporary file which only replaces `to`
+/// once the copy has succeeded.
+fn copy_streaming_at<F: FnMut(u64) -> bool>(
+    from: &Path,
+    to: &Path,
+    on_progress: F,
+) -> io::Result<u64> {
+    let mut reader = fs::File::open(from)?;
+    let permissions = reader.metadata()?.permissions();
+    let temp = temp_sibling(to, "copy-tmp")?;
+    let mut writer = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp)?;
+
+    let result = copy_chunks(&mut reader, &mut writer, on_progress).and_then(|copied| {
+        writer.set_permissions(permissions)?;
+        Ok(copied)
+    });
+    // The temporary file must be closed before it can be renamed on Windows
+    drop(writer);
+    let result = result.and_then(|copied| {
+        fs::rename(&temp, to)?;
+        Ok(copied)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Copies everything from `reader` to `writer` in chunks, calling `on_progress` with the total
+/// number of bytes copied after each one and failing if it returns `false`.
+fn copy_chunks<R: Read, W: Write, F: FnMut(u64) -> bool>(
+    reader: &mut R,
+    writer: &mut W,
+    mut on_progress: F,
+) -> io::Result<u64> {
+    let mut buf = vec![0; COPY_CHUNK_SIZE];
+    let mut copied = 0;
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(len) => len,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..len])?;
+        copied += len as u64;
+        if !on_progress(copied) {
+            return Err(copy_cancelled());
+        }
+    }
+}
+
+/// Creates a hard link to the host file `src` at `dst`, first checking that `src` isn't a directory
+/// so that the error is the same on every platform.
+fn hard_link_at(src: &Path, dst: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(src)?.is_dir() {
+        return Err(link_dir_error());
+    }
+    fs::hard_link(src, dst)
+}
+
+/// Returns the error for an attempt to hard link a directory.
+fn link_dir_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "directories can't be hard-linked",
+    )
+}
+
+/// Truncates the existing file at `path` to be empty.
+fn truncate_at(path: &Path) -> io::Result<()> {
+    fs::OpenOptions::new().write(true).open(path)?.set_len(0)
+}
+
+/// Overwrites part of the existing file at `path` with `data`, starting at `offset`.
+fn write_at_path(path: &Path, offset: u64, data: &[u8]) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new().read(true).write(true).open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(data)
+}
+
+/// Writes `contents` as the entire contents of the file at `path` (creating it if necessary),
+/// returning the number of bytes written.
+fn write_counted_at(path: &Path, contents: &[u8]) -> io::Result<u64> {
+    let mut file = fs::File::create(path)?;
+    let mut written = 0;
+    while written < contents.len() {
+        match file.write(&contents[written..]) {
+            Ok(0) => {
+                let e = io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer");
+                return Err(partial_write_error(written, contents.len(), e));
+            }
+            Ok(n) => written += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(partial_write_error(written, contents.len(), e)),
+        }
+    }
+    Ok(written as u64)
+}
+
+/// Adds how many bytes of a write had been made to the message of the error which interrupted it.
+fn partial_write_error(written: usize, total: usize, e: io::Error) -> io::Error {
+    io::Error::new(
+        e.kind(),
+        format!("wrote {} of {} bytes: {}", written, total, e),
+    )
+}
+
+/// Appends `contents` to the file at `path` (creating it if necessary) while holding an exclusive
+/// lock on it. The lock is released when the file is closed.
+fn append_locked_at(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)?;
+    lock_exclusive(&file)?;
+    file.write_all(contents)
+}
+
+/// Takes an exclusive advisory lock on `file`, waiting until it's available. The lock is released
+/// when the file is closed.
+///
+/// Files can only be locked on Unix and Windows; elsewhere this does nothing.
+#[cfg(unix)]
+fn lock_exclusive(file: &fs::File) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+            return Ok(());
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+}
+
+#[cfg(windows)]
+fn lock_exclusive(file: &fs::File) -> io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+
+    let mut overlapped = kernel32::Overlapped {
+        internal: 0,
+        internal_high: 0,
+        offset: 0,
+        offset_high: 0,
+        event: std::ptr::null_mut(),
+    };
+    let locked = unsafe {
+        kernel32::LockFileEx(
+            file.as_raw_handle(),
+            kernel32::LOCKFILE_EXCLUSIVE_LOCK,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if locked == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lock_exclusive(_file: &fs::File) -> io::Result<()> {
+    Ok(())
+}
+
+/// The parts of the Windows file locking API which are used here, declared by hand rather than
+/// adding a dependency for them.
+#[cfg(windows)]
+#[allow(non_snake_case)]
+mod kernel32 {
+    use std::os::windows::io::RawHandle;
+
+    pub const LOCKFILE_EXCLUSIVE_LOCK: u32 = 0x0002;
+
+    #[repr(C)]
+    pub struct Overlapped {
+        pub internal: usize,
+        pub internal_high: usize,
+        pub offset: u32,
+        pub offset_high: u32,
+        pub event: RawHandle,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn LockFileEx(
+            file: RawHandle,
+            flags: u32,
+            reserved: u32,
+            bytes_to_lock_low: u32,
+            bytes_to_lock_high: u32,
+            overlapped: *mut Overlapped,
+        ) -> i32;
+    }
+}
+
+/// Returns the kind of file at `path` without following a symbolic link there, or `None` if
+/// nothing exists there.
+fn kind_at(path: &Path) -> io::Result<Option<FileKind>> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) => Ok(Some(metadata.file_type().into())),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Renames `from` to `to` like `fs::rename()`, except that nothing happens if they name the same
+/// directory entry, even when written differently. Returns whether anything was renamed.
+fn rename_at(from: &Path, to: &Path) -> io::Result<bool> {
+    if same_entry(from, to) {
+        return fs::symlink_metadata(from).map(|_| false);
+    }
+    fs::rename(from, to)?;
+    Ok(true)
+}
+
+/// Returns whether `a` and `b` name the same entry of the same directory.
+///
+/// Only their parent directories are resolved, so a symbolic link and its target aren't the same
+/// entry.
+fn same_entry(a: &Path, b: &Path) -> bool {
+    fn resolve(path: &Path) -> Option<PathBuf> {
+        let parent = match path.parent()? {
+            parent if parent.as_os_str().is_empty() => Path::new("."),
+            parent => parent,
+        };
+        Some(fs::canonicalize(parent).ok()?.join(path.file_name()?))
+    }
+
+    match (resolve(a), resolve(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Distinguishes the temporary files made by this process from each other.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a path for a temporary, hidden sibling of `path`, whose name is different every time
+/// this is called. It includes the process ID, so it's only shared with leftovers of a process
+/// which had the same ID.
+fn temp_sibling(path: &Path, tag: &str) -> io::Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid path"))?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(
+        ".{}-{}.{}",
+        process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed),
+        tag
+    ));
+    Ok(path.with_file_name(temp_name))
+}
+
+/// Exchanges the paths `a` and `b`, atomically where the platform supports it.
+fn swap_paths(a: &Path, b: &Path) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        match rename_exchange(a, b) {
+            Err(ref e)
+                if e.raw_os_error() == Some(libc::EINVAL)
+                    || e.raw_os_error() == Some(libc::ENOSYS) => {}
+            result => return result,
+        }
+    }
+
+    let tmp = loop {
+        let tmp = temp_sibling(a, "swap-tmp")?;
+        if kind_at(&tmp)?.is_none() {
+            break tmp;
+        }
+    };
+
+    fs::rename(a, &tmp)?;
+    if let Err(e) = fs::rename(b, a) {
+        let _ = fs::rename(&tmp, a);
+        return Err(e);
+    }
+    fs::rename(&tmp, b)
+}
+
+#[cfg(target_os = "linux")]
+fn rename_exchange(a: &Path, b: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let a = CString::new(a.as_os_str().as_bytes())?;
+    let b = CString::new(b.as_os_str().as_bytes())?;
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_renameat2,
+            libc::AT_FDCWD,
+            a.as_ptr(),
+            libc::AT_FDCWD,
+            b.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
 /// Provides access to file I/O.
 pub trait Fs {
     /// Opens a file at `path` with the options specified by `open_options`.
@@ -123,18 +945,161 @@ pub trait Fs {
     fn open<P: AsRef<Path>>(&mut self, path: P, open_options: &OpenOptions)
         -> io::Result<fs::File>;
 
+    /// Opens a file like [`open()`](#tymethod.open), also returning the absolute path of the file
+    /// which was opened, with any symbolic links and `.` or `..` components resolved.
+    ///
+    /// For a sandboxed filesystem, this is a path within the sandbox, not on the host.
+    ///
+    /// The default implementation resolves the path with [`canonicalize()`](#method.canonicalize)
+    /// (or, if the file doesn't exist yet, resolves its parent directory), then opens the resolved
+    /// path with [`open()`](#tymethod.open), so the path reported is the one which was opened.
+    fn open_reporting<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        open_options: &OpenOptions,
+    ) -> io::Result<(PathBuf, fs::File)> {
+        let path = path.as_ref();
+        let resolved = match self.canonicalize(path) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                let file_name = path
+                    .file_name()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid path"))?;
+                let parent = match path.parent() {
+                    Some(parent) if parent != Path::new("") => parent,
+                    _ => Path::new("."),
+                };
+                self.canonicalize(parent)?.join(file_name)
+            }
+            result => result?,
+        };
+        let file = self.open(&resolved, open_options)?;
+        Ok((resolved, file))
+    }
+
+    /// Returns the canonical, absolute form of a path, with all intermediate components
+    /// normalized and symbolic links resolved.
+    ///
+    /// For a sandboxed filesystem, this is a path within the sandbox, and a path which resolves to
+    /// somewhere outside of it is an error.
+    ///
+    /// The default implementation resolves each component in turn using
+    /// [`symlink_metadata()`](#tymethod.symlink_metadata) and [`read_link()`](#tymethod.read_link),
+    /// treating a relative path as relative to the root directory.
+    ///
+    /// See [std::fs::canonicalize](https://doc.rust-lang.org/std/fs/fn.canonicalize.html) for more
+    /// information.
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let mut pending: Vec<PathBuf> = path
+            .as_ref()
+            .components()
+            .rev()
+            .map(|c| PathBuf::from(c.as_os_str()))
+            .collect();
+        let mut resolved = PathBuf::from("/");
+        let mut links_followed = 0;
+        while let Some(next) = pending.pop() {
+            match next.components().next() {
+                Some(Component::Prefix(_)) => resolved = next,
+                Some(Component::RootDir) => resolved.push(next),
+                Some(Component::ParentDir) => {
+                    resolved.pop();
+                }
+                Some(Component::Normal(_)) => {
+                    let candidate = resolved.join(&next);
+                    if self.symlink_metadata(&candidate)?.file_type().is_symlink() {
+                        links_followed += 1;
+                        if links_followed > MAX_SYMLINKS {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                "too many levels of symbolic links",
+                            ));
+                        }
+                        let target = self.read_link(&candidate)?;
+                        pending.extend(
+                            target
+                                .components()
+                                .rev()
+                                .map(|c| PathBuf::from(c.as_os_str())),
+                        );
+                    } else {
+                        resolved = candidate;
+                    }
+                }
+                Some(Component::CurDir) | None => {}
+            }
+        }
+        Ok(resolved)
+    }
+
     /// Copies the contents of one file to another. This function will also copy the permission bits
     /// of the original file to the destination file.
     ///
     /// See [std::fs::copy](https://doc.rust-lang.org/std/fs/fn.copy.html) for more information.
     fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<u64>;
 
+    /// Copies the contents of one file to another like [`copy()`](#tymethod.copy), but in chunks,
+    /// calling `on_progress` with the total number of bytes copied so far after each one.
+    ///
+    /// The chunks are written to a temporary file alongside the destination, which replaces it
+    /// only once the copy is complete. If `on_progress` returns `false`, the copy is cancelled:
+    /// the temporary file is removed, leaving any existing destination file as it was, and an
+    /// error of kind `ErrorKind::Interrupted` is returned.
+    ///
+    /// The default implementation is built on [`open()`](#tymethod.open),
+    /// [`set_permissions()`](#tymethod.set_permissions) and [`rename()`](#tymethod.rename).
+    fn copy_streaming<P: AsRef<Path>, Q: AsRef<Path>, F: FnMut(u64) -> bool>(
+        &mut self,
+        from: P,
+        to: Q,
+        on_progress: F,
+    ) -> io::Result<u64> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let mut reader = self.open(from, OpenOptions::new().read(true))?;
+        let permissions = self.metadata(from)?.permissions();
+        let temp = temp_sibling(to, "copy-tmp")?;
+        let mut writer = self.open(&temp, OpenOptions::new().write(true).create_new(true))?;
+
+        let result = copy_chunks(&mut reader, &mut writer, on_progress);
+        drop(writer);
+        let result = result.and_then(|copied| {
+            self.set_permissions(&temp, permissions)?;
+            self.rename(&temp, to)?;
+            Ok(copied)
+        });
+        if result.is_err() {
+            let _ = self.remove_file(&temp);
+        }
+        result
+    }
+
     /// Creates a new, empty directory at the provided path.
     ///
     /// See [std::fs::create_dir](https://doc.rust-lang.org/std/fs/fn.create_dir.html) for more
     /// information.
     fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()>;
 
+    /// Creates a new, empty directory at the provided path if it doesn't already exist.
+    ///
+    /// Returns `true` if the directory was created, or `false` if it already existed. Unlike
+    /// [`create_dir()`](#tymethod.create_dir), an existing directory isn't an error, but an
+    /// existing file is: it fails with `ErrorKind::NotADirectory`.
+    ///
+    /// The default implementation is built on [`create_dir()`](#tymethod.create_dir) and
+    /// [`metadata()`](#tymethod.metadata).
+    fn ensure_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        match self.create_dir(&path) {
+            Ok(()) => Ok(true),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                if self.metadata(&path)?.is_dir() {
+                    Ok(false)
+                } else {
+                    Err(io::Error::from(io::ErrorKind::NotADirectory))
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     /// Recursively create a directory and all of its parent components if they are missing.
     ///
     /// See [std::fs::create_dir_all](https://doc.rust-lang.org/std/fs/fn.create_dir_all.html) for
@@ -146,6 +1111,9 @@ pub trait Fs {
     /// The `dst` path will be a link pointing to the `src` path. Note that systems often require
     /// these two paths to both be located on the same filesystem.
     ///
+    /// Directories can't be hard-linked; if `src` is a directory, this fails with
+    /// `ErrorKind::InvalidInput` on every platform, without attempting to create the link.
+    ///
     /// See [std::fs::hard_link](https://doc.rust-lang.org/std/fs/fn.hard_link.html) for
     /// more information.
     fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q) -> io::Result<()>;
@@ -158,6 +1126,30 @@ pub trait Fs {
     /// information.
     fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata>;
 
+    /// Queries metadata about the file at `path`, following symbolic links.
+    ///
+    /// This is like [`metadata()`](#tymethod.metadata), but returns this crate's own
+    /// [`Metadata`](struct.Metadata.html) type, which every implementation can provide.
+    ///
+    /// The default implementation is built on [`metadata()`](#tymethod.metadata).
+    fn stat<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        self.metadata(path).map(Metadata::from)
+    }
+
+    /// Returns the number of hard links pointing to the file at `path`.
+    ///
+    /// This will traverse symbolic links to query information about the destination file.
+    ///
+    /// The default implementation is built on [`metadata()`](#tymethod.metadata).
+    ///
+    /// See [std::os::unix::fs::MetadataExt::nlink](https://doc.rust-lang.org/std/os/unix/fs/trait.MetadataExt.html#tymethod.nlink)
+    /// for more information.
+    #[cfg(unix)]
+    fn nlink<P: AsRef<Path>>(&self, path: P) -> io::Result<u64> {
+        use std::os::unix::fs::MetadataExt;
+        self.metadata(path).map(|metadata| metadata.nlink())
+    }
+
     /// Read the entire contents of a file into a bytes vector.
     ///
     /// This is a convenience function for using `fs::File::open` and `fs::read_to_end`
@@ -168,6 +1160,18 @@ pub trait Fs {
     /// See [std::fs::read](https://doc.rust-lang.org/std/fs/fn.read.html) for more information.
     fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>>;
 
+    /// Reads up to `n` bytes from the start of a file, e.g. to detect its type from a header.
+    ///
+    /// Fewer bytes are returned if the file is shorter than `n`. Only the bytes returned are read,
+    /// rather than the whole file. The default implementation can't do that, so it reads the whole
+    /// file with [`read()`](#tymethod.read) and keeps the first `n` bytes; every implementation in
+    /// this crate overrides it.
+    fn read_prefix<P: AsRef<Path>>(&self, path: P, n: usize) -> io::Result<Vec<u8>> {
+        let mut contents = self.read(path)?;
+        contents.truncate(n);
+        Ok(contents)
+    }
+
     /// Returns an iterator over the entries within a directory.
     ///
     /// The iterator will yield instances of `io::Result<fs::DirEntry]>`.
@@ -177,12 +1181,55 @@ pub trait Fs {
     /// information.
     fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::ReadDir>;
 
+    /// Returns the entries within a directory, sorted by file name.
+    ///
+    /// Unlike [`read_dir()`](#tymethod.read_dir), each entry's path is relative to `path` as
+    /// given, so for a sandboxed filesystem it's a path within the sandbox rather than on the host.
+    ///
+    /// The default implementation is built on [`read_dir()`](#tymethod.read_dir).
+    fn list_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<DirEntry>> {
+        sorted_dir_entries(self.read_dir(&path)?, path.as_ref())
+    }
+
     /// Reads a symbolic link, returning the file that the link points to.
     ///
     /// See [std::fs::read_link](https://doc.rust-lang.org/std/fs/fn.read_link.html) for more
     /// information.
     fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf>;
 
+    /// Returns an iterator over the lines of a file.
+    ///
+    /// Unlike calling `lines()` on the result of [`read_to_string()`](#tymethod.read_to_string),
+    /// the file is read incrementally rather than being loaded into memory all at once. The
+    /// default implementation can't do that, so it reads the whole file with
+    /// [`read()`](#tymethod.read) first; every implementation in this crate overrides it.
+    ///
+    /// See [std::io::BufRead::lines](https://doc.rust-lang.org/std/io/trait.BufRead.html#method.lines)
+    /// for more information.
+    fn read_lines<P: AsRef<Path>>(&self, path: P) -> io::Result<Lines> {
+        Ok(Lines::new(io::Cursor::new(self.read(path)?)))
+    }
+
+    /// Returns an iterator over the lines of a file like [`read_lines()`](#method.read_lines),
+    /// but without reading more than `max_line_bytes` of any one line into memory.
+    ///
+    /// A line longer than that (not counting its trailing newline) produces an error of kind
+    /// `InvalidData` from the iterator in place of the line; iteration can continue with the
+    /// following lines.
+    ///
+    /// Like `read_lines()`, the default implementation reads the whole file with
+    /// [`read()`](#tymethod.read) first; every implementation in this crate overrides it.
+    fn read_lines_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_line_bytes: usize,
+    ) -> io::Result<Lines> {
+        Ok(Lines::limited(
+            io::Cursor::new(self.read(path)?),
+            max_line_bytes,
+        ))
+    }
+
     /// Read the entire contents of a file into a string.
     ///
     /// This is a convenience function for using `fs::File::open` and `fs::read_to_string`
@@ -194,6 +1241,25 @@ pub trait Fs {
     /// more information.
     fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String>;
 
+    /// Reads the entire contents of a file into a string like
+    /// [`read_to_string()`](#tymethod.read_to_string), but fails with `ErrorKind::InvalidData`
+    /// instead if the file is longer than `max_bytes`.
+    ///
+    /// At most `max_bytes + 1` bytes are read, even if the file's size isn't known in advance, so
+    /// this is safe to use on files of untrusted size. The default implementation reads them with
+    /// [`read_prefix()`](#method.read_prefix).
+    fn read_to_string_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_bytes: usize,
+    ) -> io::Result<String> {
+        let contents = self.read_prefix(path, max_bytes.saturating_add(1))?;
+        if contents.len() > max_bytes {
+            return Err(file_too_large(max_bytes));
+        }
+        String::from_utf8(contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
     /// Removes an existing, empty directory.
     ///
     /// See [std::fs::remove_dir](https://doc.rust-lang.org/std/fs/fn.remove_dir.html) for more
@@ -223,11 +1289,41 @@ pub trait Fs {
     /// Rename a file or directory to a new name, replacing the original file if
     /// `to` already exists.
     ///
+    /// If `from` and `to` name the same file or directory (e.g. `/a` and `/a/.`), this succeeds
+    /// without doing anything, regardless of the platform.
+    ///
     /// This will not work if the new name is on a different mount point.
     ///
     /// See [std::fs::rename](https://doc.rust-lang.org/std/fs/fn.rename.html) for more information.
     fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()>;
 
+    /// Exchanges the files or directories at paths `a` and `b`, both of which must exist.
+    ///
+    /// On Linux this is done atomically using `renameat2(RENAME_EXCHANGE)`. Elsewhere, or if the
+    /// underlying filesystem doesn't support it, this falls back to three successive renames via a
+    /// temporary sibling of `a`, with a name which nothing else is using. The fallback is **not**
+    /// atomic: another process may briefly observe `a` as missing, and an interruption may leave
+    /// the temporary path behind.
+    ///
+    /// The default implementation always uses the fallback, made of calls to
+    /// [`rename()`](#tymethod.rename).
+    fn swap<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, a: P, b: Q) -> io::Result<()> {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        let tmp = loop {
+            let tmp = temp_sibling(a, "swap-tmp")?;
+            if self.kind(&tmp)?.is_none() {
+                break tmp;
+            }
+        };
+
+        self.rename(a, &tmp)?;
+        if let Err(e) = self.rename(b, a) {
+            let _ = self.rename(&tmp, a);
+            return Err(e);
+        }
+        self.rename(&tmp, b)
+    }
+
     /// Changes the permissions found on a file or a directory.
     ///
     /// See [std::fs::set_permissions](https://doc.rust-lang.org/std/fs/fn.set_permissions.html) for
@@ -241,6 +1337,18 @@ pub trait Fs {
     /// for more information.
     fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata>;
 
+    /// Truncates an existing file to be empty, without replacing it, so that its permissions, hard
+    /// links and any open handles to it are preserved.
+    ///
+    /// Unlike [`write()`](#tymethod.write), this fails with `ErrorKind::NotFound` if the file
+    /// doesn't exist.
+    ///
+    /// The default implementation is built on [`open()`](#tymethod.open).
+    fn truncate<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.open(path, OpenOptions::new().write(true).truncate(true))
+            .map(|_| ())
+    }
+
     /// Write a slice as the entire contents of a file.
     ///
     /// This function will create a file if it does not exist,
@@ -252,6 +1360,61 @@ pub trait Fs {
     /// See [std::fs::write](https://doc.rust-lang.org/std/fs/fn.write.html) for more information.
     fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&mut self, path: P, contents: C) -> io::Result<()>;
 
+    /// Writes a slice as the entire contents of a file like [`write()`](#tymethod.write), returning
+    /// the number of bytes written.
+    ///
+    /// If the write fails after part of `contents` was written, the error's message says how many
+    /// bytes were written. Providers which simulate failures, such as
+    /// [`TempFs`](struct.TempFs.html) with a byte quota, write as much as they can before failing.
+    ///
+    /// The default implementation is built on [`write()`](#tymethod.write), so it can't say how
+    /// much of a failed write was made.
+    fn write_counted<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<u64> {
+        let contents = contents.as_ref();
+        self.write(path, contents)?;
+        Ok(contents.len() as u64)
+    }
+
+    /// Overwrites part of an existing file with `data`, starting `offset` bytes from its start.
+    ///
+    /// The rest of the file is left as it was, except that it's extended if `data` runs past its
+    /// end. If `offset` is beyond the end of the file, the gap is filled with zeros.
+    ///
+    /// The default implementation is built on [`open()`](#tymethod.open).
+    fn write_at<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        offset: u64,
+        data: C,
+    ) -> io::Result<()> {
+        let mut file = self.open(path, OpenOptions::new().read(true).write(true))?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(data.as_ref())
+    }
+
+    /// Appends `contents` to the end of a file, creating it if it doesn't exist, while holding an
+    /// exclusive advisory lock on it.
+    ///
+    /// Appends made this way from several processes at once don't interleave, as long as every
+    /// process appends through a lock. Providers which aren't backed by files on disk, such as
+    /// [`MemoryFs`](struct.MemoryFs.html), are only used within a single process, so they append
+    /// without a lock. Files can only be locked on Unix and Windows.
+    ///
+    /// The default implementation is built on [`open()`](#tymethod.open).
+    fn append_locked<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<()> {
+        let mut file = self.open(path, OpenOptions::new().append(true).create(true))?;
+        lock_exclusive(&file)?;
+        file.write_all(contents.as_ref())
+    }
+
     /// Returns whether the path points at an existing entity.
     ///
     /// This function will traverse symbolic links to query information about the
@@ -263,4 +1426,38 @@ pub trait Fs {
     /// See [std::path::Path.exists](https://doc.rust-lang.org/std/path/struct.Path.html#method.exists)
     /// for more information.
     fn exists<P: AsRef<Path>>(&self, path: P) -> bool;
+
+    /// Returns `Ok(true)` if the path points at an existing entity.
+    ///
+    /// Unlike [`exists()`](#tymethod.exists), errors other than the entity not existing (such as a
+    /// permission error, or traversing through something which isn't a directory) are returned
+    /// rather than being treated as `false`.
+    ///
+    /// The default implementation is built on [`metadata()`](#tymethod.metadata).
+    ///
+    /// See [std::path::Path.try_exists](https://doc.rust-lang.org/std/path/struct.Path.html#method.try_exists)
+    /// for more information.
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        match self.metadata(path) {
+            Ok(_) => Ok(true),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the kind of file at `path`, or `None` if nothing exists there.
+    ///
+    /// A symbolic link at `path` isn't followed, so it's reported as `FileKind::Symlink`. Like
+    /// [`try_exists()`](#method.try_exists), errors other than the file not existing are
+    /// returned.
+    ///
+    /// The default implementation is built on
+    /// [`symlink_metadata()`](#tymethod.symlink_metadata).
+    fn kind<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<FileKind>> {
+        match self.symlink_metadata(path) {
+            Ok(metadata) => Ok(Some(metadata.file_type().into())),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
 }
@@ -2,7 +2,10 @@ use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
-use fs::{Fs, OpenOptions};
+use fs::{
+    copy_streaming_at, hard_link_at, list_dir_at, read_prefix_at, read_to_string_limited_at,
+    rename_at, swap_paths, write_at_path, write_counted_at, DirEntry, Fs, Lines, OpenOptions,
+};
 
 /// Provides access to native file I/O.
 #[derive(Debug, Default)]
@@ -17,10 +20,23 @@ impl Fs for NativeFs {
         open_options.as_std().open(path)
     }
 
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+
     fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<u64> {
         fs::copy(from, to)
     }
 
+    fn copy_streaming<P: AsRef<Path>, Q: AsRef<Path>, F: FnMut(u64) -> bool>(
+        &mut self,
+        from: P,
+        to: Q,
+        on_progress: F,
+    ) -> io::Result<u64> {
+        copy_streaming_at(from.as_ref(), to.as_ref(), on_progress)
+    }
+
     fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
         fs::create_dir(path)
     }
@@ -30,7 +46,7 @@ impl Fs for NativeFs {
     }
 
     fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q) -> io::Result<()> {
-        fs::hard_link(src, dst)
+        hard_link_at(src.as_ref(), dst.as_ref())
     }
 
     fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata> {
@@ -41,18 +57,46 @@ impl Fs for NativeFs {
         fs::read(path)
     }
 
+    fn read_prefix<P: AsRef<Path>>(&self, path: P, n: usize) -> io::Result<Vec<u8>> {
+        read_prefix_at(path.as_ref(), n)
+    }
+
     fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::ReadDir> {
         fs::read_dir(path)
     }
 
+    fn list_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<DirEntry>> {
+        list_dir_at(path.as_ref(), path.as_ref())
+    }
+
     fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
         fs::read_link(path)
     }
 
+    fn read_lines<P: AsRef<Path>>(&self, path: P) -> io::Result<Lines> {
+        Ok(Lines::new(fs::File::open(path)?))
+    }
+
+    fn read_lines_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_line_bytes: usize,
+    ) -> io::Result<Lines> {
+        Ok(Lines::limited(fs::File::open(path)?, max_line_bytes))
+    }
+
     fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
         fs::read_to_string(path)
     }
 
+    fn read_to_string_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_bytes: usize,
+    ) -> io::Result<String> {
+        read_to_string_limited_at(path.as_ref(), max_bytes)
+    }
+
     fn remove_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
         fs::remove_dir(path)
     }
@@ -66,7 +110,11 @@ impl Fs for NativeFs {
     }
 
     fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
-        fs::rename(from, to)
+        rename_at(from.as_ref(), to.as_ref()).map(|_| ())
+    }
+
+    fn swap<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, a: P, b: Q) -> io::Result<()> {
+        swap_paths(a.as_ref(), b.as_ref())
     }
 
     fn set_permissions<P: AsRef<Path>>(
@@ -85,7 +133,28 @@ impl Fs for NativeFs {
         fs::write(path, contents)
     }
 
+    fn write_counted<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<u64> {
+        write_counted_at(path.as_ref(), contents.as_ref())
+    }
+
+    fn write_at<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        offset: u64,
+        data: C,
+    ) -> io::Result<()> {
+        write_at_path(path.as_ref(), offset, data.as_ref())
+    }
+
     fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
         path.as_ref().exists()
     }
+
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        path.as_ref().try_exists()
+    }
 }
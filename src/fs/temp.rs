@@ -1,10 +1,19 @@
+use std::cmp::Reverse;
+use std::error::Error;
+use std::fmt;
 use std::fs;
 use std::io;
-use std::path::{Path, PathBuf};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
 
 use tempfile::{tempdir, TempDir};
 
-use fs::{Fs, OpenOptions};
+use fs::{
+    append_locked_at, copy_streaming_at, hard_link_at, kind_at, list_dir_at, partial_write_error,
+    read_prefix_at, read_to_string_limited_at, rename_at, swap_paths, truncate_at, write_at_path,
+    write_counted_at, DirEntry, FileKind, Fs, FsEvent, FsOp, Latencies, Lines, OpenOptions,
+};
 
 /// Provides access to file I/O in a chroot-like temporary filesystem, located in the system's
 /// default temp directory. This temporary directory acts like the root of the filesystem: all
@@ -15,19 +24,53 @@ use fs::{Fs, OpenOptions};
 ///   * This is NOT intended to act as a secure sandbox; while it ought to handle edge cases such as
 ///     path traversals and symbolic links correctly, no attempt has been made to verify that there
 ///     is no way to circumvent this.
-///   * [`Fs::create_dir_all()`](fs/trait.Fs.html#tymethod.create_dir_all) is not currently
-///     implemented. It is possible to implement, but it's non-trivial to handle path traversals and
-///     symlinks for this function.
+///   * By default, new files and directories are created with permissions determined by the
+///     process's umask, which varies between machines. On Unix, use
+///     [`set_umask()`](#method.set_umask) to make them deterministic.
+///   * The number of bytes written can be limited using
+///     [`set_byte_quota()`](#method.set_byte_quota), but writes made through a file handle
+///     returned by [`Fs::open()`](fs/trait.Fs.html#tymethod.open) can't be counted; use
+///     [`open_sandboxed()`](#method.open_sandboxed) to have them counted too.
+///   * Error messages are prefixed with the path as given (relative to the sandbox) and never
+///     contain the location of the temporary directory on the host, so they're the same on every
+///     machine. The `ErrorKind` is preserved, and the original error (including its OS error
+///     code) is available as the error's `source()`.
+///   * [`Fs::read_link()`](fs/trait.Fs.html#tymethod.read_link) reports an absolute link target
+///     as a path within the sandbox, and fails with `ErrorKind::InvalidInput` if it lies outside
+///     of it. Relative targets are returned as they are.
+///   * [`Fs::remove_dir_all()`](fs/trait.Fs.html#tymethod.remove_dir_all) never follows symbolic
+///     links, including one at the path it's given, and checks that everything it would remove
+///     lies within the sandbox before removing anything.
+///   * Changes made to the filesystem are recorded, and can be inspected using
+///     [`operations()`](#method.operations). As with the byte quota, writes made through a file
+///     handle aren't recorded.
 #[derive(Debug)]
 pub struct TempFs {
     temp_dir: TempDir,
+    /// The canonical form of the temporary directory's path, which differs from it if the system
+    /// temp directory is reached through a symbolic link (e.g. `/var` on macOS).
+    canonical_root: PathBuf,
+    #[cfg(unix)]
+    umask: Option<u32>,
+    byte_quota: Option<u64>,
+    bytes_written: u64,
+    latencies: Latencies,
+    events: Vec<FsEvent>,
 }
 
 impl TempFs {
     /// Creates a new `TempFs`.
     pub fn new() -> io::Result<TempFs> {
+        let temp_dir = tempdir()?;
         Ok(TempFs {
-            temp_dir: tempdir()?,
+            canonical_root: temp_dir.path().canonicalize()?,
+            temp_dir,
+            #[cfg(unix)]
+            umask: None,
+            byte_quota: None,
+            bytes_written: 0,
+            latencies: Latencies::default(),
+            events: Vec::new(),
         })
     }
 
@@ -36,29 +79,278 @@ impl TempFs {
         self.temp_dir.path()
     }
 
+    /// Sets the file mode creation mask used for files and directories created from now on,
+    /// independently of the process's actual umask.
+    ///
+    /// New files are given the mode `0o666 & !mask` and new directories `0o777 & !mask`.
+    #[cfg(unix)]
+    pub fn set_umask(&mut self, mask: u32) {
+        self.umask = Some(mask);
+    }
+
+    /// Sets the maximum total number of bytes which may be written to this filesystem, or `None`
+    /// for no limit (the default).
+    ///
+    /// Once a write or copy would take the total written since this `TempFs` was created over the
+    /// quota, it fails with `ErrorKind::StorageFull` without writing anything, except that
+    /// [`Fs::write_counted()`](fs/trait.Fs.html#method.write_counted) writes as much as fits
+    /// first. This is useful for simulating a full disk, or for stopping a runaway test before it
+    /// fills the real one.
+    ///
+    /// Bytes are counted once they've been written, so an operation which fails doesn't use up
+    /// any of the quota. Writes, appends and copies made through `Fs` methods are counted, as are
+    /// writes through a [`SandboxFile`](struct.SandboxFile.html) returned by
+    /// [`open_sandboxed()`](#method.open_sandboxed). Writes through a file returned by
+    /// [`Fs::open()`](fs/trait.Fs.html#tymethod.open) are **not** counted: that's a plain
+    /// `std::fs::File`, which this `TempFs` has no way to observe.
+    pub fn set_byte_quota(&mut self, max: Option<u64>) {
+        self.byte_quota = max;
+    }
+
+    /// Sets a delay to wait for before each operation of the kind `op`, simulating a slow disk;
+    /// `Duration::ZERO` removes the delay.
+    pub fn set_latency(&mut self, op: FsOp, delay: Duration) {
+        self.latencies.set(op, delay);
+    }
+
+    /// Returns the changes which have been made to this filesystem, in the order they were made.
+    ///
+    /// This makes it possible to check that an operation makes only the expected changes, e.g.
+    /// that a file is replaced with a single rename rather than being rewritten in place.
+    pub fn operations(&self) -> &[FsEvent] {
+        &self.events
+    }
+
+    /// Opens a file at `path` like [`Fs::open()`](fs/trait.Fs.html#tymethod.open), but returns a
+    /// [`SandboxFile`](struct.SandboxFile.html) which stays associated with this `TempFs`.
+    ///
+    /// Writes made through the returned file count towards the byte quota, and its errors refer
+    /// to `path` rather than the file's location on the host.
+    pub fn open_sandboxed<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        open_options: &OpenOptions,
+    ) -> io::Result<SandboxFile<'_>> {
+        let file = self.open(&path, open_options)?;
+        Ok(SandboxFile {
+            fs: self,
+            file,
+            path: path.as_ref().to_path_buf(),
+        })
+    }
+
+    /// Fails if there isn't enough of the byte quota remaining to write `len` bytes. Nothing is
+    /// counted until the bytes have been written; see `count_bytes()`.
+    fn check_quota(&self, len: u64) -> io::Result<()> {
+        match self.byte_quota {
+            Some(max) if self.bytes_written.saturating_add(len) > max => Err(quota_exceeded()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts `len` bytes which have been written against the byte quota.
+    fn count_bytes(&mut self, len: u64) {
+        self.bytes_written = self.bytes_written.saturating_add(len);
+    }
+
+    /// Applies the configured umask (if any) to a newly-created file or directory.
+    #[cfg(unix)]
+    fn apply_umask(&self, path: &Path, is_dir: bool) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        match self.umask {
+            Some(mask) => {
+                let mode = if is_dir { 0o777 } else { 0o666 };
+                fs::set_permissions(path, fs::Permissions::from_mode(mode & !mask))
+            }
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn apply_umask(&self, _path: &Path, _is_dir: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Reroots `path` onto the temporary directory, failing with `ErrorKind::InvalidInput` if the
+    /// result would lie outside of it.
     fn change_path<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
-        let exists = path.as_ref().exists();
-        let mut result: PathBuf = self.temp_dir.path().join(path);
+        self.reroot(path.as_ref(), true)
+    }
+
+    /// Reroots `path` like [`change_path()`](#method.change_path), but without following a
+    /// symbolic link in its last component, so that the link itself can be inspected.
+    fn change_path_nofollow<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        self.reroot(path.as_ref(), false)
+    }
+
+    /// Implements `change_path()` and `change_path_nofollow()`.
+    fn reroot(&self, path: &Path, follow: bool) -> io::Result<PathBuf> {
+        let relative: PathBuf = path
+            .components()
+            .filter(|c| !matches!(c, Component::Prefix(_) | Component::RootDir))
+            .collect();
+        let mut result: PathBuf = self.temp_dir.path().join(relative);
 
-        result = if exists {
+        result = if follow && result.exists() {
             result.canonicalize()?
         } else {
             result
                 .parent()
                 .map(|p| p.canonicalize())
                 .unwrap_or_else(|| Ok(PathBuf::new()))?
-                .join(
-                    result
-                        .file_name()
-                        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Invalid path"))?,
-                )
+                .join(result.file_name().ok_or_else(invalid_path)?)
         };
 
-        if result.starts_with(&self.temp_dir.path()) {
+        if result.starts_with(self.temp_dir.path()) || result.starts_with(&self.canonical_root) {
             Ok(result)
         } else {
-            Err(io::Error::new(io::ErrorKind::Other, "Invalid path"))
+            Err(invalid_path())
+        }
+    }
+
+    /// Runs `op` on the rerooted form of `path`, rewriting any error to refer to `path` instead.
+    fn on_path<T, F: FnOnce(PathBuf) -> io::Result<T>>(&self, path: &Path, op: F) -> io::Result<T> {
+        self.change_path(path)
+            .and_then(op)
+            .map_err(|e| self.sandbox_error(path.display(), e))
+    }
+
+    /// Wraps an error from an operation on `path` (as given by the caller, i.e. relative to the
+    /// sandbox), so that its message names that path and doesn't reveal the location of the
+    /// temporary directory on the host.
+    fn sandbox_error<D: fmt::Display>(&self, path: D, e: io::Error) -> io::Error {
+        let mut roots = [
+            self.temp_dir.path().to_string_lossy(),
+            self.canonical_root.to_string_lossy(),
+        ];
+        // Strip the longer root first, in case the other is contained in it
+        roots.sort_by_key(|root| Reverse(root.len()));
+        let message = roots
+            .iter()
+            .fold(e.to_string(), |message, root| message.replace(&**root, ""));
+        io::Error::new(
+            e.kind(),
+            SandboxError {
+                message: format!("{}: {}", path, message),
+                source: e,
+            },
+        )
+    }
+
+    /// Removes `path` using `remove`, recording the removal if it succeeds.
+    fn remove_with<F: FnOnce(&Path) -> io::Result<()>>(
+        &mut self,
+        path: &Path,
+        remove: F,
+    ) -> io::Result<()> {
+        let rerooted = self.on_path(path, |rerooted| {
+            remove(&rerooted)?;
+            Ok(rerooted)
+        })?;
+        self.events
+            .push(FsEvent::Remove(self.sandbox_path(&rerooted)?));
+        Ok(())
+    }
+
+    /// Returns whether `path` is an existing directory.
+    fn is_dir(&self, path: &Path) -> io::Result<bool> {
+        self.on_path(path, |rerooted| Ok(rerooted.is_dir()))
+    }
+
+    /// Implements `open()`, without rewriting errors. Also returns the rerooted path.
+    fn open_rerooted(
+        &mut self,
+        path: &Path,
+        open_options: &OpenOptions,
+    ) -> io::Result<(PathBuf, fs::File)> {
+        let path = self.change_path(path)?;
+        let sandboxed = self.sandbox_path(&path)?;
+        let created = !path.exists();
+        let file = open_options.as_std().open(&path)?;
+        if created {
+            self.apply_umask(&path, false)?;
+            self.events.push(FsEvent::Write(sandboxed));
+        }
+        Ok((path, file))
+    }
+
+    /// Converts a rerooted path back into an absolute path within the sandbox, failing if it's
+    /// outside of the sandbox.
+    fn sandbox_path(&self, rerooted: &Path) -> io::Result<PathBuf> {
+        let relative = rerooted
+            .strip_prefix(self.temp_dir.path())
+            .or_else(|_| rerooted.strip_prefix(&self.canonical_root))
+            .map_err(|_| invalid_path())?;
+        Ok(Path::new("/").join(relative))
+    }
+
+    /// Implements `create_dir()`, without applying its latency.
+    fn create_dir_undelayed(&mut self, path: &Path) -> io::Result<()> {
+        let rerooted = self
+            .change_path(path)
+            .and_then(|rerooted| {
+                fs::create_dir(&rerooted)?;
+                self.apply_umask(&rerooted, true)?;
+                Ok(rerooted)
+            })
+            .map_err(|e| self.sandbox_error(path.display(), e))?;
+        self.events
+            .push(FsEvent::CreateDir(self.sandbox_path(&rerooted)?));
+        Ok(())
+    }
+
+    /// Implements `copy()`, without rewriting errors.
+    fn copy_rerooted(&mut self, from: &Path, to: &Path) -> io::Result<u64> {
+        let (from, to) = (self.change_path(from)?, self.change_path(to)?);
+        self.check_quota(fs::metadata(&from)?.len())?;
+        let len = fs::copy(from, &to)?;
+        self.count_bytes(len);
+        self.events.push(FsEvent::Write(self.sandbox_path(&to)?));
+        Ok(len)
+    }
+
+    /// Implements `write()`, without rewriting errors.
+    fn write_rerooted(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let path = self.change_path(path)?;
+        let sandboxed = self.sandbox_path(&path)?;
+        let created = !path.exists();
+        self.check_quota(contents.len() as u64)?;
+        fs::write(&path, contents)?;
+        self.count_bytes(contents.len() as u64);
+        if created {
+            self.apply_umask(&path, false)?;
+        }
+        self.events.push(FsEvent::Write(sandboxed));
+        Ok(())
+    }
+
+    /// Writes as much of `contents` to `path` as the byte quota allows, failing if that isn't all
+    /// of it.
+    fn write_counted_rerooted(&mut self, path: &Path, contents: &[u8]) -> io::Result<u64> {
+        let path = self.change_path(path)?;
+        let sandboxed = self.sandbox_path(&path)?;
+        let created = !path.exists();
+        let allowed = match self.byte_quota {
+            Some(max) => max
+                .saturating_sub(self.bytes_written)
+                .min(contents.len() as u64),
+            None => contents.len() as u64,
+        };
+        let written = write_counted_at(&path, &contents[..allowed as usize])?;
+        self.count_bytes(written);
+        if created {
+            self.apply_umask(&path, false)?;
         }
+        self.events.push(FsEvent::Write(sandboxed));
+        if written < contents.len() as u64 {
+            return Err(partial_write_error(
+                written as usize,
+                contents.len(),
+                quota_exceeded(),
+            ));
+        }
+        Ok(written)
     }
 }
 
@@ -68,62 +360,271 @@ impl Fs for TempFs {
         path: P,
         open_options: &OpenOptions,
     ) -> io::Result<fs::File> {
-        open_options.as_std().open(self.change_path(path)?)
+        self.latencies.apply(FsOp::Open);
+        let path = path.as_ref();
+        self.open_rerooted(path, open_options)
+            .map(|(_, file)| file)
+            .map_err(|e| self.sandbox_error(path.display(), e))
+    }
+
+    fn open_reporting<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        open_options: &OpenOptions,
+    ) -> io::Result<(PathBuf, fs::File)> {
+        self.latencies.apply(FsOp::Open);
+        let path = path.as_ref();
+        self.open_rerooted(path, open_options)
+            .and_then(|(rerooted, file)| Ok((self.sandbox_path(&rerooted)?, file)))
+            .map_err(|e| self.sandbox_error(path.display(), e))
+    }
+
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        self.latencies.apply(FsOp::Metadata);
+        // Rerooting already resolves symlinks and rejects paths which escape the sandbox
+        self.on_path(path.as_ref(), |rerooted| {
+            fs::canonicalize(rerooted).and_then(|canonical| self.sandbox_path(&canonical))
+        })
     }
 
     fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<u64> {
-        fs::copy(self.change_path(from)?, self.change_path(to)?)
+        self.latencies.apply(FsOp::Copy);
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.copy_rerooted(from, to)
+            .map_err(|e| self.sandbox_error(format!("{} -> {}", from.display(), to.display()), e))
+    }
+
+    fn copy_streaming<P: AsRef<Path>, Q: AsRef<Path>, F: FnMut(u64) -> bool>(
+        &mut self,
+        from: P,
+        to: Q,
+        on_progress: F,
+    ) -> io::Result<u64> {
+        self.latencies.apply(FsOp::Copy);
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.change_path(from)
+            .and_then(|rerooted_from| {
+                let rerooted_to = self.change_path(to)?;
+                self.check_quota(fs::metadata(&rerooted_from)?.len())?;
+                let len = copy_streaming_at(&rerooted_from, &rerooted_to, on_progress)?;
+                self.count_bytes(len);
+                self.events
+                    .push(FsEvent::Write(self.sandbox_path(&rerooted_to)?));
+                Ok(len)
+            })
+            .map_err(|e| self.sandbox_error(format!("{} -> {}", from.display(), to.display()), e))
     }
 
     fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        fs::create_dir(self.change_path(path)?)
+        self.latencies.apply(FsOp::CreateDir);
+        self.create_dir_undelayed(path.as_ref())
+    }
+
+    fn ensure_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        self.latencies.apply(FsOp::CreateDir);
+        let path = path.as_ref();
+        match self.create_dir_undelayed(path) {
+            Ok(()) => Ok(true),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                if self.is_dir(path)? {
+                    Ok(false)
+                } else {
+                    Err(self.sandbox_error(
+                        path.display(),
+                        io::Error::from(io::ErrorKind::NotADirectory),
+                    ))
+                }
+            }
+            Err(e) => Err(e),
+        }
     }
 
-    #[allow(unused_variables)]
     fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        unimplemented!(
-            "It's difficult to implement path canonicalization correctly for create_dir_all()"
-        );
+        self.latencies.apply(FsOp::CreateDir);
+        // Create one component at a time, so that each new directory is validated against the
+        // sandbox after any symlinks or `..` components preceding it have been resolved
+        let mut current = PathBuf::new();
+        for component in path.as_ref().components() {
+            current.push(component);
+            if !self.is_dir(&current)? {
+                match self.create_dir_undelayed(&current) {
+                    Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                        if !self.is_dir(&current)? {
+                            return Err(self.sandbox_error(
+                                current.display(),
+                                io::Error::from(io::ErrorKind::AlreadyExists),
+                            ));
+                        }
+                    }
+                    result => result?,
+                }
+            }
+        }
+        Ok(())
     }
 
     fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q) -> io::Result<()> {
-        fs::hard_link(self.change_path(src)?, self.change_path(dst)?)
+        self.latencies.apply(FsOp::Link);
+        let (src, dst) = (src.as_ref(), dst.as_ref());
+        let (rerooted_src, rerooted_dst) = self
+            .change_path(src)
+            .and_then(|rerooted_src| {
+                let rerooted_dst = self.change_path(dst)?;
+                hard_link_at(&rerooted_src, &rerooted_dst)?;
+                Ok((rerooted_src, rerooted_dst))
+            })
+            .map_err(|e| {
+                self.sandbox_error(format!("{} -> {}", src.display(), dst.display()), e)
+            })?;
+        self.events.push(FsEvent::Link {
+            src: self.sandbox_path(&rerooted_src)?,
+            dst: self.sandbox_path(&rerooted_dst)?,
+        });
+        Ok(())
     }
 
     fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata> {
-        fs::metadata(self.change_path(path)?)
+        self.latencies.apply(FsOp::Metadata);
+        self.on_path(path.as_ref(), fs::metadata)
     }
 
     fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
-        fs::read(self.change_path(path)?)
+        self.latencies.apply(FsOp::Read);
+        self.on_path(path.as_ref(), fs::read)
+    }
+
+    fn read_prefix<P: AsRef<Path>>(&self, path: P, n: usize) -> io::Result<Vec<u8>> {
+        self.latencies.apply(FsOp::Read);
+        self.on_path(path.as_ref(), |rerooted| read_prefix_at(&rerooted, n))
     }
 
     fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::ReadDir> {
-        fs::read_dir(self.change_path(path)?)
+        self.latencies.apply(FsOp::ReadDir);
+        self.on_path(path.as_ref(), fs::read_dir)
+    }
+
+    fn list_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<DirEntry>> {
+        self.latencies.apply(FsOp::ReadDir);
+        let path = path.as_ref();
+        self.on_path(path, |rerooted| list_dir_at(&rerooted, path))
     }
 
     fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
-        fs::read_link(self.change_path(path)?)
+        self.latencies.apply(FsOp::Read);
+        let path = path.as_ref();
+        self.change_path_nofollow(path)
+            .and_then(fs::read_link)
+            .and_then(|target| {
+                // Absolute targets are host paths, so they're only meaningful inside the sandbox
+                if target.is_absolute() {
+                    self.sandbox_path(&target)
+                } else {
+                    Ok(target)
+                }
+            })
+            .map_err(|e| self.sandbox_error(path.display(), e))
+    }
+
+    fn read_lines<P: AsRef<Path>>(&self, path: P) -> io::Result<Lines> {
+        self.latencies.apply(FsOp::Read);
+        self.on_path(path.as_ref(), |rerooted| {
+            Ok(Lines::new(fs::File::open(rerooted)?))
+        })
+    }
+
+    fn read_lines_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_line_bytes: usize,
+    ) -> io::Result<Lines> {
+        self.latencies.apply(FsOp::Read);
+        self.on_path(path.as_ref(), |rerooted| {
+            Ok(Lines::limited(fs::File::open(rerooted)?, max_line_bytes))
+        })
     }
 
     fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
-        fs::read_to_string(self.change_path(path)?)
+        self.latencies.apply(FsOp::Read);
+        self.on_path(path.as_ref(), fs::read_to_string)
+    }
+
+    fn read_to_string_limited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_bytes: usize,
+    ) -> io::Result<String> {
+        self.latencies.apply(FsOp::Read);
+        self.on_path(path.as_ref(), |rerooted| {
+            read_to_string_limited_at(&rerooted, max_bytes)
+        })
     }
 
     fn remove_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        fs::remove_dir(self.change_path(path)?)
+        self.latencies.apply(FsOp::Remove);
+        self.remove_with(path.as_ref(), |rerooted| fs::remove_dir(rerooted))
     }
 
     fn remove_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        fs::remove_dir_all(self.change_path(path)?)
+        self.latencies.apply(FsOp::Remove);
+        let path = path.as_ref();
+        // A symbolic link at `path` is removed itself, like `fs::remove_dir_all()` does, rather
+        // than the directory it points to
+        let rerooted = self
+            .change_path_nofollow(path)
+            .and_then(|rerooted| {
+                remove_tree_within(&rerooted, &self.canonical_root)?;
+                Ok(rerooted)
+            })
+            .map_err(|e| self.sandbox_error(path.display(), e))?;
+        self.events
+            .push(FsEvent::Remove(self.sandbox_path(&rerooted)?));
+        Ok(())
     }
 
     fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        fs::remove_file(self.change_path(path)?)
+        self.latencies.apply(FsOp::Remove);
+        self.remove_with(path.as_ref(), |rerooted| fs::remove_file(rerooted))
     }
 
     fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
-        fs::rename(self.change_path(from)?, self.change_path(to)?)
+        self.latencies.apply(FsOp::Rename);
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let (rerooted_from, rerooted_to, renamed) = self
+            .change_path(from)
+            .and_then(|rerooted_from| {
+                let rerooted_to = self.change_path(to)?;
+                let renamed = rename_at(&rerooted_from, &rerooted_to)?;
+                Ok((rerooted_from, rerooted_to, renamed))
+            })
+            .map_err(|e| {
+                self.sandbox_error(format!("{} -> {}", from.display(), to.display()), e)
+            })?;
+        if !renamed {
+            return Ok(());
+        }
+        self.events.push(FsEvent::Rename {
+            from: self.sandbox_path(&rerooted_from)?,
+            to: self.sandbox_path(&rerooted_to)?,
+        });
+        Ok(())
+    }
+
+    fn swap<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, a: P, b: Q) -> io::Result<()> {
+        self.latencies.apply(FsOp::Rename);
+        let (a, b) = (a.as_ref(), b.as_ref());
+        let (rerooted_a, rerooted_b) = self
+            .change_path(a)
+            .and_then(|rerooted_a| {
+                let rerooted_b = self.change_path(b)?;
+                swap_paths(&rerooted_a, &rerooted_b)?;
+                Ok((rerooted_a, rerooted_b))
+            })
+            .map_err(|e| self.sandbox_error(format!("{} <-> {}", a.display(), b.display()), e))?;
+        self.events.push(FsEvent::Swap {
+            a: self.sandbox_path(&rerooted_a)?,
+            b: self.sandbox_path(&rerooted_b)?,
+        });
+        Ok(())
     }
 
     fn set_permissions<P: AsRef<Path>>(
@@ -131,18 +632,353 @@ impl Fs for TempFs {
         path: P,
         perm: fs::Permissions,
     ) -> io::Result<()> {
-        fs::set_permissions(self.change_path(path)?, perm)
+        self.latencies.apply(FsOp::SetPermissions);
+        let rerooted = self.on_path(path.as_ref(), |rerooted| {
+            fs::set_permissions(&rerooted, perm)?;
+            Ok(rerooted)
+        })?;
+        self.events
+            .push(FsEvent::SetPermissions(self.sandbox_path(&rerooted)?));
+        Ok(())
     }
 
     fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata> {
-        fs::symlink_metadata(self.change_path(path)?)
+        self.latencies.apply(FsOp::Metadata);
+        let path = path.as_ref();
+        self.change_path_nofollow(path)
+            .and_then(fs::symlink_metadata)
+            .map_err(|e| self.sandbox_error(path.display(), e))
+    }
+
+    fn truncate<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.latencies.apply(FsOp::Write);
+        let rerooted = self.on_path(path.as_ref(), |rerooted| {
+            truncate_at(&rerooted)?;
+            Ok(rerooted)
+        })?;
+        self.events
+            .push(FsEvent::Write(self.sandbox_path(&rerooted)?));
+        Ok(())
     }
 
     fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&mut self, path: P, contents: C) -> io::Result<()> {
-        fs::write(self.change_path(path)?, contents)
+        self.latencies.apply(FsOp::Write);
+        let path = path.as_ref();
+        self.write_rerooted(path, contents.as_ref())
+            .map_err(|e| self.sandbox_error(path.display(), e))
+    }
+
+    fn write_counted<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<u64> {
+        self.latencies.apply(FsOp::Write);
+        let path = path.as_ref();
+        self.write_counted_rerooted(path, contents.as_ref())
+            .map_err(|e| self.sandbox_error(path.display(), e))
+    }
+
+    fn write_at<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        offset: u64,
+        data: C,
+    ) -> io::Result<()> {
+        self.latencies.apply(FsOp::Write);
+        let path = path.as_ref();
+        let rerooted = self
+            .change_path(path)
+            .and_then(|rerooted| {
+                self.check_quota(data.as_ref().len() as u64)?;
+                write_at_path(&rerooted, offset, data.as_ref())?;
+                self.count_bytes(data.as_ref().len() as u64);
+                Ok(rerooted)
+            })
+            .map_err(|e| self.sandbox_error(path.display(), e))?;
+        self.events
+            .push(FsEvent::Write(self.sandbox_path(&rerooted)?));
+        Ok(())
+    }
+
+    fn append_locked<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<()> {
+        self.latencies.apply(FsOp::Write);
+        let path = path.as_ref();
+        let rerooted = self
+            .change_path(path)
+            .and_then(|rerooted| {
+                self.check_quota(contents.as_ref().len() as u64)?;
+                append_locked_at(&rerooted, contents.as_ref())?;
+                self.count_bytes(contents.as_ref().len() as u64);
+                Ok(rerooted)
+            })
+            .map_err(|e| self.sandbox_error(path.display(), e))?;
+        self.events
+            .push(FsEvent::Write(self.sandbox_path(&rerooted)?));
+        Ok(())
     }
 
     fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
-        self.change_path(path).map(|p| p.exists()).unwrap_or(false)
+        self.try_exists(path).unwrap_or(false)
+    }
+
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        self.latencies.apply(FsOp::Metadata);
+        let path = path.as_ref();
+        match self.change_path(path) {
+            Ok(rerooted) => rerooted
+                .try_exists()
+                .map_err(|e| self.sandbox_error(path.display(), e)),
+            Err(ref e)
+                if e.kind() == io::ErrorKind::InvalidInput
+                    || e.kind() == io::ErrorKind::NotFound =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(self.sandbox_error(path.display(), e)),
+        }
+    }
+
+    fn kind<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<FileKind>> {
+        self.latencies.apply(FsOp::Metadata);
+        let path = path.as_ref();
+        match self.change_path_nofollow(path) {
+            Ok(rerooted) => kind_at(&rerooted).map_err(|e| self.sandbox_error(path.display(), e)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(self.sandbox_error(path.display(), e)),
+        }
+    }
+}
+
+/// A file opened using [`TempFs::open_sandboxed()`](struct.TempFs.html#method.open_sandboxed).
+///
+/// This borrows the `TempFs` it was opened from for as long as it's open, so that writes can be
+/// counted towards its byte quota.
+#[derive(Debug)]
+pub struct SandboxFile<'a> {
+    fs: &'a mut TempFs,
+    file: fs::File,
+    path: PathBuf,
+}
+
+impl<'a> SandboxFile<'a> {
+    /// Returns the path this file was opened with, relative to the sandbox.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Queries metadata about the file at this file's path.
+    ///
+    /// The path is resolved again, so if the file has since been replaced, this describes the
+    /// replacement.
+    pub fn metadata(&self) -> io::Result<fs::Metadata> {
+        self.fs.metadata(&self.path)
+    }
+
+    /// Attempts to sync all OS-internal metadata to disk.
+    ///
+    /// See [std::fs::File::sync_all](https://doc.rust-lang.org/std/fs/struct.File.html#method.sync_all)
+    /// for more information.
+    pub fn sync_all(&self) -> io::Result<()> {
+        self.file.sync_all().map_err(|e| self.error(e))
+    }
+
+    /// Rewrites an error from an operation on the file to refer to its sandbox path.
+    fn error(&self, e: io::Error) -> io::Error {
+        self.fs.sandbox_error(self.path.display(), e)
+    }
+}
+
+impl<'a> Read for SandboxFile<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf).map_err(|e| self.error(e))
+    }
+}
+
+impl<'a> Write for SandboxFile<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self
+            .fs
+            .check_quota(buf.len() as u64)
+            .and_then(|()| self.file.write(buf))
+            .map_err(|e| self.error(e))?;
+        self.fs.count_bytes(written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush().map_err(|e| self.error(e))
+    }
+}
+
+impl<'a> Seek for SandboxFile<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos).map_err(|e| self.error(e))
+    }
+}
+
+/// An error from an operation within a `TempFs`, whose message refers to paths within the
+/// sandbox.
+#[derive(Debug)]
+struct SandboxError {
+    message: String,
+    source: io::Error,
+}
+
+impl fmt::Display for SandboxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for SandboxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Removes the directory tree at `path` like `fs::remove_dir_all()`, but first checks that every
+/// entry in it lies within `root`, removing nothing otherwise.
+///
+/// Symbolic links are removed without being followed, so nothing they point to is affected.
+fn remove_tree_within(path: &Path, root: &Path) -> io::Result<()> {
+    let file_type = fs::symlink_metadata(path)?.file_type();
+    if !file_type.is_dir() && !file_type.is_symlink() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotADirectory,
+            "Not a directory",
+        ));
+    }
+
+    let mut entries = Vec::new();
+    collect_tree(path, file_type, &mut entries)?;
+    for (entry, _) in &entries {
+        let parent = entry.parent().ok_or_else(invalid_path)?.canonicalize()?;
+        if !parent.starts_with(root) {
+            return Err(invalid_path());
+        }
+    }
+
+    for (entry, file_type) in entries {
+        if file_type.is_dir() {
+            fs::remove_dir(&entry)?;
+        } else {
+            remove_link_or_file(&entry, file_type)?;
+        }
+    }
+    Ok(())
+}
+
+/// Appends the entries of the tree at `path` to `entries`, each directory's contents before the
+/// directory itself. Symbolic links aren't followed.
+fn collect_tree(
+    path: &Path,
+    file_type: fs::FileType,
+    entries: &mut Vec<(PathBuf, fs::FileType)>,
+) -> io::Result<()> {
+    if file_type.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            collect_tree(&entry.path(), entry.file_type()?, entries)?;
+        }
+    }
+    entries.push((path.to_path_buf(), file_type));
+    Ok(())
+}
+
+/// Removes a file or symbolic link.
+fn remove_link_or_file(path: &Path, file_type: fs::FileType) -> io::Result<()> {
+    if is_dir_link(file_type) {
+        fs::remove_dir(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Returns whether `file_type` is a symbolic link which has to be removed as a directory, as
+/// links to directories do on Windows.
+#[cfg(windows)]
+fn is_dir_link(file_type: fs::FileType) -> bool {
+    use std::os::windows::fs::FileTypeExt;
+    file_type.is_symlink_dir()
+}
+
+#[cfg(not(windows))]
+fn is_dir_link(_file_type: fs::FileType) -> bool {
+    false
+}
+
+fn quota_exceeded() -> io::Error {
+    io::Error::new(io::ErrorKind::StorageFull, "TempFs byte quota exceeded")
+}
+
+fn invalid_path() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "Invalid path")
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use std::io;
+    use std::path::Path;
+
+    use super::TempFs;
+
+    #[test]
+    #[cfg(unix)]
+    fn sandbox_error__message_with_either_root__roots_stripped_and_source_kept() {
+        let mut fs = TempFs::new().expect("Failed to create new TempFs");
+        // Simulate a temp directory reached through a symbolic link, like `/var` on macOS
+        fs.canonical_root =
+            Path::new("/private").join(fs.temp_dir.path().strip_prefix("/").unwrap());
+        let message = format!(
+            "{} and {}",
+            fs.canonical_root.join("a").display(),
+            fs.temp_dir.path().join("b").display()
+        );
+
+        let error = fs.sandbox_error("a", io::Error::new(io::ErrorKind::NotFound, message));
+
+        assert_eq!("a: /a and /b", error.to_string());
+        let source = error.get_ref().unwrap().source().unwrap();
+        assert!(source.to_string().starts_with("/private/"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn remove_dir_all__root_behind_symlink__tree_removed() {
+        use fs::Fs;
+        use std::os::unix::fs::symlink;
+        use tempfile::{tempdir, TempDir};
+
+        let real = tempdir().expect("Failed to create temp dir");
+        let links = tempdir().expect("Failed to create temp dir");
+        let link = links.path().join("link");
+        symlink(real.path(), &link).expect("Failed to create symlink");
+        let mut fs = TempFs::new().expect("Failed to create new TempFs");
+        // Reach the temp directory through a symbolic link, like `/var` on macOS
+        fs.temp_dir = TempDir::new_in(&link).expect("Failed to create temp dir");
+        fs.canonical_root = fs.temp_dir.path().canonicalize().unwrap();
+        fs.create_dir_all("/dir/sub")
+            .expect("Failed to create directories");
+        fs.write("/dir/sub/test.txt", "contents")
+            .expect("Failed to write test file");
+
+        fs.remove_dir_all("/dir").unwrap();
+
+        assert!(!fs.exists("/dir"));
+    }
+
+    #[test]
+    fn sandbox_path__path_outside_both_roots__invalid_input() {
+        let fs = TempFs::new().expect("Failed to create new TempFs");
+
+        let result = fs.sandbox_path(Path::new("/elsewhere/file.txt"));
+
+        assert_eq!(io::ErrorKind::InvalidInput, result.unwrap_err().kind());
     }
 }
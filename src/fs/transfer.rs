@@ -0,0 +1,83 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use fs::{FileKind, Fs};
+
+/// Copies the contents of the file at `from` in `src` to `to` in `dst`, which may be a different
+/// kind of filesystem provider. Returns the number of bytes copied.
+///
+/// This is useful for e.g. setting up a [`MemoryFs`](struct.MemoryFs.html) from fixture files
+/// on disk. The file is read into memory in its entirety before being written, since not every
+/// provider supports opening files.
+///
+/// ## Example
+///
+/// ```
+/// use io_providers::fs::{self, Fs, MemoryFs, TempFs};
+///
+/// let mut src = TempFs::new().unwrap();
+/// src.write("/fixture.txt", "contents").unwrap();
+/// let mut dst = MemoryFs::new();
+///
+/// fs::transfer(&src, &mut dst, "/fixture.txt", "/copy.txt").unwrap();
+///
+/// assert_eq!("contents", dst.read_to_string("/copy.txt").unwrap());
+/// ```
+pub fn transfer<Src: Fs, Dst: Fs, P: AsRef<Path>, Q: AsRef<Path>>(
+    src: &Src,
+    dst: &mut Dst,
+    from: P,
+    to: Q,
+) -> io::Result<u64> {
+    let contents = src.read(from)?;
+    dst.write(to, &contents)?;
+    Ok(contents.len() as u64)
+}
+
+/// Recursively copies the directory `from` in `src` and all of its contents to `to` in `dst`,
+/// like [`transfer()`](fn.transfer.html) does for a single file. Returns the total number of
+/// bytes copied.
+///
+/// `to` and any missing parents of it are created. Symbolic links are followed, so the copy
+/// contains the files they point to rather than the links themselves. A link to a directory
+/// which is already being copied (such as one pointing back up the tree) is skipped, so cycles
+/// can't make the copy go on forever.
+pub fn transfer_all<Src: Fs, Dst: Fs, P: AsRef<Path>, Q: AsRef<Path>>(
+    src: &Src,
+    dst: &mut Dst,
+    from: P,
+    to: Q,
+) -> io::Result<u64> {
+    let from = from.as_ref();
+    let mut ancestors = vec![src.canonicalize(from)?];
+    transfer_tree(src, dst, from, to.as_ref(), &mut ancestors)
+}
+
+/// Copies the tree under `from` to `to` like `transfer_all()`, where `ancestors` holds the
+/// canonical paths of the directories currently being copied, to detect cycles.
+fn transfer_tree<Src: Fs, Dst: Fs>(
+    src: &Src,
+    dst: &mut Dst,
+    from: &Path,
+    to: &Path,
+    ancestors: &mut Vec<PathBuf>,
+) -> io::Result<u64> {
+    dst.create_dir_all(to)?;
+
+    let mut copied = 0;
+    for entry in src.list_dir(from)? {
+        let dst_path = to.join(entry.file_name());
+        if src.stat(entry.path())?.file_type() == FileKind::Dir {
+            let canonical = src.canonicalize(entry.path())?;
+            if ancestors.contains(&canonical) {
+                continue;
+            }
+            ancestors.push(canonical);
+            copied += transfer_tree(src, dst, entry.path(), &dst_path, ancestors)?;
+            ancestors.pop();
+        } else {
+            copied += transfer(src, dst, entry.path(), &dst_path)?;
+        }
+    }
+    Ok(copied)
+}
@@ -15,6 +15,8 @@
 //!   stream input and inspecting output
 //! * [`TempFs`](fs/trait.TempFs.html) for performing filesystem access in a `chroot`-like sandbox
 //!   isolated from the rest of the filesystem
+//! * [`MemoryFs`](fs/struct.MemoryFs.html) for performing filesystem access entirely in memory,
+//!   optionally shared between several handles
 //!
 //! Each provider trait can be used independently, however there is also the all-encompassing
 //! [`Io`](trait.Io.html) which provides access to all of them. If you have a variety of I/O
@@ -51,23 +53,41 @@
 //! }
 //! ```
 
+#[cfg(unix)]
+extern crate libc;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+#[cfg(feature = "hash")]
+extern crate sha2;
 extern crate tempfile;
+#[cfg(feature = "zip")]
+extern crate zip;
 
 use std::io;
+use std::mem;
+use std::path::Path;
 
 pub mod env;
 pub mod fs;
 pub mod std_streams;
 
-pub use env::{Env, NativeEnv, SimulatedEnv};
-pub use fs::{Fs, NativeFs, OpenOptions, TempFs};
-pub use std_streams::{NativeStdStreams, SimulatedStdStreams, StdStreams};
+pub use env::{AnyEnv, Env, EnvExt, NativeEnv, SimulatedEnv};
+pub use fs::{AnyFs, DryRunFs, Fs, FsExt, MemoryFs, NativeFs, OpenOptions, TempFs};
+pub use std_streams::{
+    AnyStdStreams, NativeStdStreams, SimulatedStdStreams, StdStreams, StdStreamsExt,
+};
 
 /// Provides access to the process environment, filesystem, and standard streams.
 ///
 /// See [`env::Env`](env/trait.Env.html),
 /// [`std_streams::StdStreams`](std_streams/trait.StdStreams.html) and
 /// [`fs::Fs`](fs/trait.Fs.html) for details.
+///
+/// The environment and filesystem can be read through a shared reference (e.g. using
+/// `io.env().var()`), so code which only inspects them can take `&Io`. The standard streams can
+/// only be accessed through a mutable reference, since reading and writing them changes them.
 pub trait Io {
     // The type of the environment provider.
     type E: env::Env;
@@ -92,6 +112,51 @@ pub trait Io {
 
     /// Gets a mutable reference to the [`std_streams::StdStreams`](std_streams/trait.StdStreams.html).
     fn std_streams(&mut self) -> &mut Self::S;
+
+    /// Gets a mutable reference to the stream provider, like
+    /// [`std_streams()`](#tymethod.std_streams); this is named to match `env_mut()` and `fs_mut()`.
+    fn std_streams_mut(&mut self) -> &mut Self::S {
+        self.std_streams()
+    }
+
+    /// Gets a mutable reference to the [`std_streams::StdStreams`](std_streams/trait.StdStreams.html)
+    /// as a trait object, for passing to code which isn't generic over the provider type.
+    ///
+    /// There are no equivalents for `Env` and `Fs`, since their generic methods prevent them from
+    /// being used as trait objects.
+    fn std_streams_dyn(&mut self) -> &mut dyn std_streams::StdStreams {
+        self.std_streams()
+    }
+
+    /// Opens an input named by a command-line argument for reading.
+    ///
+    /// Following the Unix convention, an argument of `-` refers to the input stream; any other
+    /// argument is the path of a file, which is opened using the filesystem provider.
+    fn open_input_arg<P: AsRef<Path>>(&mut self, arg: P) -> io::Result<Box<dyn io::Read + '_>> {
+        let arg = arg.as_ref();
+        if arg == Path::new("-") {
+            self.input_source(None)
+        } else {
+            self.input_source(Some(arg))
+        }
+    }
+
+    /// Opens the input of a program which reads either the input stream or a file given as an
+    /// optional argument: the input stream if `path` is `None`, or otherwise the file at `path`,
+    /// opened using the filesystem provider.
+    ///
+    /// Unlike [`open_input_arg()`](#method.open_input_arg), a path of `-` is opened as a file.
+    fn input_source(&mut self, path: Option<&Path>) -> io::Result<Box<dyn io::Read + '_>> {
+        match path {
+            None => Ok(Box::new(self.std_streams().input())),
+            Some(path) => {
+                let file = self
+                    .fs_mut()
+                    .open(path, fs::OpenOptions::new().read(true))?;
+                Ok(Box::new(file))
+            }
+        }
+    }
 }
 
 /// `Io` implementation using the native system.
@@ -143,6 +208,8 @@ impl Io for NativeIo {
 
 /// `Io` implementation using a simulated environment.
 ///
+/// The environment's current directory is initially `/`, the root of the temporary filesystem.
+///
 /// See `env::SimulatedEnv` and `std_streams::SimulatedStdStreams` for more information.
 pub struct SimulatedIo {
     env: env::SimulatedEnv,
@@ -153,12 +220,134 @@ pub struct SimulatedIo {
 impl SimulatedIo {
     /// Creates a new `SimulatedIo`.
     pub fn new() -> io::Result<SimulatedIo> {
+        let mut env = env::SimulatedEnv::new();
+        env.set_current_dir("/")?;
+
         Ok(SimulatedIo {
-            env: env::SimulatedEnv::new(),
+            env,
             fs: fs::TempFs::new()?,
             stream: std_streams::SimulatedStdStreams::new(),
         })
     }
+
+    /// Returns the location on the host of the root of the simulated filesystem.
+    ///
+    /// See [`TempFs::path()`](fs/struct.TempFs.html#method.path) for more information.
+    pub fn fs_root(&self) -> &Path {
+        self.fs.path()
+    }
+
+    /// Writes a file to the simulated filesystem, creating any missing parent directories; this
+    /// is convenient for setting up test fixtures.
+    pub fn seed_file<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            self.fs.create_dir_all(parent)?;
+        }
+        self.fs.write(path, contents)
+    }
+
+    /// Runs `f` with `streams` in place of this `SimulatedIo`'s standard streams, then restores the
+    /// original streams. Returns the result of `f`, along with `streams` so that what `f` wrote
+    /// to them can be inspected.
+    ///
+    /// If `f` panics, the original streams aren't restored.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use io_providers::{Io, SimulatedIo, SimulatedStdStreams, StdStreams};
+    ///
+    /// let mut io = SimulatedIo::new().unwrap();
+    /// let (_, captured) = io.with_std_streams(SimulatedStdStreams::new(), |io| {
+    ///     write!(io.std_streams().output(), "captured").unwrap();
+    /// });
+    ///
+    /// assert_eq!(b"captured", captured.read_output());
+    /// assert!(io.std_streams().read_output().is_empty());
+    /// ```
+    pub fn with_std_streams<R, F: FnOnce(&mut SimulatedIo) -> R>(
+        &mut self,
+        streams: std_streams::SimulatedStdStreams,
+        f: F,
+    ) -> (R, std_streams::SimulatedStdStreams) {
+        let original = mem::replace(&mut self.stream, streams);
+        let result = f(self);
+        (result, mem::replace(&mut self.stream, original))
+    }
+
+    /// Returns the names of the environment variables which have been read from the simulated
+    /// environment, in the order they were first read.
+    ///
+    /// See [`SimulatedEnv::var_reads()`](env/struct.SimulatedEnv.html#method.var_reads) for more
+    /// information.
+    pub fn env_reads(&self) -> Vec<String> {
+        self.env.var_reads()
+    }
+
+    /// Asserts that exactly `expected_output` and `expected_error` have been written to the
+    /// output and error streams.
+    ///
+    /// On a mismatch, this panics with a line-by-line diff of each stream which doesn't match,
+    /// where lines prefixed with `-` were expected and lines prefixed with `+` were written.
+    /// Output which isn't valid UTF-8 is converted lossily first.
+    pub fn assert_transcript(&self, expected_output: &str, expected_error: &str) {
+        let mut report = String::new();
+        for &(name, expected, actual) in &[
+            ("output", expected_output, self.stream.read_output()),
+            ("error", expected_error, self.stream.read_error()),
+        ] {
+            let actual = String::from_utf8_lossy(actual);
+            if actual != expected {
+                report.push_str(&format!("--- {} ---\n", name));
+                report.push_str(&line_diff(expected, &actual));
+            }
+        }
+        if !report.is_empty() {
+            panic!("transcript mismatch:\n{}", report);
+        }
+    }
+}
+
+/// Diffs `expected` against `actual` line by line, keeping their longest common subsequence of
+/// lines and marking the others with `-` (expected) and `+` (actual).
+fn line_diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.split_inclusive('\n').collect();
+    let actual: Vec<&str> = actual.split_inclusive('\n').collect();
+
+    // common[i][j] is the length of the longest common subsequence of expected[i..] and actual[j..]
+    let mut common = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            diff.push_str(&format!("  {:?}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1])
+        {
+            diff.push_str(&format!("- {:?}\n", expected[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+ {:?}\n", actual[j]));
+            j += 1;
+        }
+    }
+    diff
 }
 
 impl Io for SimulatedIo {
@@ -186,3 +375,82 @@ impl Io for SimulatedIo {
         &mut self.stream
     }
 }
+
+/// `Io` implementation using either the native system or a simulated environment, chosen at
+/// runtime.
+///
+/// This makes it possible for the same binary to run against real or simulated I/O, for example
+/// in integration tests.
+///
+/// See `NativeIo` and `SimulatedIo` for more information.
+pub struct AnyIo {
+    env: env::AnyEnv,
+    fs: fs::AnyFs,
+    stream: std_streams::AnyStdStreams,
+}
+
+impl AnyIo {
+    /// Creates a new `AnyIo` which is simulated if the environment variable `var` is set, and
+    /// native otherwise.
+    pub fn from_env(var: &str) -> io::Result<AnyIo> {
+        if std::env::var_os(var).is_some() {
+            SimulatedIo::new().map(AnyIo::from)
+        } else {
+            Ok(AnyIo::from(NativeIo::new()))
+        }
+    }
+
+    /// Returns whether this uses a simulated environment rather than the native system.
+    pub fn is_simulated(&self) -> bool {
+        match self.fs {
+            fs::AnyFs::Native(_) => false,
+            fs::AnyFs::Simulated(_) => true,
+        }
+    }
+}
+
+impl From<NativeIo> for AnyIo {
+    fn from(io: NativeIo) -> AnyIo {
+        AnyIo {
+            env: env::AnyEnv::Native(io.env),
+            fs: fs::AnyFs::Native(io.fs),
+            stream: std_streams::AnyStdStreams::Native(io.stream),
+        }
+    }
+}
+
+impl From<SimulatedIo> for AnyIo {
+    fn from(io: SimulatedIo) -> AnyIo {
+        AnyIo {
+            env: env::AnyEnv::Simulated(Box::new(io.env)),
+            fs: fs::AnyFs::Simulated(io.fs),
+            stream: std_streams::AnyStdStreams::Simulated(io.stream),
+        }
+    }
+}
+
+impl Io for AnyIo {
+    type E = env::AnyEnv;
+    type F = fs::AnyFs;
+    type S = std_streams::AnyStdStreams;
+
+    fn env(&self) -> &env::AnyEnv {
+        &self.env
+    }
+
+    fn env_mut(&mut self) -> &mut env::AnyEnv {
+        &mut self.env
+    }
+
+    fn fs(&self) -> &fs::AnyFs {
+        &self.fs
+    }
+
+    fn fs_mut(&mut self) -> &mut fs::AnyFs {
+        &mut self.fs
+    }
+
+    fn std_streams(&mut self) -> &mut std_streams::AnyStdStreams {
+        &mut self.stream
+    }
+}
@@ -0,0 +1,88 @@
+use std::io;
+
+use std_streams::{NativeStdStreams, NewlineStyle, SimulatedStdStreams, StdStreams};
+
+/// Provides access to either the process's standard streams (using a
+/// [`NativeStdStreams`](struct.NativeStdStreams.html)) or
+/// [`SimulatedStdStreams`](struct.SimulatedStdStreams.html), chosen at runtime.
+///
+/// See [`AnyIo`](../struct.AnyIo.html) for more information.
+#[derive(Debug)]
+pub enum AnyStdStreams {
+    /// The process's standard streams.
+    Native(NativeStdStreams),
+    /// Simulated standard streams.
+    Simulated(SimulatedStdStreams),
+}
+
+impl StdStreams for AnyStdStreams {
+    fn input(&mut self) -> &mut dyn io::Read {
+        match *self {
+            AnyStdStreams::Native(ref mut streams) => streams.input(),
+            AnyStdStreams::Simulated(ref mut streams) => streams.input(),
+        }
+    }
+
+    fn buffered_input(&mut self) -> Box<dyn io::BufRead + '_> {
+        match *self {
+            AnyStdStreams::Native(ref mut streams) => streams.buffered_input(),
+            AnyStdStreams::Simulated(ref mut streams) => streams.buffered_input(),
+        }
+    }
+
+    fn output(&mut self) -> &mut dyn io::Write {
+        match *self {
+            AnyStdStreams::Native(ref mut streams) => streams.output(),
+            AnyStdStreams::Simulated(ref mut streams) => streams.output(),
+        }
+    }
+
+    fn output_written(&self) -> bool {
+        match *self {
+            AnyStdStreams::Native(ref streams) => streams.output_written(),
+            AnyStdStreams::Simulated(ref streams) => streams.output_written(),
+        }
+    }
+
+    fn input_len_hint(&self) -> Option<u64> {
+        match *self {
+            AnyStdStreams::Native(ref streams) => streams.input_len_hint(),
+            AnyStdStreams::Simulated(ref streams) => streams.input_len_hint(),
+        }
+    }
+
+    fn read_password(&mut self) -> io::Result<String> {
+        match *self {
+            AnyStdStreams::Native(ref mut streams) => streams.read_password(),
+            AnyStdStreams::Simulated(ref mut streams) => streams.read_password(),
+        }
+    }
+
+    fn newline_style(&self) -> NewlineStyle {
+        match *self {
+            AnyStdStreams::Native(ref streams) => streams.newline_style(),
+            AnyStdStreams::Simulated(ref streams) => streams.newline_style(),
+        }
+    }
+
+    fn terminal_size(&self) -> Option<(u16, u16)> {
+        match *self {
+            AnyStdStreams::Native(ref streams) => streams.terminal_size(),
+            AnyStdStreams::Simulated(ref streams) => streams.terminal_size(),
+        }
+    }
+
+    fn error(&mut self) -> &mut dyn io::Write {
+        match *self {
+            AnyStdStreams::Native(ref mut streams) => streams.error(),
+            AnyStdStreams::Simulated(ref mut streams) => streams.error(),
+        }
+    }
+
+    fn log(&mut self) -> &mut dyn io::Write {
+        match *self {
+            AnyStdStreams::Native(ref mut streams) => streams.log(),
+            AnyStdStreams::Simulated(ref mut streams) => streams.log(),
+        }
+    }
+}
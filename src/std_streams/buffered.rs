@@ -0,0 +1,196 @@
+use std::io;
+use std::io::{BufRead, Read};
+
+/// An input stream which can be read either directly or through a buffer, without losing data
+/// when switching between the two.
+///
+/// In raw mode, each read which finds the buffer empty is passed straight through to the
+/// underlying reader, so it returns whatever a single read of that reader returns. In buffered
+/// mode, reads go through the buffer like those of a `BufReader`. Either way, the `BufRead`
+/// implementation is buffered, and data it has buffered is returned by subsequent reads.
+#[derive(Debug)]
+pub struct InputBuffer<R> {
+    reader: io::BufReader<R>,
+    buffered: bool,
+}
+
+impl<R: Read> InputBuffer<R> {
+    /// Creates a new `InputBuffer` in raw mode.
+    pub fn new(inner: R) -> InputBuffer<R> {
+        InputBuffer {
+            reader: io::BufReader::new(inner),
+            buffered: false,
+        }
+    }
+
+    /// Sets whether reads go through the buffer.
+    pub fn set_buffered(&mut self, buffered: bool) {
+        self.buffered = buffered;
+    }
+
+    /// Discards any data which has been buffered but not yet read.
+    pub fn discard_buffer(&mut self) {
+        let len = self.reader.buffer().len();
+        self.reader.consume(len);
+    }
+
+    /// Returns the length of the data which has been buffered but not yet read.
+    pub fn buffered_len(&self) -> usize {
+        self.reader.buffer().len()
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        self.reader.get_ref()
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly skips any data which has already been buffered.
+    pub fn get_mut(&mut self) -> &mut R {
+        self.reader.get_mut()
+    }
+}
+
+impl<R: Read> Read for InputBuffer<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buffered || !self.reader.buffer().is_empty() {
+            self.reader.read(buf)
+        } else {
+            self.reader.get_mut().read(buf)
+        }
+    }
+}
+
+impl<R: Read> BufRead for InputBuffer<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt)
+    }
+}
+
+/// A `BufRead` implementation which reads from the underlying reader a byte at a time, so that it
+/// never takes more data from it than it has returned.
+pub struct ByteReader<R> {
+    inner: R,
+    byte: [u8; 1],
+    filled: bool,
+}
+
+impl<R: Read> ByteReader<R> {
+    /// Creates a new `ByteReader` over `inner`.
+    pub fn new(inner: R) -> ByteReader<R> {
+        ByteReader {
+            inner,
+            byte: [0],
+            filled: false,
+        }
+    }
+}
+
+impl<R: Read> Read for ByteReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.filled && !buf.is_empty() {
+            buf[0] = self.byte[0];
+            self.filled = false;
+            Ok(1)
+        } else {
+            self.inner.read(buf)
+        }
+    }
+}
+
+impl<R: Read> BufRead for ByteReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if !self.filled {
+            self.filled = self.inner.read(&mut self.byte)? == 1;
+        }
+        Ok(if self.filled { &self.byte } else { &[] })
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if amt > 0 {
+            self.filled = false;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::{ByteReader, InputBuffer};
+    use std::io;
+    use std::io::{BufRead, Read};
+
+    #[test]
+    fn read__raw_mode_after_buffered_line__returns_remaining_buffered_data_first() {
+        let mut input = InputBuffer::new(&b"line\nrest"[..]);
+        let mut line = String::new();
+        let mut rest = String::new();
+
+        input.read_line(&mut line).unwrap();
+        input.read_to_string(&mut rest).unwrap();
+
+        assert_eq!("line\n", line);
+        assert_eq!("rest", rest);
+    }
+
+    #[test]
+    fn read_line__byte_reader__rest_left_in_underlying_reader() {
+        let mut inner = &b"line\nrest"[..];
+        let mut line = String::new();
+
+        ByteReader::new(&mut inner).read_line(&mut line).unwrap();
+
+        assert_eq!("line\n", line);
+        assert_eq!(b"rest", inner);
+    }
+
+    /// A reader which counts the reads made from it.
+    struct CountingReader<R> {
+        inner: R,
+        reads: usize,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            self.inner.read(buf)
+        }
+    }
+
+    /// Reads all of `input` a byte at a time, returning the bytes read and the number of reads
+    /// made from `input`.
+    fn read_bytewise(input: &[u8], buffered: bool) -> (Vec<u8>, usize) {
+        let mut reader = InputBuffer::new(CountingReader {
+            inner: input,
+            reads: 0,
+        });
+        reader.set_buffered(buffered);
+        let mut result = Vec::new();
+        let mut byte = [0];
+
+        while reader.read(&mut byte).unwrap() == 1 {
+            result.push(byte[0]);
+        }
+        (result, reader.get_mut().reads)
+    }
+
+    #[test]
+    fn read__many_lines_bytewise__buffered_same_bytes_with_fewer_reads() {
+        let input: Vec<u8> = (0..1000)
+            .flat_map(|i| format!("line {}\n", i).into_bytes())
+            .collect();
+
+        let (raw, raw_reads) = read_bytewise(&input, false);
+        let (buffered, buffered_reads) = read_bytewise(&input, true);
+
+        assert_eq!(input, raw);
+        assert_eq!(input, buffered);
+        assert_eq!(input.len() + 1, raw_reads);
+        assert!(buffered_reads < 10, "{} reads", buffered_reads);
+    }
+}
@@ -0,0 +1,238 @@
+use std::io;
+use std::io::BufRead;
+
+use std_streams::{read_line_trimmed, StdStreams};
+
+/// A text style which can be applied using
+/// [`StdStreamsExt::write_styled()`](trait.StdStreamsExt.html#method.write_styled).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// Bold or increased intensity.
+    Bold,
+    /// Faint or decreased intensity.
+    Dim,
+    /// Underlined.
+    Underline,
+    /// Red foreground.
+    Red,
+    /// Green foreground.
+    Green,
+    /// Yellow foreground.
+    Yellow,
+    /// Blue foreground.
+    Blue,
+}
+
+impl Style {
+    /// Returns the ANSI escape sequence which enables this style.
+    fn ansi_code(self) -> &'static str {
+        match self {
+            Style::Bold => "\x1b[1m",
+            Style::Dim => "\x1b[2m",
+            Style::Underline => "\x1b[4m",
+            Style::Red => "\x1b[31m",
+            Style::Green => "\x1b[32m",
+            Style::Yellow => "\x1b[33m",
+            Style::Blue => "\x1b[34m",
+        }
+    }
+}
+
+/// A line ending, as written by
+/// [`StdStreamsExt::writeln_output()`](trait.StdStreamsExt.html#method.writeln_output).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NewlineStyle {
+    /// `\n`, as used on Unix.
+    Lf,
+    /// `\r\n`, as used on Windows.
+    CrLf,
+}
+
+impl NewlineStyle {
+    /// Returns the line ending used by the platform: `CrLf` on Windows, and `Lf` elsewhere.
+    pub fn native() -> NewlineStyle {
+        if cfg!(windows) {
+            NewlineStyle::CrLf
+        } else {
+            NewlineStyle::Lf
+        }
+    }
+
+    /// Returns the line ending itself.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NewlineStyle::Lf => "\n",
+            NewlineStyle::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Higher-level stream operations, implemented in terms of [`StdStreams`](trait.StdStreams.html).
+///
+/// These are kept out of `StdStreams` so that implementers of that trait only need to provide the
+/// three streams; every `StdStreams` implementation gets these methods for free.
+///
+/// # Examples
+///
+/// ```
+/// extern crate io_providers;
+///
+/// use io_providers::{SimulatedStdStreams, StdStreamsExt};
+///
+/// fn main() {
+///     let mut streams = SimulatedStdStreams::new();
+///     streams.write_input("Alice\n".as_bytes());
+///
+///     let name = streams.prompt("Name: ").unwrap();
+///     streams.write_output(format!("Hello, {}!", name)).unwrap();
+///
+///     assert_eq!("Name: Hello, Alice!", streams.read_output_str().unwrap());
+/// }
+/// ```
+pub trait StdStreamsExt: StdStreams {
+    /// Reads a line of input into a new `String`, including its trailing newline (if any).
+    ///
+    /// The line is read using [`StdStreams::buffered_input()`], so any input following it which
+    /// was read at the same time is kept for subsequent reads.
+    ///
+    /// [`StdStreams::buffered_input()`]: trait.StdStreams.html#method.buffered_input
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        self.buffered_input().read_line(&mut line)?;
+        Ok(line)
+    }
+
+    /// Returns an iterator over the lines of the input stream, without their trailing newlines.
+    ///
+    /// See [`std::io::BufRead::lines`](https://doc.rust-lang.org/std/io/trait.BufRead.html#method.lines)
+    /// for more information.
+    fn input_lines(&mut self) -> io::Lines<Box<dyn io::BufRead + '_>> {
+        self.buffered_input().lines()
+    }
+
+    /// Writes `message` to the output stream, then reads a line of input and returns it without
+    /// its trailing newline.
+    fn prompt(&mut self, message: &str) -> io::Result<String> {
+        self.write_output(message)?;
+        self.output().flush()?;
+        read_line_trimmed(&mut *self.buffered_input())
+    }
+
+    /// Writes all of `data` to the output stream.
+    fn write_output<B: AsRef<[u8]>>(&mut self, data: B) -> io::Result<()> {
+        self.output().write_all(data.as_ref())
+    }
+
+    /// Writes `s` to the output stream followed by a line ending in the style given by
+    /// [`StdStreams::newline_style()`], then flushes the output stream.
+    ///
+    /// [`StdStreams::newline_style()`]: trait.StdStreams.html#method.newline_style
+    fn writeln_output(&mut self, s: &str) -> io::Result<()> {
+        let newline = self.newline_style().as_str();
+        let output = self.output();
+        output.write_all(s.as_bytes())?;
+        output.write_all(newline.as_bytes())?;
+        output.flush()
+    }
+
+    /// Writes `text` to the output stream, surrounded by the ANSI escape sequences which apply
+    /// `style` to it and then reset the style.
+    fn write_styled(&mut self, style: Style, text: &str) -> io::Result<()> {
+        write!(self.output(), "{}{}\x1b[0m", style.ansi_code(), text)
+    }
+}
+
+impl<T: StdStreams + ?Sized> StdStreamsExt for T {}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::{NewlineStyle, StdStreamsExt, Style};
+    use std_streams::SimulatedStdStreams;
+
+    #[test]
+    fn read_line__queued_line__returns_line_with_newline() {
+        let mut provider = SimulatedStdStreams::new();
+        provider.write_input("first\n".as_bytes());
+
+        let result = provider.read_line().unwrap();
+
+        assert_eq!("first\n", result);
+    }
+
+    #[test]
+    fn read_line__two_lines_in_one_chunk__second_line_kept() {
+        let mut provider = SimulatedStdStreams::new();
+        provider.write_input("first\nsecond\n".as_bytes());
+
+        assert_eq!("first\n", provider.read_line().unwrap());
+        assert_eq!("second\n", provider.read_line().unwrap());
+    }
+
+    #[test]
+    fn input_lines__multiple_lines__yields_each() {
+        let mut provider = SimulatedStdStreams::new();
+        provider.write_input("a\nb\n".as_bytes());
+        provider.write_input("c".as_bytes());
+
+        let result: Vec<String> = provider.input_lines().map(|l| l.unwrap()).collect();
+
+        assert_eq!(vec!["a", "b", "c"], result);
+    }
+
+    #[test]
+    fn prompt__queued_line__writes_message_and_returns_trimmed_line() {
+        let mut provider = SimulatedStdStreams::new();
+        provider.write_input("yes\r\n".as_bytes());
+
+        let result = provider.prompt("Continue? ").unwrap();
+
+        assert_eq!("yes", result);
+        assert_eq!("Continue? ", provider.read_output_str().unwrap());
+    }
+
+    #[test]
+    fn write_output__str_and_bytes__all_written() {
+        let mut provider = SimulatedStdStreams::new();
+
+        provider.write_output("foo").unwrap();
+        provider.write_output(b"bar").unwrap();
+
+        assert_eq!("foobar", provider.read_output_str().unwrap());
+    }
+
+    #[test]
+    fn writeln_output__lf_and_crlf_styles__terminated_accordingly() {
+        for &(style, expected) in &[
+            (NewlineStyle::Lf, "line\n"),
+            (NewlineStyle::CrLf, "line\r\n"),
+        ] {
+            let mut provider = SimulatedStdStreams::new();
+            provider.set_newline_style(style);
+
+            provider.writeln_output("line").unwrap();
+
+            assert_eq!(expected, provider.read_output_str().unwrap());
+            assert_eq!(1, provider.output_flush_count());
+        }
+    }
+
+    #[test]
+    fn writeln_output__default_style__native_line_ending() {
+        let mut provider = SimulatedStdStreams::new();
+
+        provider.writeln_output("line").unwrap();
+
+        let expected = if cfg!(windows) { "line\r\n" } else { "line\n" };
+        assert_eq!(expected, provider.read_output_str().unwrap());
+    }
+
+    #[test]
+    fn write_styled__red__wrapped_in_escape_codes() {
+        let mut provider = SimulatedStdStreams::new();
+
+        provider.write_styled(Style::Red, "error").unwrap();
+
+        assert_eq!("\x1b[31merror\x1b[0m", provider.read_output_str().unwrap());
+    }
+}
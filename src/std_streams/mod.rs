@@ -30,22 +30,107 @@
 //! }
 //! ```
 
+mod any;
+mod buffered;
+mod ext;
 mod native;
+mod panic;
+mod recording;
 mod simulated;
 
 use std::io;
 
+pub use self::any::AnyStdStreams;
+pub use self::ext::{NewlineStyle, StdStreamsExt, Style};
 pub use self::native::NativeStdStreams;
+pub use self::panic::{install_panic_hook, SharedStdStreams};
+pub use self::recording::RecordingStreams;
 pub use self::simulated::SimulatedStdStreams;
 
 /// Provides access to input, output and error streams.
 pub trait StdStreams {
     /// Gets the input stream.
-    fn input(&mut self) -> &mut io::Read;
+    fn input(&mut self) -> &mut dyn io::Read;
+
+    /// Gets the input stream as a buffered reader, e.g. for reading it line by line.
+    ///
+    /// Data which this reader buffers but doesn't return is returned by subsequent reads from
+    /// [`input()`](#tymethod.input), so the two can be used interchangeably.
+    ///
+    /// By default this reads from [`input()`](#tymethod.input) a byte at a time, so that it never
+    /// buffers anything; implementations which keep their own buffer override it.
+    fn buffered_input(&mut self) -> Box<dyn io::BufRead + '_> {
+        Box::new(buffered::ByteReader::new(self.input()))
+    }
 
     /// Gets the output stream.
-    fn output(&mut self) -> &mut io::Write;
+    fn output(&mut self) -> &mut dyn io::Write;
+
+    /// Returns whether any data has been written to the output stream.
+    ///
+    /// This is useful for e.g. only ending output with a newline if there was any. By default this
+    /// is `true`, since an implementation which doesn't keep track can't rule out that there was.
+    fn output_written(&self) -> bool {
+        true
+    }
+
+    /// Returns the number of bytes which remain to be read from the input stream, if known.
+    ///
+    /// This is intended for preallocating a buffer before reading all of the input, so it's only a
+    /// hint: it's `None` when the length can't be known in advance (e.g. when the input is a pipe
+    /// or terminal), which is the default.
+    fn input_len_hint(&self) -> Option<u64> {
+        None
+    }
+
+    /// Reads a line of input which shouldn't be displayed, such as a password, and returns it
+    /// without its trailing newline.
+    ///
+    /// By default this reads a line from [`buffered_input()`](#method.buffered_input) without
+    /// doing anything to hide it. Implementations override this to suppress echo: for example,
+    /// [`NativeStdStreams`](struct.NativeStdStreams.html) turns off terminal echo while reading.
+    fn read_password(&mut self) -> io::Result<String> {
+        read_line_trimmed(&mut *self.buffered_input())
+    }
+
+    /// Returns the line ending which text written to the output stream should use.
+    ///
+    /// By default this is the platform's line ending, but implementations may override it; for
+    /// example, [`SimulatedStdStreams`](struct.SimulatedStdStreams.html) can be configured to
+    /// always use `\n` so that tests are deterministic.
+    fn newline_style(&self) -> NewlineStyle {
+        NewlineStyle::native()
+    }
+
+    /// Returns the size of the terminal which output is written to, as `(columns, rows)`, or
+    /// `None` if it isn't known (e.g. when output is redirected to a file), which is the default.
+    ///
+    /// This is intended for fitting output to the terminal, e.g. by wrapping text.
+    fn terminal_size(&self) -> Option<(u16, u16)> {
+        None
+    }
 
     /// Gets the error stream.
-    fn error(&mut self) -> &mut io::Write;
+    fn error(&mut self) -> &mut dyn io::Write;
+
+    /// Gets the log stream, for diagnostics which should be kept separate from error messages.
+    ///
+    /// By default this is the error stream, but implementations may override it; for example,
+    /// [`SimulatedStdStreams`](struct.SimulatedStdStreams.html) captures it separately.
+    fn log(&mut self) -> &mut dyn io::Write {
+        self.error()
+    }
+}
+
+/// Reads a line from `input`, without its trailing newline (`\n` or `\r\n`).
+fn read_line_trimmed(input: &mut dyn io::BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(line)
 }
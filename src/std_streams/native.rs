@@ -1,11 +1,17 @@
+use std::fs;
 use std::io;
-use std_streams::StdStreams;
+use std::io::Seek;
+#[cfg(unix)]
+use std::mem;
+use std_streams::buffered::InputBuffer;
+use std_streams::{read_line_trimmed, StdStreams};
 
 /// Handles for the standard input streams of a process, using
 /// [`std::io`](https://doc.rust-lang.org/stable/std/io/).
+#[derive(Debug)]
 pub struct NativeStdStreams {
-    input: io::Stdin,
-    output: io::Stdout,
+    input: InputBuffer<io::Stdin>,
+    output: WriteTracker<io::Stdout>,
     error: io::Stderr,
 }
 
@@ -16,12 +22,28 @@ impl NativeStdStreams {
     /// `std::io` functions return a new handle to their stream, so it's not possible to return
     /// `&mut` references to these handles unless we store them.
     pub fn new() -> Self {
+        let mut input = InputBuffer::new(io::stdin());
+        input.set_buffered(true);
+
         NativeStdStreams {
-            input: io::stdin(),
-            output: io::stdout(),
+            input,
+            output: WriteTracker::new(io::stdout()),
             error: io::stderr(),
         }
     }
+
+    /// Sets whether reads from [`StdStreams::input()`] go through the buffer used by
+    /// [`StdStreams::buffered_input()`].
+    ///
+    /// By default they do, so that small reads (e.g. reading input a byte at a time) don't each
+    /// need a system call. When disabled, each read is a single read of stdin once that buffer is
+    /// empty.
+    ///
+    /// [`StdStreams::input()`]: trait.StdStreams.html#tymethod.input
+    /// [`StdStreams::buffered_input()`]: trait.StdStreams.html#method.buffered_input
+    pub fn set_input_buffered(&mut self, buffered: bool) {
+        self.input.set_buffered(buffered);
+    }
 }
 
 impl Default for NativeStdStreams {
@@ -31,15 +53,297 @@ impl Default for NativeStdStreams {
 }
 
 impl StdStreams for NativeStdStreams {
-    fn input(&mut self) -> &mut io::Read {
+    fn input(&mut self) -> &mut dyn io::Read {
         &mut self.input
     }
 
-    fn output(&mut self) -> &mut io::Write {
+    fn buffered_input(&mut self) -> Box<dyn io::BufRead + '_> {
+        Box::new(&mut self.input)
+    }
+
+    fn output(&mut self) -> &mut dyn io::Write {
         &mut self.output
     }
 
-    fn error(&mut self) -> &mut io::Write {
+    fn output_written(&self) -> bool {
+        self.output.written
+    }
+
+    /// Returns the number of bytes left to read from stdin when it's redirected from a regular
+    /// file, or `None` otherwise.
+    fn input_len_hint(&self) -> Option<u64> {
+        let remaining = stdin_file().and_then(|file| remaining_len(&file))?;
+        Some(remaining + self.input.buffered_len() as u64)
+    }
+
+    /// Reads a line of input with terminal echo turned off, if stdin is a terminal.
+    ///
+    /// Echo can only be turned off on Unix and Windows; elsewhere the input is read as-is.
+    fn read_password(&mut self) -> io::Result<String> {
+        let _echo_off = EchoOff::new()?;
+        read_line_trimmed(&mut self.input)
+    }
+
+    /// Returns the size of the terminal which stdout is connected to, if it is one.
+    ///
+    /// The size can only be queried on Unix and Windows; elsewhere this returns `None`.
+    fn terminal_size(&self) -> Option<(u16, u16)> {
+        stdout_terminal_size()
+    }
+
+    fn error(&mut self) -> &mut dyn io::Write {
         &mut self.error
     }
 }
+
+/// Turns off terminal echo on stdin until it's dropped.
+struct EchoOff {
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+    #[cfg(windows)]
+    original: Option<u32>,
+}
+
+impl EchoOff {
+    /// Turns off echo, unless stdin isn't a terminal.
+    #[cfg(unix)]
+    fn new() -> io::Result<EchoOff> {
+        let mut termios = unsafe { mem::zeroed::<libc::termios>() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+            return Ok(EchoOff { original: None });
+        }
+
+        let original = termios;
+        termios.c_lflag &= !libc::ECHO;
+        termios.c_lflag |= libc::ECHONL;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(EchoOff {
+            original: Some(original),
+        })
+    }
+
+    /// Turns off echo, unless stdin isn't a console.
+    #[cfg(windows)]
+    fn new() -> io::Result<EchoOff> {
+        use std::os::windows::io::AsRawHandle;
+
+        let handle = io::stdin().as_raw_handle();
+        let mut mode = 0;
+        if unsafe { console::GetConsoleMode(handle, &mut mode) } == 0 {
+            return Ok(EchoOff { original: None });
+        }
+
+        if unsafe { console::SetConsoleMode(handle, mode & !console::ENABLE_ECHO_INPUT) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(EchoOff {
+            original: Some(mode),
+        })
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn new() -> io::Result<EchoOff> {
+        Ok(EchoOff {})
+    }
+}
+
+impl Drop for EchoOff {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        {
+            if let Some(ref original) = self.original {
+                unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original) };
+            }
+        }
+        #[cfg(windows)]
+        {
+            use std::os::windows::io::AsRawHandle;
+            if let Some(original) = self.original {
+                unsafe { console::SetConsoleMode(io::stdin().as_raw_handle(), original) };
+            }
+        }
+    }
+}
+
+/// The parts of the Windows console API which are used here, declared by hand rather than adding
+/// a dependency for them.
+#[cfg(windows)]
+#[allow(non_snake_case)]
+mod console {
+    use std::os::windows::io::RawHandle;
+
+    pub const ENABLE_ECHO_INPUT: u32 = 0x0004;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    pub struct Coord {
+        pub x: i16,
+        pub y: i16,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    pub struct SmallRect {
+        pub left: i16,
+        pub top: i16,
+        pub right: i16,
+        pub bottom: i16,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct ScreenBufferInfo {
+        pub size: Coord,
+        pub cursor_position: Coord,
+        pub attributes: u16,
+        pub window: SmallRect,
+        pub maximum_window_size: Coord,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn GetConsoleMode(handle: RawHandle, mode: *mut u32) -> i32;
+        pub fn SetConsoleMode(handle: RawHandle, mode: u32) -> i32;
+        pub fn GetConsoleScreenBufferInfo(handle: RawHandle, info: *mut ScreenBufferInfo) -> i32;
+    }
+}
+
+/// Queries the size of the terminal which stdout is connected to.
+#[cfg(unix)]
+fn stdout_terminal_size() -> Option<(u16, u16)> {
+    let mut size = unsafe { mem::zeroed::<libc::winsize>() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } != 0
+        || size.ws_col == 0
+    {
+        return None;
+    }
+    Some((size.ws_col, size.ws_row))
+}
+
+#[cfg(windows)]
+fn stdout_terminal_size() -> Option<(u16, u16)> {
+    use std::convert::TryFrom;
+    use std::os::windows::io::AsRawHandle;
+
+    let mut info = console::ScreenBufferInfo::default();
+    if unsafe { console::GetConsoleScreenBufferInfo(io::stdout().as_raw_handle(), &mut info) } == 0
+    {
+        return None;
+    }
+    let window = info.window;
+    let columns = u16::try_from(window.right - window.left + 1).ok()?;
+    let rows = u16::try_from(window.bottom - window.top + 1).ok()?;
+    Some((columns, rows))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn stdout_terminal_size() -> Option<(u16, u16)> {
+    None
+}
+
+/// Duplicates the handle to stdin as a `File`, so that its metadata can be queried.
+#[cfg(unix)]
+fn stdin_file() -> Option<fs::File> {
+    use std::os::unix::io::AsFd;
+    let fd = io::stdin().as_fd().try_clone_to_owned().ok()?;
+    Some(fs::File::from(fd))
+}
+
+/// Duplicates the handle to stdin as a `File`, so that its metadata can be queried.
+#[cfg(windows)]
+fn stdin_file() -> Option<fs::File> {
+    use std::os::windows::io::AsHandle;
+    let handle = io::stdin().as_handle().try_clone_to_owned().ok()?;
+    Some(fs::File::from(handle))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn stdin_file() -> Option<fs::File> {
+    None
+}
+
+/// Returns the number of bytes between the current position of `file` and its end, if it's a
+/// regular file.
+fn remaining_len(mut file: &fs::File) -> Option<u64> {
+    let metadata = file.metadata().ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    let position = file.stream_position().ok()?;
+    Some(metadata.len().saturating_sub(position))
+}
+
+/// A `Write` implementer which records whether any data has been written through it.
+#[derive(Debug)]
+struct WriteTracker<W> {
+    inner: W,
+    written: bool,
+}
+
+impl<W: io::Write> WriteTracker<W> {
+    fn new(inner: W) -> WriteTracker<W> {
+        WriteTracker {
+            inner,
+            written: false,
+        }
+    }
+}
+
+impl<W: io::Write> io::Write for WriteTracker<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written |= written > 0;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use std::io::{Read, Seek, Write};
+
+    use super::{remaining_len, WriteTracker};
+
+    #[test]
+    fn write_tracker__empty_then_nonempty_write__written_after_data() {
+        let mut tracker = WriteTracker::new(Vec::new());
+
+        tracker.write_all(b"").unwrap();
+        assert!(!tracker.written);
+
+        tracker.write_all(b"data").unwrap();
+        assert!(tracker.written);
+        assert_eq!(b"data", &tracker.inner[..]);
+    }
+
+    #[test]
+    fn remaining_len__partly_read_regular_file__unread_length() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"contents").unwrap();
+        file.rewind().unwrap();
+        let mut buf = [0; 3];
+
+        file.read_exact(&mut buf).unwrap();
+
+        assert_eq!(Some(5), remaining_len(&file));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn remaining_len__pipe__none() {
+        use std::fs::File;
+        use std::io;
+        use std::os::unix::io::OwnedFd;
+
+        let (reader, mut writer) = io::pipe().unwrap();
+        writer.write_all(b"contents").unwrap();
+
+        assert_eq!(None, remaining_len(&File::from(OwnedFd::from(reader))));
+    }
+}
@@ -0,0 +1,87 @@
+use std::io::Write;
+use std::panic;
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::thread;
+use std_streams::StdStreams;
+
+/// Standard streams which can be shared between threads, and with a panic hook.
+///
+/// Cloning a `SharedStdStreams` gives another handle to the same streams.
+#[derive(Debug, Default)]
+pub struct SharedStdStreams<S> {
+    streams: Arc<Mutex<S>>,
+}
+
+impl<S> SharedStdStreams<S> {
+    /// Creates a new `SharedStdStreams` which wraps `streams`.
+    pub fn new(streams: S) -> SharedStdStreams<S> {
+        SharedStdStreams {
+            streams: Arc::new(Mutex::new(streams)),
+        }
+    }
+
+    /// Locks the streams for exclusive use by the current thread until the returned guard is
+    /// dropped.
+    ///
+    /// Streams are still usable after a thread panicked while holding the lock, so this doesn't
+    /// fail on poisoning like `Mutex::lock()` does.
+    pub fn lock(&self) -> MutexGuard<'_, S> {
+        self.streams
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<S> Clone for SharedStdStreams<S> {
+    fn clone(&self) -> SharedStdStreams<S> {
+        SharedStdStreams {
+            streams: Arc::clone(&self.streams),
+        }
+    }
+}
+
+/// Replaces the process's panic hook with one which writes panic messages to the error stream of
+/// `streams` instead of the process's stderr.
+///
+/// The messages have the same form as those of the default hook, without a backtrace. If the
+/// streams are locked when a panic occurs (e.g. by the panicking thread itself), the message is
+/// written to the process's stderr instead, so that the hook can't deadlock.
+///
+/// Like [`std::panic::set_hook()`], this affects every thread in the process; the previous hook
+/// can be restored with [`std::panic::take_hook()`].
+///
+/// [`std::panic::set_hook()`]: https://doc.rust-lang.org/std/panic/fn.set_hook.html
+/// [`std::panic::take_hook()`]: https://doc.rust-lang.org/std/panic/fn.take_hook.html
+///
+/// ## Example
+///
+/// ```
+/// use std::panic;
+/// use io_providers::std_streams::{install_panic_hook, SharedStdStreams, SimulatedStdStreams};
+///
+/// let streams = SharedStdStreams::new(SimulatedStdStreams::new());
+/// install_panic_hook(streams.clone());
+///
+/// assert!(panic::catch_unwind(|| panic!("oh no")).is_err());
+/// let _ = panic::take_hook();
+///
+/// let error = String::from_utf8(streams.lock().read_error().to_vec()).unwrap();
+/// assert!(error.contains("oh no"));
+/// ```
+pub fn install_panic_hook<S: StdStreams + Send + 'static>(streams: SharedStdStreams<S>) {
+    panic::set_hook(Box::new(move |info| {
+        let thread = thread::current();
+        let message = format!(
+            "thread '{}' {}\n",
+            thread.name().unwrap_or("<unnamed>"),
+            info
+        );
+        let _ = match streams.streams.try_lock() {
+            Ok(mut guard) => guard.error().write_all(message.as_bytes()),
+            Err(TryLockError::Poisoned(poisoned)) => {
+                poisoned.into_inner().error().write_all(message.as_bytes())
+            }
+            Err(TryLockError::WouldBlock) => ::std::io::stderr().write_all(message.as_bytes()),
+        };
+    }));
+}
@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+use std::io;
+use std::io::{Read, Write};
+use std_streams::buffered::InputBuffer;
+use std_streams::StdStreams;
+
+/// Standard streams which replay a scripted sequence of input responses and record all output.
+///
+/// Unlike [`SimulatedStdStreams`](struct.SimulatedStdStreams.html), each input response may be an
+/// error, making it possible to test how code handles failures when reading input.
+///
+/// Each response is used for a SINGLE read from [`StdStreams::input()`], regardless of how much of
+/// it was (or was not) read, unless the read is made through [`StdStreams::buffered_input()`]. Once
+/// all responses have been used, reads return zero bytes.
+///
+/// [`StdStreams::input()`]: trait.StdStreams.html#tymethod.input
+/// [`StdStreams::buffered_input()`]: trait.StdStreams.html#method.buffered_input
+///
+/// ## Example
+///
+/// ```
+/// use std::io;
+/// use std::io::Read;
+/// use io_providers::std_streams::{RecordingStreams, StdStreams};
+///
+/// let mut streams = RecordingStreams::new(vec![
+///     Ok(b"foo".to_vec()),
+///     Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted")),
+/// ]);
+/// let mut buf = [0; 8];
+///
+/// assert_eq!(3, streams.input().read(&mut buf).unwrap());
+/// assert!(streams.input().read(&mut buf).is_err());
+/// assert_eq!(0, streams.input().read(&mut buf).unwrap());
+/// ```
+pub struct RecordingStreams {
+    input: InputBuffer<ReplayReader>,
+    output: Vec<u8>,
+    error: Vec<u8>,
+}
+
+impl RecordingStreams {
+    /// Creates a new `RecordingStreams` which will respond to reads from its input stream with
+    /// each of `responses` in turn.
+    pub fn new<I: IntoIterator<Item = io::Result<Vec<u8>>>>(responses: I) -> RecordingStreams {
+        RecordingStreams {
+            input: InputBuffer::new(ReplayReader {
+                responses: responses.into_iter().collect(),
+            }),
+            output: Vec::new(),
+            error: Vec::new(),
+        }
+    }
+
+    /// Gets the data which has been written to the output stream.
+    pub fn read_output(&self) -> &[u8] {
+        &self.output[..]
+    }
+
+    /// Gets the data which has been written to the error stream.
+    pub fn read_error(&self) -> &[u8] {
+        &self.error[..]
+    }
+}
+
+impl StdStreams for RecordingStreams {
+    fn input(&mut self) -> &mut dyn Read {
+        &mut self.input
+    }
+
+    fn buffered_input(&mut self) -> Box<dyn io::BufRead + '_> {
+        Box::new(&mut self.input)
+    }
+
+    fn output(&mut self) -> &mut dyn Write {
+        &mut self.output
+    }
+
+    fn output_written(&self) -> bool {
+        !self.output.is_empty()
+    }
+
+    fn error(&mut self) -> &mut dyn Write {
+        &mut self.error
+    }
+}
+
+/// A `Read` implementer which responds to each read with the next of a sequence of results.
+struct ReplayReader {
+    responses: VecDeque<io::Result<Vec<u8>>>,
+}
+
+impl Read for ReplayReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.responses.pop_front() {
+            Some(Ok(data)) => io::Cursor::new(data).read(buf),
+            Some(Err(e)) => Err(e),
+            None => Ok(0),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::RecordingStreams;
+    use std::io;
+    use std_streams::StdStreams;
+
+    #[test]
+    fn provider__replay_and_record__sanity_check() {
+        let mut provider = RecordingStreams::new(vec![
+            Ok(vec![1, 2, 3]),
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "injected")),
+            Ok(vec![4]),
+        ]);
+        let mut buf = vec![0; 4];
+
+        let result1 = provider.input().read(&mut buf).unwrap();
+        assert_eq!(3, result1);
+        assert_eq!(vec![1, 2, 3, 0], buf);
+
+        let result2 = provider.input().read(&mut buf);
+        assert_eq!(io::ErrorKind::BrokenPipe, result2.unwrap_err().kind());
+
+        let result3 = provider.input().read(&mut buf).unwrap();
+        assert_eq!(1, result3);
+        assert_eq!(4, buf[0]);
+
+        assert_eq!(0, provider.input().read(&mut buf).unwrap());
+
+        provider.output().write_all(&[5, 6]).unwrap();
+        provider.error().write_all(&[7]).unwrap();
+        assert_eq!(&[5, 6], provider.read_output());
+        assert_eq!(&[7], provider.read_error());
+    }
+
+    #[test]
+    fn provider__no_responses__length_zero_read() {
+        let mut provider = RecordingStreams::new(Vec::new());
+        let mut buf = vec![0; 4];
+
+        let result = provider.input().read(&mut buf).unwrap();
+
+        assert_eq!(0, result);
+    }
+}
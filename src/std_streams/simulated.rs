@@ -1,7 +1,16 @@
 use std::collections::VecDeque;
+use std::fmt;
 use std::io;
 use std::io::{Read, Write};
-use std_streams::StdStreams;
+use std::mem;
+use std::str;
+use std_streams::buffered::InputBuffer;
+use std_streams::{read_line_trimmed, NewlineStyle, StdStreams};
+
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde")]
+use serde_json;
 
 /// Simulated handles for the standard input streams of a process.
 ///
@@ -9,23 +18,104 @@ use std_streams::StdStreams;
 /// [`write_input()`](std_streams/struct.SimulatedStdStreams.html#method.write_input), and output
 /// can be observed using [`read_output()`](std_streams/struct.SimulatedStdStreams.html#method.read_output)
 /// and [`read_error()`](std_streams/struct.SimulatedStdStreams.html#method.read_error).
-#[derive(Default)]
+#[derive(Debug)]
 pub struct SimulatedStdStreams {
-    inputs: ChunkPipe,
-    output: Vec<u8>,
+    /// The input, along with the output so that input can be echoed to it.
+    console: InputBuffer<Console>,
+    /// Every input buffer which has been enqueued, for `rewind_input()`.
+    input_history: Vec<Vec<u8>>,
     error: Vec<u8>,
+    log: Vec<u8>,
+    newline_style: NewlineStyle,
+    terminal_size: Option<(u16, u16)>,
 }
 
 impl SimulatedStdStreams {
     /// Creates a new `SimulatedStdStreams`.
     pub fn new() -> SimulatedStdStreams {
         SimulatedStdStreams {
-            inputs: ChunkPipe::new(),
-            output: Vec::new(),
+            console: InputBuffer::new(Console {
+                input: ChunkPipe::new(),
+                output: OutputBuffer::new(),
+                echo: false,
+            }),
+            input_history: Vec::new(),
             error: Vec::new(),
+            log: Vec::new(),
+            newline_style: NewlineStyle::native(),
+            terminal_size: None,
         }
     }
 
+    /// Sets whether reads from [`StdStreams::input()`] go through the buffer used by
+    /// [`StdStreams::buffered_input()`].
+    ///
+    /// By default they don't, so that (once that buffer is empty) each read consumes exactly one
+    /// buffer given to [`write_input()`](#method.write_input). When enabled, a read may instead
+    /// return data from several such buffers, like a read from a real, buffered stdin.
+    ///
+    /// [`StdStreams::input()`]: trait.StdStreams.html#tymethod.input
+    /// [`StdStreams::buffered_input()`]: trait.StdStreams.html#method.buffered_input
+    pub fn set_input_buffered(&mut self, buffered: bool) {
+        self.console.set_buffered(buffered);
+    }
+
+    /// Redirects output to `sink`, in addition to capturing it.
+    ///
+    /// Output is buffered before being written to `sink`; the buffer is written out when the
+    /// output stream is flushed, when it becomes large, and when these streams are dropped.
+    pub fn redirect_output<W: Write + Send + 'static>(&mut self, sink: W) {
+        self.console.get_mut().output.redirect = Some(Redirect {
+            sink: Box::new(sink),
+            pending: Vec::new(),
+        });
+    }
+
+    /// Sets whether writes to the output stream must be valid UTF-8 (by default they needn't be).
+    ///
+    /// When enabled, a write to [`StdStreams::output()`] which would make the captured output
+    /// invalid UTF-8 fails with `ErrorKind::InvalidData`, and nothing is written. A multi-byte
+    /// character may be split across several writes, but flushing the output stream while such a
+    /// character is incomplete fails with `ErrorKind::InvalidData`.
+    ///
+    /// [`StdStreams::output()`]: trait.StdStreams.html#tymethod.output
+    pub fn set_output_utf8_strict(&mut self, strict: bool) {
+        self.console.get_mut().output.utf8_strict = strict;
+    }
+
+    /// Sets the line ending reported by [`StdStreams::newline_style()`], which is the platform's
+    /// line ending by default. Setting it to `NewlineStyle::Lf` makes output written with e.g.
+    /// [`StdStreamsExt::writeln_output()`] the same on every platform.
+    ///
+    /// [`StdStreams::newline_style()`]: trait.StdStreams.html#method.newline_style
+    /// [`StdStreamsExt::writeln_output()`]: trait.StdStreamsExt.html#method.writeln_output
+    pub fn set_newline_style(&mut self, style: NewlineStyle) {
+        self.newline_style = style;
+    }
+
+    /// Sets the terminal size reported by [`StdStreams::terminal_size()`], as `(columns, rows)`.
+    /// By default it's `None`, as if output were redirected to a file.
+    ///
+    /// [`StdStreams::terminal_size()`]: trait.StdStreams.html#method.terminal_size
+    pub fn set_terminal_size(&mut self, size: Option<(u16, u16)>) {
+        self.terminal_size = size;
+    }
+
+    /// Sets whether input is echoed to the output stream as it's read, like a terminal echoes what
+    /// the user types (by default it isn't).
+    ///
+    /// Input is echoed when it's read from the queue of buffers given to
+    /// [`write_input()`](#method.write_input): a read through [`StdStreams::input()`] echoes the
+    /// data it returns, while a read through [`StdStreams::buffered_input()`] echoes a whole buffer
+    /// when it's first read, as a terminal echoes a line as it's typed. This makes it possible to
+    /// test the transcript of an interactive session, or that echo is avoided when it should be.
+    ///
+    /// [`StdStreams::input()`]: trait.StdStreams.html#tymethod.input
+    /// [`StdStreams::buffered_input()`]: trait.StdStreams.html#method.buffered_input
+    pub fn set_echo(&mut self, echo: bool) {
+        self.console.get_mut().echo = echo;
+    }
+
     /// Writes the provided buffer to the queue of buffers to be used when input is requested
     /// using [`StdStreams::input()`].
     ///
@@ -37,6 +127,10 @@ impl SimulatedStdStreams {
     /// This enables precise control over the length of data returned from a call to
     /// [`StdStreams::input()`].
     ///
+    /// An empty `input` is ignored, since a zero-length read would be interpreted by the reader
+    /// as the end of the stream; use [`write_input_eof()`](#method.write_input_eof) to simulate
+    /// that explicitly.
+    ///
     /// [`StdStreams::input()`]: trait.StdStreams.html#tymethod.input
     ///
     /// ## Example
@@ -51,7 +145,49 @@ impl SimulatedStdStreams {
     /// // The second read on `streams.input()` will read from "bar"
     /// ```
     pub fn write_input(&mut self, input: &[u8]) {
-        self.inputs.write_all(input).unwrap();
+        if !input.is_empty() {
+            self.console.get_mut().input.write_all(input).unwrap();
+            self.input_history.push(input.to_vec());
+        }
+    }
+
+    /// Enqueues the contents of several files as input, like `cat file1 file2` would, with each
+    /// of `chunks` being read by a separate read from [`StdStreams::input()`].
+    ///
+    /// See [`write_input_concatenated()`](#method.write_input_concatenated) to have them read as
+    /// one continuous chunk instead.
+    ///
+    /// [`StdStreams::input()`]: trait.StdStreams.html#tymethod.input
+    pub fn write_input_files(&mut self, chunks: &[&[u8]]) {
+        for chunk in chunks {
+            self.write_input(chunk);
+        }
+    }
+
+    /// Enqueues the contents of several files as input, like
+    /// [`write_input_files()`](#method.write_input_files), but concatenated into a single chunk.
+    pub fn write_input_concatenated(&mut self, chunks: &[&[u8]]) {
+        self.write_input(&chunks.concat());
+    }
+
+    /// Enqueues an end-of-file, so that the corresponding read from [`StdStreams::input()`]
+    /// returns zero bytes (as happens when a user presses Ctrl-D at a terminal). Input enqueued
+    /// afterwards is still read by subsequent reads.
+    ///
+    /// [`StdStreams::input()`]: trait.StdStreams.html#tymethod.input
+    pub fn write_input_eof(&mut self) {
+        self.console.get_mut().input.items.push_back(Vec::new());
+        self.input_history.push(Vec::new());
+    }
+
+    /// Restores all of the input which has ever been enqueued, so that it can be read again from
+    /// the start.
+    ///
+    /// Any input which hasn't been read yet is discarded first, so that it isn't read twice. This
+    /// makes it possible to run the same input through code under test repeatedly.
+    pub fn rewind_input(&mut self) {
+        self.console.discard_buffer();
+        self.console.get_mut().input.items = self.input_history.iter().cloned().collect();
     }
 
     /// Gets the data which has been written to the output stream.
@@ -68,7 +204,23 @@ impl SimulatedStdStreams {
     /// assert_eq!("test1\ntest2", ::std::str::from_utf8(streams.read_output()).unwrap());
     /// ```
     pub fn read_output(&self) -> &[u8] {
-        &self.output[..]
+        &self.console.get_ref().output.data[..]
+    }
+
+    /// Parses each line which has been written to the output stream as JSON, as written by a
+    /// program emitting [JSON Lines](https://jsonlines.org/) (also known as NDJSON).
+    ///
+    /// Lines may end with `\n` or `\r\n`, and empty lines are skipped.
+    ///
+    /// This requires the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn read_output_json_lines<T: DeserializeOwned>(&self) -> Result<Vec<T>, serde_json::Error> {
+        self.read_output()
+            .split(|&b| b == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_slice)
+            .collect()
     }
 
     /// Gets the data which has been written to the error stream.
@@ -87,25 +239,254 @@ impl SimulatedStdStreams {
     pub fn read_error(&self) -> &[u8] {
         &self.error[..]
     }
+
+    /// Gets the data which has been written to the log stream, which is captured separately from
+    /// the error stream.
+    pub fn read_log(&self) -> &[u8] {
+        &self.log[..]
+    }
+
+    /// Gets the number of times the output stream has been flushed, e.g. to check that a prompt
+    /// is flushed before input is read.
+    ///
+    /// Every call to `flush()` is counted, whether or not it succeeded.
+    pub fn output_flush_count(&self) -> usize {
+        self.console.get_ref().output.flush_count
+    }
+
+    /// Renders everything written to the output and error streams as a labelled, human-readable
+    /// string, e.g. for including in the message of a failed assertion.
+    ///
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use io_providers::{StdStreams, SimulatedStdStreams};
+    ///
+    /// let mut streams = SimulatedStdStreams::new();
+    /// write!(streams.output(), "result").unwrap();
+    /// assert!(streams.read_output().starts_with(b"result"), "{}", streams.dump());
+    /// ```
+    pub fn dump(&self) -> String {
+        let mut result = String::new();
+        for &(label, data) in [
+            ("output", &self.console.get_ref().output.data),
+            ("error", &self.error),
+        ]
+        .iter()
+        {
+            result.push_str(&format!("--- {} ({} bytes) ---\n", label, data.len()));
+            result.push_str(&String::from_utf8_lossy(data));
+            if !data.is_empty() && !data.ends_with(b"\n") {
+                result.push('\n');
+            }
+        }
+        result
+    }
+
+    /// Gets the data which has been written to the output stream, as a string slice.
+    ///
+    /// Returns an error if the data is not valid UTF-8.
+    pub fn read_output_str(&self) -> Result<&str, str::Utf8Error> {
+        str::from_utf8(&self.console.get_ref().output.data)
+    }
+
+    /// Gets the data which has been written to the error stream, as a string slice.
+    ///
+    /// Returns an error if the data is not valid UTF-8.
+    pub fn read_error_str(&self) -> Result<&str, str::Utf8Error> {
+        str::from_utf8(&self.error)
+    }
+}
+
+impl Default for SimulatedStdStreams {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl StdStreams for SimulatedStdStreams {
-    fn input(&mut self) -> &mut Read {
-        &mut self.inputs
+    fn input(&mut self) -> &mut dyn Read {
+        &mut self.console
+    }
+
+    fn buffered_input(&mut self) -> Box<dyn io::BufRead + '_> {
+        Box::new(&mut self.console)
+    }
+
+    fn output(&mut self) -> &mut dyn Write {
+        &mut self.console.get_mut().output
+    }
+
+    fn output_written(&self) -> bool {
+        !self.console.get_ref().output.data.is_empty()
+    }
+
+    fn input_len_hint(&self) -> Option<u64> {
+        let queued: usize = self
+            .console
+            .get_ref()
+            .input
+            .items
+            .iter()
+            .map(Vec::len)
+            .sum();
+        Some((self.console.buffered_len() + queued) as u64)
+    }
+
+    /// Reads a line of input without echoing it, regardless of [`set_echo()`](#method.set_echo).
+    fn read_password(&mut self) -> io::Result<String> {
+        let echo = mem::replace(&mut self.console.get_mut().echo, false);
+        let result = read_line_trimmed(&mut self.console);
+        self.console.get_mut().echo = echo;
+        result
     }
 
-    fn output(&mut self) -> &mut Write {
-        &mut self.output
+    fn newline_style(&self) -> NewlineStyle {
+        self.newline_style
     }
 
-    fn error(&mut self) -> &mut Write {
+    fn terminal_size(&self) -> Option<(u16, u16)> {
+        self.terminal_size
+    }
+
+    fn error(&mut self) -> &mut dyn Write {
         &mut self.error
     }
+
+    fn log(&mut self) -> &mut dyn Write {
+        &mut self.log
+    }
+}
+
+/// A `Write` implementer which captures everything written to it, optionally requiring that it be
+/// valid UTF-8.
+#[derive(Debug, Default)]
+struct OutputBuffer {
+    data: Vec<u8>,
+    utf8_strict: bool,
+    /// The length of the incomplete UTF-8 sequence at the end of `data`, if `utf8_strict` is set.
+    incomplete_len: usize,
+    redirect: Option<Redirect>,
+    flush_count: usize,
+}
+
+impl OutputBuffer {
+    /// Creates a new, empty `OutputBuffer`.
+    pub fn new() -> OutputBuffer {
+        OutputBuffer {
+            data: Vec::new(),
+            utf8_strict: false,
+            incomplete_len: 0,
+            redirect: None,
+            flush_count: 0,
+        }
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut incomplete_len = 0;
+        if self.utf8_strict {
+            let mut tail = self.data[self.data.len() - self.incomplete_len..].to_vec();
+            tail.extend_from_slice(buf);
+            incomplete_len = match str::from_utf8(&tail) {
+                Ok(_) => 0,
+                Err(ref e) if e.error_len().is_none() => tail.len() - e.valid_up_to(),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            };
+        }
+
+        if let Some(ref mut redirect) = self.redirect {
+            redirect.write_all(buf)?;
+        }
+        self.incomplete_len = incomplete_len;
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_count += 1;
+        if self.incomplete_len > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "output ends with an incomplete UTF-8 sequence",
+            ));
+        }
+        match self.redirect {
+            Some(ref mut redirect) => redirect.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The size above which buffered output is written to the sink without waiting for a flush.
+const REDIRECT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// A `Write` implementer which buffers data before writing it to a sink, and flushes it when
+/// dropped.
+struct Redirect {
+    sink: Box<dyn Write + Send>,
+    pending: Vec<u8>,
+}
+
+impl fmt::Debug for Redirect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Redirect")
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Write for Redirect {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.pending.len() + buf.len() < REDIRECT_BUFFER_SIZE {
+            self.pending.extend_from_slice(buf);
+        } else {
+            self.sink.write_all(&self.pending)?;
+            self.pending.clear();
+            self.sink.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.sink.write_all(&self.pending)?;
+        self.pending.clear();
+        self.sink.flush()
+    }
+}
+
+impl Drop for Redirect {
+    fn drop(&mut self) {
+        // Errors can't be reported from here; flush explicitly to observe them
+        let _ = self.flush();
+    }
+}
+
+/// The input of simulated streams, and the output which it may be echoed to.
+#[derive(Debug)]
+struct Console {
+    input: ChunkPipe,
+    output: OutputBuffer,
+    echo: bool,
+}
+
+impl Read for Console {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.input.read(buf)?;
+        if self.echo {
+            self.output.write_all(&buf[..read])?;
+        }
+        Ok(read)
+    }
 }
 
 /// A `Read` and `Write` implementer where data is written in chunks and each read consumes a
 /// single chunk.
-#[derive(Default)]
+#[derive(Debug, Default)]
 struct ChunkPipe {
     items: VecDeque<Vec<u8>>,
 }
@@ -146,13 +527,75 @@ impl Write for ChunkPipe {
 #[allow(non_snake_case)]
 mod tests {
     use super::{ChunkPipe, SimulatedStdStreams, StdStreams};
-    use std::io::{Read, Write};
+    use std::io;
+    use std::io::{BufRead, Read, Write};
+    use std::sync::{Arc, Mutex};
+
+    /// A `Write` implementer whose data can be inspected after it has been moved elsewhere.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Writes `text` to the output stream, wrapping it between words to fit the terminal width.
+    fn write_wrapped(streams: &mut dyn StdStreams, text: &str) {
+        let width = streams
+            .terminal_size()
+            .map_or(usize::MAX, |(columns, _)| columns as usize);
+        let mut line = String::new();
+        for word in text.split_whitespace() {
+            if !line.is_empty() && line.len() + 1 + word.len() > width {
+                writeln!(streams.output(), "{}", line).unwrap();
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        writeln!(streams.output(), "{}", line).unwrap();
+    }
+
+    #[test]
+    fn terminal_size__set__used_for_wrapping() {
+        let mut provider = SimulatedStdStreams::new();
+        provider.set_terminal_size(Some((10, 24)));
+
+        write_wrapped(&mut provider, "the quick brown fox jumps");
+
+        assert_eq!(Some((10, 24)), provider.terminal_size());
+        assert_eq!(
+            "the quick\nbrown fox\njumps\n",
+            String::from_utf8_lossy(provider.read_output())
+        );
+    }
+
+    #[test]
+    fn terminal_size__not_set__none_and_not_wrapped() {
+        let mut provider = SimulatedStdStreams::new();
+
+        write_wrapped(&mut provider, "the quick brown fox jumps");
+
+        assert_eq!(None, provider.terminal_size());
+        assert_eq!(
+            "the quick brown fox jumps\n",
+            String::from_utf8_lossy(provider.read_output())
+        );
+    }
 
     #[test]
     fn chunk_pipe__no_writes__reads_successfully() {
-        let mut buf: Vec<u8> = vec![0; 8];
+        let mut buf = [0; 8];
         let mut pipe = ChunkPipe::new();
-        pipe.write(&[]).unwrap();
+        pipe.write_all(&[]).unwrap();
 
         let result = pipe.read(&mut buf);
         assert_eq!(0, result.unwrap());
@@ -160,39 +603,39 @@ mod tests {
 
     #[test]
     fn chunk_pipe__one_write__reads_successfully() {
-        let data = vec![1, 2, 3];
-        let mut buf1 = vec![0; 4];
-        let mut buf2 = vec![0; 4];
+        let data = [1, 2, 3];
+        let mut buf1 = [0; 4];
+        let mut buf2 = [0; 4];
         let mut pipe = ChunkPipe::new();
 
-        pipe.write(&data[..]).unwrap();
+        pipe.write_all(&data[..]).unwrap();
         let result1 = pipe.read(&mut buf1).unwrap();
         let result2 = pipe.read(&mut buf2).unwrap();
 
         assert_eq!(data.len(), result1);
-        assert_eq!(vec![1, 2, 3, 0], buf1);
+        assert_eq!([1, 2, 3, 0], buf1);
         assert_eq!(0, result2);
     }
 
     #[test]
     fn chunk_pipe__two_writes__reads_successfully() {
-        let data1 = vec![1, 2, 3];
-        let data2 = vec![4, 5, 6, 7];
-        let mut buf1 = vec![0; 4];
-        let mut buf2 = vec![0; 3];
-        let mut buf3 = vec![0; 3];
+        let data1 = [1, 2, 3];
+        let data2 = [4, 5, 6, 7];
+        let mut buf1 = [0; 4];
+        let mut buf2 = [0; 3];
+        let mut buf3 = [0; 3];
         let mut pipe = ChunkPipe::new();
 
-        pipe.write(&data1[..]).unwrap();
+        pipe.write_all(&data1[..]).unwrap();
         let result1 = pipe.read(&mut buf1).unwrap();
-        pipe.write(&data2[..]).unwrap();
+        pipe.write_all(&data2[..]).unwrap();
         let result2 = pipe.read(&mut buf2).unwrap();
         let result3 = pipe.read(&mut buf3).unwrap();
 
         assert_eq!(data1.len(), result1);
-        assert_eq!(vec![1, 2, 3, 0], buf1);
+        assert_eq!([1, 2, 3, 0], buf1);
         assert_eq!(buf2.len(), result2);
-        assert_eq!(vec![4, 5, 6], buf2);
+        assert_eq!([4, 5, 6], buf2);
         assert_eq!(0, result3);
     }
 
@@ -206,6 +649,28 @@ mod tests {
         assert_eq!(0, result);
     }
 
+    #[test]
+    fn log__written__captured_separately_from_error() {
+        let mut provider = SimulatedStdStreams::new();
+
+        provider.error().write_all(b"error").unwrap();
+        provider.log().write_all(b"debug").unwrap();
+
+        assert_eq!(b"error", provider.read_error());
+        assert_eq!(b"debug", provider.read_log());
+    }
+
+    #[test]
+    fn output_written__before_and_after_write__false_then_true() {
+        let mut provider = SimulatedStdStreams::new();
+
+        assert!(!provider.output_written());
+        provider.error().write_all(b"error").unwrap();
+        assert!(!provider.output_written());
+        provider.output().write_all(b"output").unwrap();
+        assert!(provider.output_written());
+    }
+
     #[test]
     fn provider__write_and_read_input__success() {
         let mut provider = SimulatedStdStreams::new();
@@ -223,6 +688,94 @@ mod tests {
         assert_eq!(0, result);
     }
 
+    #[test]
+    fn write_input_files__two_files__one_read_each() {
+        let mut provider = SimulatedStdStreams::new();
+        let mut buf = vec![0; 16];
+
+        provider.write_input_files(&[b"first\n", b"second\n"]);
+
+        assert_eq!(6, provider.input().read(&mut buf).unwrap());
+        assert_eq!(b"first\n", &buf[..6]);
+        assert_eq!(7, provider.input().read(&mut buf).unwrap());
+        assert_eq!(b"second\n", &buf[..7]);
+        assert_eq!(0, provider.input().read(&mut buf).unwrap());
+    }
+
+    #[test]
+    fn write_input_concatenated__two_files__read_as_one_stream() {
+        let mut provider = SimulatedStdStreams::new();
+        let mut buf = vec![0; 16];
+
+        provider.write_input_concatenated(&[b"first\n", b"second\n"]);
+        assert_eq!(13, provider.input().read(&mut buf).unwrap());
+
+        provider.rewind_input();
+        let mut contents = String::new();
+        provider.input().read_to_string(&mut contents).unwrap();
+        assert_eq!("first\nsecond\n", contents);
+    }
+
+    #[test]
+    fn input_len_hint__partly_read_input__remaining_queued_length() {
+        let mut provider = SimulatedStdStreams::new();
+        assert_eq!(Some(0), provider.input_len_hint());
+
+        provider.write_input(b"first\n");
+        provider.write_input(b"second\n");
+        assert_eq!(Some(13), provider.input_len_hint());
+
+        let mut line = String::new();
+        provider.buffered_input().read_line(&mut line).unwrap();
+        assert_eq!(Some(7), provider.input_len_hint());
+    }
+
+    #[test]
+    fn set_echo__enabled_or_not__input_echoed_after_prompt_only_when_enabled() {
+        for &(echo, expected) in &[(true, "name? abc"), (false, "name? ")] {
+            let mut provider = SimulatedStdStreams::new();
+            provider.write_input(b"abc");
+            provider.set_echo(echo);
+            let mut input = String::new();
+
+            write!(provider.output(), "name? ").unwrap();
+            provider.input().read_to_string(&mut input).unwrap();
+
+            assert_eq!("abc", input);
+            assert_eq!(expected.as_bytes(), provider.read_output());
+        }
+    }
+
+    #[test]
+    fn read_password__echo_enabled__secret_returned_but_not_echoed() {
+        let mut provider = SimulatedStdStreams::new();
+        provider.write_input(b"hunter2\r\n");
+        provider.write_input(b"visible\n");
+        provider.set_echo(true);
+        let mut line = String::new();
+
+        write!(provider.output(), "Password: ").unwrap();
+        let password = provider.read_password().unwrap();
+        provider.buffered_input().read_line(&mut line).unwrap();
+
+        assert_eq!("hunter2", password);
+        assert_eq!(b"Password: visible\n", provider.read_output());
+    }
+
+    #[test]
+    fn set_echo__buffered_line_read__whole_line_echoed_once() {
+        let mut provider = SimulatedStdStreams::new();
+        provider.write_input(b"line 1\nline 2\n");
+        provider.set_echo(true);
+        let mut line = String::new();
+
+        provider.buffered_input().read_line(&mut line).unwrap();
+        provider.buffered_input().read_line(&mut line).unwrap();
+
+        assert_eq!("line 1\nline 2\n", line);
+        assert_eq!(b"line 1\nline 2\n", provider.read_output());
+    }
+
     #[test]
     fn provider__two_input_writes__two_reads() {
         let mut provider = SimulatedStdStreams::new();
@@ -240,6 +793,90 @@ mod tests {
         assert_eq!(expected2, actual2);
     }
 
+    #[test]
+    fn provider__buffered_input_lines_across_writes__lines_joined() {
+        let mut provider = SimulatedStdStreams::new();
+
+        provider.write_input("a\nb".as_bytes());
+        provider.write_input("c\n".as_bytes());
+        let result: Vec<String> = provider
+            .buffered_input()
+            .lines()
+            .map(|l| l.unwrap())
+            .collect();
+
+        assert_eq!(vec!["a", "bc"], result);
+    }
+
+    #[test]
+    fn provider__raw_read_after_buffered_line__buffered_data_then_next_chunk() {
+        let mut provider = SimulatedStdStreams::new();
+        let (mut line, mut buf) = (String::new(), vec![0; 8]);
+
+        provider.write_input("a\nb".as_bytes());
+        provider.write_input("cd".as_bytes());
+        provider.buffered_input().read_line(&mut line).unwrap();
+        let result1 = provider.input().read(&mut buf).unwrap();
+        let result2 = provider.input().read(&mut buf[1..]).unwrap();
+
+        assert_eq!("a\n", line);
+        assert_eq!(1, result1);
+        assert_eq!(2, result2);
+        assert_eq!(b"bcd", &buf[..3]);
+    }
+
+    #[test]
+    fn provider__rewind_input_after_reading__same_chunks_read_again() {
+        let mut provider = SimulatedStdStreams::new();
+        let mut buf = vec![0; 8];
+        provider.write_input("ab".as_bytes());
+        provider.write_input_eof();
+        provider.write_input("cd".as_bytes());
+
+        let mut read_all = |provider: &mut SimulatedStdStreams| {
+            (0..4)
+                .map(|_| {
+                    let len = provider.input().read(&mut buf).unwrap();
+                    buf[..len].to_vec()
+                })
+                .collect::<Vec<Vec<u8>>>()
+        };
+        let first = read_all(&mut provider);
+        provider.rewind_input();
+        let second = read_all(&mut provider);
+
+        let expected = vec![b"ab".to_vec(), vec![], b"cd".to_vec(), vec![]];
+        assert_eq!(expected, first);
+        assert_eq!(expected, second);
+    }
+
+    #[test]
+    fn provider__empty_input_then_data__no_premature_eof() {
+        let mut provider = SimulatedStdStreams::new();
+        let mut actual = String::new();
+
+        provider.write_input("".as_bytes());
+        provider.write_input("data".as_bytes());
+        provider.input().read_to_string(&mut actual).unwrap();
+
+        assert_eq!("data", actual);
+    }
+
+    #[test]
+    fn provider__eof_then_data__eof_read_before_data() {
+        let mut provider = SimulatedStdStreams::new();
+        let mut buf = vec![0; 4];
+
+        provider.write_input_eof();
+        provider.write_input("data".as_bytes());
+        let result1 = provider.input().read(&mut buf).unwrap();
+        let result2 = provider.input().read(&mut buf).unwrap();
+
+        assert_eq!(0, result1);
+        assert_eq!(4, result2);
+        assert_eq!(b"data", &buf[..]);
+    }
+
     #[test]
     fn provider__write_read_output__success() {
         let mut provider = SimulatedStdStreams::new();
@@ -253,6 +890,45 @@ mod tests {
         assert_eq!(&[1, 2, 3, 4], actual);
     }
 
+    #[test]
+    fn provider__utf8_strict_multibyte_split_across_writes__success() {
+        let mut provider = SimulatedStdStreams::new();
+        provider.set_output_utf8_strict(true);
+        let bytes = "h\u{e9}\u{1F600}".as_bytes();
+
+        for byte in bytes {
+            provider.output().write_all(&[*byte]).unwrap();
+        }
+        provider.output().flush().unwrap();
+
+        assert_eq!("h\u{e9}\u{1F600}", provider.read_output_str().unwrap());
+    }
+
+    #[test]
+    fn provider__utf8_strict_invalid_write__error_and_nothing_written() {
+        let mut provider = SimulatedStdStreams::new();
+        provider.set_output_utf8_strict(true);
+
+        let result = provider.output().write(b"ok\xFF");
+
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+        assert!(provider.read_output().is_empty());
+    }
+
+    #[test]
+    fn provider__utf8_strict_truncated_sequence__error_on_flush() {
+        let mut provider = SimulatedStdStreams::new();
+        provider.set_output_utf8_strict(true);
+
+        provider
+            .output()
+            .write_all(&"\u{e9}".as_bytes()[..1])
+            .unwrap();
+        let result = provider.output().flush();
+
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+    }
+
     #[test]
     fn provider__write_read_error__success() {
         let mut provider = SimulatedStdStreams::new();
@@ -265,4 +941,45 @@ mod tests {
         assert_eq!(2, result2);
         assert_eq!(&[1, 2, 3, 4], actual);
     }
+
+    #[test]
+    fn provider__redirect_output_then_drop__all_output_reaches_sink() {
+        let sink = SharedBuffer::default();
+        let mut provider = SimulatedStdStreams::new();
+        provider.redirect_output(sink.clone());
+
+        provider.output().write_all(b"first ").unwrap();
+        provider.output().flush().unwrap();
+        assert_eq!(b"first ", &sink.0.lock().unwrap()[..]);
+        provider.output().write_all(b"second").unwrap();
+        assert_eq!(b"first second", provider.read_output());
+        drop(provider);
+
+        assert_eq!(b"first second", &sink.0.lock().unwrap()[..]);
+    }
+
+    #[test]
+    fn dump__both_streams_written__labelled_sections() {
+        let mut provider = SimulatedStdStreams::new();
+        provider.output().write_all(b"out line\n").unwrap();
+        provider.error().write_all(b"err").unwrap();
+
+        let result = provider.dump();
+
+        assert_eq!(
+            "--- output (9 bytes) ---\nout line\n--- error (3 bytes) ---\nerr\n",
+            result
+        );
+    }
+
+    #[test]
+    fn output_flush_count__write_then_flush__counts_only_flushes() {
+        let mut provider = SimulatedStdStreams::new();
+
+        provider.output().write_all(b"progress").unwrap();
+        assert_eq!(0, provider.output_flush_count());
+
+        provider.output().flush().unwrap();
+        assert_eq!(1, provider.output_flush_count());
+    }
 }
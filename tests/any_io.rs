@@ -0,0 +1,31 @@
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+
+use std::env;
+use std::path::Path;
+
+use io_providers::{AnyIo, Env, Io};
+
+// Changing the process's environment while other threads may read it isn't safe, so this is kept
+// in its own test binary.
+#[test]
+fn any_io_from_env__var_set_then_unset__simulated_then_native() {
+    let var = "IO_PROVIDERS_TEST_SIMULATED";
+
+    env::set_var(var, "1");
+    let mut simulated = AnyIo::from_env(var).expect("Failed to create AnyIo");
+    env::remove_var(var);
+    let mut native = AnyIo::from_env(var).expect("Failed to create AnyIo");
+
+    assert!(simulated.is_simulated());
+    assert_eq!(Path::new("/"), simulated.env().current_dir().unwrap());
+    assert!(format!("{:?}", simulated.env()).starts_with("Simulated("));
+    assert!(format!("{:?}", simulated.std_streams()).starts_with("Simulated("));
+    assert!(!native.is_simulated());
+    assert_eq!(
+        env::current_dir().unwrap(),
+        native.env().current_dir().unwrap()
+    );
+    assert!(format!("{:?}", native.std_streams()).starts_with("Native("));
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "zip")]
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+
+use std::io;
+use std::path::Path;
+
+use io_providers::fs::{ArchiveFs, FileKind, Fs};
+
+/// Contains `dir/a.txt`, `dir/sub/b.txt` and `top.txt`, with an explicit entry only for `dir/`.
+static ARCHIVE: &[u8] = include_bytes!("fixtures/archive.zip");
+
+fn archive_fs() -> ArchiveFs {
+    ArchiveFs::new(io::Cursor::new(ARCHIVE)).expect("Failed to open archive")
+}
+
+#[test]
+fn read_to_string__entry_in_dir__contents() {
+    let fs = archive_fs();
+
+    assert_eq!("alpha\n", fs.read_to_string("/dir/a.txt").unwrap());
+    assert_eq!("beta\n", fs.read_to_string("dir/sub/b.txt").unwrap());
+}
+
+#[test]
+fn list_dir__dir_in_archive__immediate_children() {
+    let fs = archive_fs();
+
+    let entries = fs.list_dir("/dir").expect("Failed to list directory");
+
+    let paths: Vec<&Path> = entries.iter().map(|e| e.path()).collect();
+    assert_eq!(vec![Path::new("/dir/a.txt"), Path::new("/dir/sub")], paths);
+    assert_eq!(FileKind::Dir, entries[1].file_type().unwrap());
+}
+
+#[test]
+fn exists__root_file_and_missing_file__found_and_not_found() {
+    let fs = archive_fs();
+
+    assert!(fs.exists("/top.txt"));
+    assert!(!fs.exists("/missing.txt"));
+}
+
+#[test]
+fn write__any_path__permission_denied() {
+    let mut fs = archive_fs();
+
+    let result = fs.write("/dir/a.txt", "changed");
+
+    assert_eq!(io::ErrorKind::PermissionDenied, result.unwrap_err().kind());
+    assert_eq!("alpha\n", fs.read_to_string("/dir/a.txt").unwrap());
+}
+
+#[test]
+fn new__not_a_zip__error() {
+    assert!(ArchiveFs::new(io::Cursor::new(b"not a zip".to_vec())).is_err());
+}
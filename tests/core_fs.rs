@@ -0,0 +1,405 @@
+//! Tests of the default implementations of `Fs` methods, using an implementation which only
+//! provides the required ones.
+
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use io_providers::fs::{FileKind, Fs, OpenOptions, TempFs};
+
+/// A filesystem which implements only the required `Fs` methods, by delegating to a `TempFs`.
+struct CoreFs {
+    inner: TempFs,
+}
+
+impl CoreFs {
+    fn new() -> CoreFs {
+        CoreFs {
+            inner: TempFs::new().expect("Failed to create new TempFs"),
+        }
+    }
+}
+
+impl Fs for CoreFs {
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        open_options: &OpenOptions,
+    ) -> io::Result<fs::File> {
+        self.inner.open(path, open_options)
+    }
+
+    fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<u64> {
+        self.inner.copy(from, to)
+    }
+
+    fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.inner.create_dir(path)
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.inner.create_dir_all(path)
+    }
+
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q) -> io::Result<()> {
+        self.inner.hard_link(src, dst)
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata> {
+        self.inner.metadata(path)
+    }
+
+    fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        self.inner.read(path)
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::ReadDir> {
+        self.inner.read_dir(path)
+    }
+
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        self.inner.read_link(path)
+    }
+
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        self.inner.read_to_string(path)
+    }
+
+    fn remove_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.inner.remove_dir(path)
+    }
+
+    fn remove_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.inner.remove_dir_all(path)
+    }
+
+    fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.inner.remove_file(path)
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        self.inner.rename(from, to)
+    }
+
+    fn set_permissions<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        perm: fs::Permissions,
+    ) -> io::Result<()> {
+        self.inner.set_permissions(path, perm)
+    }
+
+    fn symlink_metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<fs::Metadata> {
+        self.inner.symlink_metadata(path)
+    }
+
+    fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&mut self, path: P, contents: C) -> io::Result<()> {
+        self.inner.write(path, contents)
+    }
+
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.inner.exists(path)
+    }
+}
+
+#[test]
+fn try_exists__file_missing_and_through_file__true_false_and_error() {
+    let mut fs = CoreFs::new();
+    fs.write("file.txt", "contents")
+        .expect("Failed to write test file");
+
+    assert!(fs.try_exists("file.txt").unwrap());
+    assert!(!fs.try_exists("missing.txt").unwrap());
+    assert!(fs.try_exists("file.txt/child.txt").is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn nlink__hard_link_added__count_includes_link() {
+    let mut fs = CoreFs::new();
+    fs.write("file.txt", "contents")
+        .expect("Failed to write test file");
+
+    fs.hard_link("file.txt", "link.txt")
+        .expect("Failed to create hard link");
+
+    assert_eq!(2, fs.nlink("file.txt").unwrap());
+}
+
+#[test]
+fn list_dir__mixed_entries__sorted_with_paths_relative_to_given_dir() {
+    let mut fs = CoreFs::new();
+    fs.create_dir_all("dir/sub")
+        .expect("Failed to create directories");
+    fs.write("dir/file.txt", "")
+        .expect("Failed to write test file");
+
+    let entries = fs.list_dir("dir").unwrap();
+
+    let paths: Vec<&Path> = entries.iter().map(|e| e.path()).collect();
+    assert_eq!(vec![Path::new("dir/file.txt"), Path::new("dir/sub")], paths);
+    assert_eq!(FileKind::File, entries[0].file_type().unwrap());
+    assert_eq!(FileKind::Dir, entries[1].file_type().unwrap());
+}
+
+#[test]
+fn stat__file_and_dir__kind_and_length_reported() {
+    let mut fs = CoreFs::new();
+    fs.create_dir("dir").expect("Failed to create directory");
+    fs.write("file.txt", "contents")
+        .expect("Failed to write test file");
+
+    let file = fs.stat("file.txt").unwrap();
+    let dir = fs.stat("dir").unwrap();
+
+    assert_eq!(FileKind::File, file.file_type());
+    assert_eq!(8, file.len());
+    assert!(dir.is_dir());
+}
+
+#[test]
+#[cfg(unix)]
+fn canonicalize__symlinks_and_dot_components__resolved() {
+    let mut fs = CoreFs::new();
+    fs.create_dir_all("a/b")
+        .expect("Failed to create directories");
+    std::os::unix::fs::symlink("a/b", fs.inner.path().join("link"))
+        .expect("Failed to create symlink");
+    std::os::unix::fs::symlink("loop", fs.inner.path().join("loop"))
+        .expect("Failed to create symlink");
+
+    assert_eq!(Path::new("/a/b"), fs.canonicalize("link/../b/./").unwrap());
+    assert_eq!(
+        io::ErrorKind::NotFound,
+        fs.canonicalize("link/missing").unwrap_err().kind()
+    );
+    assert_eq!(
+        io::ErrorKind::InvalidInput,
+        fs.canonicalize("loop").unwrap_err().kind()
+    );
+}
+
+#[test]
+fn open_reporting__existing_and_new_files__resolved_paths_reported() {
+    let mut fs = CoreFs::new();
+    fs.create_dir_all("dir/sub")
+        .expect("Failed to create directories");
+    fs.write("dir/existing.txt", "contents")
+        .expect("Failed to write test file");
+
+    let (existing, _) = fs
+        .open_reporting("dir/sub/../existing.txt", OpenOptions::new().read(true))
+        .unwrap();
+    let (created, _) = fs
+        .open_reporting(
+            "dir/sub/../new.txt",
+            OpenOptions::new().write(true).create(true),
+        )
+        .unwrap();
+
+    assert_eq!(Path::new("/dir/existing.txt"), existing);
+    assert_eq!(Path::new("/dir/new.txt"), created);
+    assert!(fs.inner.exists("dir/new.txt"));
+}
+
+#[test]
+fn read_lines__crlf_and_lf_lines__yields_each_line_without_newline() {
+    let mut fs = CoreFs::new();
+    fs.write("file.txt", "one\r\ntwo\nthree")
+        .expect("Failed to write test file");
+
+    let lines = fs
+        .read_lines("file.txt")
+        .unwrap()
+        .collect::<io::Result<Vec<String>>>()
+        .unwrap();
+
+    assert_eq!(vec!["one", "two", "three"], lines);
+}
+
+#[test]
+fn ensure_dir__missing_existing_and_file__true_false_then_error() {
+    let mut fs = CoreFs::new();
+    fs.write("file.txt", "contents")
+        .expect("Failed to write test file");
+
+    assert!(fs.ensure_dir("dir").unwrap());
+    assert!(!fs.ensure_dir("dir").unwrap());
+    assert_eq!(
+        io::ErrorKind::NotADirectory,
+        fs.ensure_dir("file.txt").unwrap_err().kind()
+    );
+}
+
+#[test]
+fn read_to_string_limited__files_at_and_over_limit__contents_and_invalid_data() {
+    let mut fs = CoreFs::new();
+    fs.write("short.txt", "1234")
+        .expect("Failed to write test file");
+    fs.write("long.txt", "12345")
+        .expect("Failed to write test file");
+
+    let result = fs.read_to_string_limited("long.txt", 4);
+
+    assert_eq!("1234", fs.read_to_string_limited("short.txt", 4).unwrap());
+    assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+}
+
+#[test]
+fn write_at__offsets_within_and_past_end__patched_and_extended() {
+    let mut fs = CoreFs::new();
+    fs.write("file.txt", "AAAA")
+        .expect("Failed to write test file");
+
+    fs.write_at("file.txt", 1, "BB").unwrap();
+    fs.write_at("file.txt", 6, "C").unwrap();
+
+    assert_eq!(b"ABBA\0\0C", &fs.read("file.txt").unwrap()[..]);
+}
+
+#[test]
+fn swap__two_dirs__contents_are_exchanged() {
+    let mut fs = CoreFs::new();
+    fs.create_dir_all("a").expect("Failed to create directory");
+    fs.create_dir_all("b").expect("Failed to create directory");
+    fs.write("a/file.txt", "first")
+        .expect("Failed to write test file");
+    fs.write("b/file.txt", "second")
+        .expect("Failed to write test file");
+
+    fs.swap("a", "b").expect("Failed to swap directories");
+
+    assert_eq!("second", fs.read_to_string("a/file.txt").unwrap());
+    assert_eq!("first", fs.read_to_string("b/file.txt").unwrap());
+    assert_eq!(2, fs.inner.list_dir("").unwrap().len());
+}
+
+#[test]
+fn swap__missing_second_path__first_path_restored() {
+    let mut fs = CoreFs::new();
+    fs.write("a.txt", "first")
+        .expect("Failed to write test file");
+
+    let result = fs.swap("a.txt", "missing.txt");
+
+    assert_eq!(io::ErrorKind::NotFound, result.unwrap_err().kind());
+    assert_eq!("first", fs.read_to_string("a.txt").unwrap());
+    assert_eq!(1, fs.inner.list_dir("").unwrap().len());
+}
+
+#[test]
+fn copy_streaming__multi_chunk_file__copied_with_progress() {
+    let mut fs = CoreFs::new();
+    let contents = vec![7; 200 * 1024];
+    fs.write("src.bin", &contents)
+        .expect("Failed to write test file");
+    let mut progress = Vec::new();
+
+    let result = fs.copy_streaming("src.bin", "dst.bin", |copied| {
+        progress.push(copied);
+        true
+    });
+
+    assert_eq!(contents.len() as u64, result.unwrap());
+    assert_eq!(contents, fs.read("dst.bin").unwrap());
+    assert!(progress.len() > 1);
+    assert_eq!(Some(&(contents.len() as u64)), progress.last());
+}
+
+#[test]
+fn copy_streaming__cancelled_over_existing_destination__destination_unchanged() {
+    let mut fs = CoreFs::new();
+    fs.write("src.bin", vec![7; 200 * 1024])
+        .expect("Failed to write test file");
+    fs.write("dst.bin", "original")
+        .expect("Failed to write test file");
+
+    let result = fs.copy_streaming("src.bin", "dst.bin", |_| false);
+
+    assert_eq!(io::ErrorKind::Interrupted, result.unwrap_err().kind());
+    assert_eq!("original", fs.read_to_string("dst.bin").unwrap());
+    assert_eq!(2, fs.inner.list_dir("").unwrap().len());
+}
+
+#[test]
+fn truncate__existing_and_missing__emptied_or_not_found() {
+    let mut fs = CoreFs::new();
+    fs.write("file.txt", "contents")
+        .expect("Failed to write test file");
+
+    fs.truncate("file.txt").unwrap();
+
+    assert_eq!("", fs.read_to_string("file.txt").unwrap());
+    assert_eq!(
+        io::ErrorKind::NotFound,
+        fs.truncate("missing.txt").unwrap_err().kind()
+    );
+    assert!(!fs.exists("missing.txt"));
+}
+
+#[test]
+fn read_prefix__longer_and_shorter_files__at_most_n_bytes() {
+    let mut fs = CoreFs::new();
+    fs.write("longer.bin", b"\x89PNG\r\n")
+        .expect("Failed to write test file");
+    fs.write("shorter.bin", b"\x89P")
+        .expect("Failed to write test file");
+
+    assert_eq!(b"\x89PNG", &fs.read_prefix("longer.bin", 4).unwrap()[..]);
+    assert_eq!(b"\x89P", &fs.read_prefix("shorter.bin", 4).unwrap()[..]);
+}
+
+#[test]
+fn read_lines_limited__normal_and_overlong_lines__error_only_for_overlong() {
+    let mut fs = CoreFs::new();
+    let long_line = "x".repeat(100 * 1024);
+    let contents = format!("short\r\n{}\nexactly8\nlast", long_line);
+    fs.write("test.txt", contents)
+        .expect("Failed to write test file");
+
+    let lines: Vec<io::Result<String>> = fs.read_lines_limited("test.txt", 8).unwrap().collect();
+
+    assert_eq!(4, lines.len());
+    assert_eq!("short", lines[0].as_ref().unwrap());
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        lines[1].as_ref().unwrap_err().kind()
+    );
+    assert_eq!("exactly8", lines[2].as_ref().unwrap());
+    assert_eq!("last", lines[3].as_ref().unwrap());
+}
+
+#[test]
+fn kind__file_dir_and_missing__reported() {
+    let mut fs = CoreFs::new();
+    fs.create_dir("dir").expect("Failed to create directory");
+    fs.write("dir/file.txt", "contents")
+        .expect("Failed to write test file");
+
+    assert_eq!(Some(FileKind::Dir), fs.kind("dir").unwrap());
+    assert_eq!(Some(FileKind::File), fs.kind("dir/file.txt").unwrap());
+    assert_eq!(None, fs.kind("missing").unwrap());
+}
+
+#[test]
+fn append_locked__missing_then_existing__creates_then_appends() {
+    let mut fs = CoreFs::new();
+
+    fs.append_locked("log.txt", "first\n").unwrap();
+    fs.append_locked("log.txt", "second\n").unwrap();
+
+    assert_eq!("first\nsecond\n", fs.read_to_string("log.txt").unwrap());
+}
+
+#[test]
+fn write_counted__new_and_existing_file__count_equals_length() {
+    let mut fs = CoreFs::new();
+
+    assert_eq!(8, fs.write_counted("test.txt", "contents").unwrap());
+    assert_eq!(3, fs.write_counted("test.txt", "new").unwrap());
+    assert_eq!("new", fs.read_to_string("test.txt").unwrap());
+}
@@ -0,0 +1,61 @@
+//! Tests of the default implementations of `StdStreams` methods, using an implementation which
+//! only provides the required ones.
+
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+
+use std::io;
+
+use io_providers::std_streams::{StdStreams, StdStreamsExt};
+
+/// Standard streams which implement only the required `StdStreams` methods, reading input from a
+/// byte slice and writing output and errors to vectors.
+struct CoreStreams {
+    input: &'static [u8],
+    output: Vec<u8>,
+    error: Vec<u8>,
+}
+
+impl CoreStreams {
+    fn new(input: &'static [u8]) -> CoreStreams {
+        CoreStreams {
+            input,
+            output: Vec::new(),
+            error: Vec::new(),
+        }
+    }
+}
+
+impl StdStreams for CoreStreams {
+    fn input(&mut self) -> &mut dyn io::Read {
+        &mut self.input
+    }
+
+    fn output(&mut self) -> &mut dyn io::Write {
+        &mut self.output
+    }
+
+    fn error(&mut self) -> &mut dyn io::Write {
+        &mut self.error
+    }
+}
+
+#[test]
+fn buffered_input__line_read__rest_left_for_input() {
+    let mut streams = CoreStreams::new(b"line\nrest");
+    let mut rest = String::new();
+
+    let line = streams.read_line().unwrap();
+    streams.input().read_to_string(&mut rest).unwrap();
+
+    assert_eq!("line\n", line);
+    assert_eq!("rest", rest);
+}
+
+#[test]
+fn output_written__nothing_written__assumed_written() {
+    let streams = CoreStreams::new(b"");
+
+    assert!(streams.output_written());
+}
@@ -0,0 +1,337 @@
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+extern crate tempfile;
+
+use std::io;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use io_providers::fs::{ContentKind, Fs, FsExt, NativeFs, TempFs, WalkDir};
+
+fn touch__missing_then_existing__creates_and_preserves<F: Fs>(fs: &mut F, root: &Path) {
+    let path = root.join("test.txt");
+
+    fs.touch(&path).unwrap();
+    assert_eq!("", fs.read_to_string(&path).unwrap());
+
+    fs.write(&path, "contents").unwrap();
+    fs.touch(&path).unwrap();
+    assert_eq!("contents", fs.read_to_string(&path).unwrap());
+}
+
+fn copy_dir_all__nested_tree__copies_everything<F: Fs>(fs: &mut F, root: &Path) {
+    fs.create_dir(root.join("src")).unwrap();
+    fs.create_dir(root.join("src/sub")).unwrap();
+    fs.write(root.join("src/a.txt"), "a").unwrap();
+    fs.write(root.join("src/sub/b.txt"), "b").unwrap();
+
+    fs.copy_dir_all(root.join("src"), root.join("dst")).unwrap();
+
+    assert_eq!("a", fs.read_to_string(root.join("dst/a.txt")).unwrap());
+    assert_eq!("b", fs.read_to_string(root.join("dst/sub/b.txt")).unwrap());
+    assert!(fs.exists(root.join("src/sub/b.txt")));
+}
+
+fn rename_create__missing_nested_parent__creates_and_moves<F: Fs>(fs: &mut F, root: &Path) {
+    fs.write(root.join("file.txt"), "contents").unwrap();
+
+    fs.rename_create(root.join("file.txt"), root.join("new/nested/file.txt"))
+        .unwrap();
+
+    assert!(!fs.exists(root.join("file.txt")));
+    assert_eq!(
+        "contents",
+        fs.read_to_string(root.join("new/nested/file.txt")).unwrap()
+    );
+}
+
+fn move_path__same_device__moves_file<F: Fs>(fs: &mut F, root: &Path) {
+    fs.write(root.join("file.txt"), "contents").unwrap();
+
+    fs.move_path(root.join("file.txt"), root.join("moved.txt"))
+        .unwrap();
+
+    assert!(!fs.exists(root.join("file.txt")));
+    assert_eq!(
+        "contents",
+        fs.read_to_string(root.join("moved.txt")).unwrap()
+    );
+}
+
+fn write_atomic__existing_file__replaced_without_leftovers<F: Fs>(fs: &mut F, root: &Path) {
+    let dir = root.join("dir");
+    fs.create_dir(&dir).unwrap();
+    fs.write(dir.join("test.txt"), "old").unwrap();
+
+    fs.write_atomic(dir.join("test.txt"), "new").unwrap();
+
+    assert_eq!("new", fs.read_to_string(dir.join("test.txt")).unwrap());
+    let names: Vec<_> = fs
+        .list_dir(&dir)
+        .unwrap()
+        .iter()
+        .map(|e| e.file_name().to_os_string())
+        .collect();
+    assert_eq!(vec!["test.txt"], names);
+}
+
+fn read_to_string_lossy__invalid_utf8__replaces_sequences<F: Fs>(fs: &mut F, root: &Path) {
+    fs.write(root.join("test.txt"), b"ok\xFF").unwrap();
+
+    let result = fs.read_to_string_lossy(root.join("test.txt")).unwrap();
+
+    assert_eq!("ok\u{FFFD}", result);
+}
+
+fn glob__wildcards__returns_sorted_matches<F: Fs>(fs: &mut F, root: &Path) {
+    fs.create_dir(root.join("dir")).unwrap();
+    fs.write(root.join("dir/b.txt"), "").unwrap();
+    fs.write(root.join("dir/a.txt"), "").unwrap();
+    fs.write(root.join("dir/a.rs"), "").unwrap();
+    fs.create_dir(root.join("dir/sub")).unwrap();
+    fs.write(root.join("dir/sub/c.txt"), "").unwrap();
+    let flat_pattern = root.join("dir/?.txt");
+    let recursive_pattern = root.join("dir/**/*.txt");
+
+    let flat = fs.glob(flat_pattern.to_str().unwrap()).unwrap();
+    let recursive = fs.glob(recursive_pattern.to_str().unwrap()).unwrap();
+
+    assert_eq!(vec![root.join("dir/a.txt"), root.join("dir/b.txt")], flat);
+    assert_eq!(
+        vec![
+            root.join("dir/a.txt"),
+            root.join("dir/b.txt"),
+            root.join("dir/sub/c.txt"),
+        ],
+        recursive
+    );
+}
+
+fn metadata_opt__missing_and_existing__none_then_some<F: Fs>(fs: &mut F, root: &Path) {
+    let path = root.join("test.txt");
+
+    assert!(fs.metadata_opt(&path).unwrap().is_none());
+    fs.write(&path, "contents").unwrap();
+    assert!(fs.metadata_opt(&path).unwrap().unwrap().is_file());
+}
+
+fn remove_file_if_exists__existing_then_missing__true_then_false<F: Fs>(fs: &mut F, root: &Path) {
+    let path = root.join("test.txt");
+    fs.write(&path, "contents").unwrap();
+
+    assert!(fs.remove_file_if_exists(&path).unwrap());
+    assert!(!fs.remove_file_if_exists(&path).unwrap());
+}
+
+fn walk_dir_depth__three_level_tree__limited_to_depth<F: Fs>(fs: &mut F, root: &Path) {
+    let dir = root.join("dir");
+    fs.create_dir_all(dir.join("a/b")).unwrap();
+    fs.write(dir.join("top.txt"), "").unwrap();
+    fs.write(dir.join("a/mid.txt"), "").unwrap();
+    fs.write(dir.join("a/b/low.txt"), "").unwrap();
+    let relative = |walk: WalkDir| -> Vec<PathBuf> {
+        walk.map(|e| e.path().strip_prefix(&dir).unwrap().to_path_buf())
+            .collect()
+    };
+
+    let depth_1 = relative(fs.walk_dir_depth(&dir, 1).unwrap());
+    let depth_2 = relative(fs.walk_dir_depth(&dir, 2).unwrap());
+    let unlimited = relative(fs.walk_dir(&dir).unwrap());
+
+    assert_eq!(paths(&["a", "a/b", "a/mid.txt", "top.txt"]), depth_1);
+    assert_eq!(
+        paths(&["a", "a/b", "a/b/low.txt", "a/mid.txt", "top.txt"]),
+        depth_2
+    );
+    assert_eq!(depth_2, unlimited);
+}
+
+fn dir_size__nested_tree__sums_file_lengths<F: Fs>(fs: &mut F, root: &Path) {
+    let dir = root.join("dir");
+    fs.create_dir_all(dir.join("a/b")).unwrap();
+    fs.write(dir.join("top.txt"), "12345").unwrap();
+    fs.write(dir.join("a/mid.txt"), "123").unwrap();
+    fs.write(dir.join("a/b/low.txt"), "1234567").unwrap();
+    fs.write(root.join("outside.txt"), "not counted").unwrap();
+
+    assert_eq!(15, fs.dir_size(&dir).unwrap());
+}
+
+fn open_buffered_write_and_read__lines__round_trip<F: Fs>(fs: &mut F, root: &Path) {
+    let path = root.join("test.txt");
+    fs.write(&path, "old contents which are longer").unwrap();
+
+    let mut writer = fs.open_buffered_write(&path).unwrap();
+    writeln!(writer, "line 1").unwrap();
+    writeln!(writer, "line 2").unwrap();
+    writer.flush().unwrap();
+    let lines: Vec<String> = fs
+        .open_buffered_read(&path)
+        .unwrap()
+        .lines()
+        .collect::<io::Result<_>>()
+        .unwrap();
+
+    assert_eq!(vec!["line 1", "line 2"], lines);
+}
+
+fn read_dirs_only_and_read_files_only__mixed_dir__matching_subsets<F: Fs>(fs: &mut F, root: &Path) {
+    let dir = root.join("plugins");
+    fs.create_dir_all(dir.join("b_plugin/nested")).unwrap();
+    fs.create_dir(dir.join("a_plugin")).unwrap();
+    fs.write(dir.join("readme.txt"), "").unwrap();
+    fs.write(dir.join("b_plugin/plugin.toml"), "").unwrap();
+    fs.write(dir.join("config.toml"), "").unwrap();
+
+    assert_eq!(
+        vec![dir.join("a_plugin"), dir.join("b_plugin")],
+        fs.read_dirs_only(&dir).unwrap()
+    );
+    assert_eq!(
+        vec![dir.join("config.toml"), dir.join("readme.txt")],
+        fs.read_files_only(&dir).unwrap()
+    );
+}
+
+fn same_contents__identical_and_differing_files__compared<F: Fs>(fs: &mut F, root: &Path) {
+    fs.write(root.join("a.txt"), "contents").unwrap();
+    fs.write(root.join("b.txt"), "contents").unwrap();
+    fs.write(root.join("c.txt"), "Contents").unwrap();
+    fs.write(root.join("d.txt"), "longer contents").unwrap();
+
+    assert!(fs
+        .same_contents(root.join("a.txt"), root.join("b.txt"))
+        .unwrap());
+    assert!(!fs
+        .same_contents(root.join("a.txt"), root.join("c.txt"))
+        .unwrap());
+    assert!(!fs
+        .same_contents(root.join("a.txt"), root.join("d.txt"))
+        .unwrap());
+}
+
+fn write_if_changed__new_same_and_changed_contents__written_only_when_different<F: Fs>(
+    fs: &mut F,
+    root: &Path,
+) {
+    let path = root.join("generated.rs");
+
+    assert!(fs.write_if_changed(&path, "fn a() {}").unwrap());
+    let modified = fs.stat(&path).unwrap().modified();
+    thread::sleep(Duration::from_millis(20));
+    assert!(!fs.write_if_changed(&path, "fn a() {}").unwrap());
+    assert_eq!(modified, fs.stat(&path).unwrap().modified());
+    assert!(fs.write_if_changed(&path, "fn b() {}").unwrap());
+
+    assert_eq!("fn b() {}", fs.read_to_string(&path).unwrap());
+}
+
+fn sniff__text_binary_and_empty_files__classified<F: Fs>(fs: &mut F, root: &Path) {
+    let mut cut_off = vec![b'a'; 7999];
+    cut_off.extend_from_slice("é and more".as_bytes());
+    fs.write(root.join("text.txt"), "héllo\n").unwrap();
+    fs.write(root.join("cut_off.txt"), &cut_off).unwrap();
+    fs.write(root.join("nul.bin"), b"text\0with a NUL").unwrap();
+    fs.write(root.join("latin1.txt"), b"caf\xe9").unwrap();
+    fs.write(root.join("empty"), "").unwrap();
+
+    assert_eq!(
+        ContentKind::Utf8Text,
+        fs.sniff(root.join("text.txt")).unwrap()
+    );
+    assert_eq!(
+        ContentKind::Utf8Text,
+        fs.sniff(root.join("cut_off.txt")).unwrap()
+    );
+    assert_eq!(ContentKind::Binary, fs.sniff(root.join("nul.bin")).unwrap());
+    assert_eq!(
+        ContentKind::Binary,
+        fs.sniff(root.join("latin1.txt")).unwrap()
+    );
+    assert_eq!(ContentKind::Empty, fs.sniff(root.join("empty")).unwrap());
+}
+
+fn paths(paths: &[&str]) -> Vec<PathBuf> {
+    paths.iter().map(PathBuf::from).collect()
+}
+
+/// Generates a test per check for both `TempFs` (using paths relative to its root) and `NativeFs`
+/// (rooted in a fresh temporary directory).
+macro_rules! test_each_fs {
+    ($($check:ident),*) => {
+        mod temp_fs {
+            use super::*;
+            $(#[test] fn $check() { super::$check(&mut TempFs::new().unwrap(), Path::new("")); })*
+        }
+
+        mod native_fs {
+            use super::*;
+            $(#[test] fn $check() { super::$check(&mut NativeFs, tempfile::tempdir().unwrap().path()); })*
+        }
+    };
+}
+
+test_each_fs!(
+    touch__missing_then_existing__creates_and_preserves,
+    copy_dir_all__nested_tree__copies_everything,
+    rename_create__missing_nested_parent__creates_and_moves,
+    move_path__same_device__moves_file,
+    write_atomic__existing_file__replaced_without_leftovers,
+    read_to_string_lossy__invalid_utf8__replaces_sequences,
+    glob__wildcards__returns_sorted_matches,
+    metadata_opt__missing_and_existing__none_then_some,
+    remove_file_if_exists__existing_then_missing__true_then_false,
+    walk_dir_depth__three_level_tree__limited_to_depth,
+    dir_size__nested_tree__sums_file_lengths,
+    open_buffered_write_and_read__lines__round_trip,
+    read_dirs_only_and_read_files_only__mixed_dir__matching_subsets,
+    same_contents__identical_and_differing_files__compared,
+    write_if_changed__new_same_and_changed_contents__written_only_when_different,
+    sniff__text_binary_and_empty_files__classified
+);
+
+#[test]
+#[cfg(feature = "encoding")]
+fn write_encoding_and_read_to_string_encoding__utf16le__round_trip() {
+    use io_providers::fs::Encoding;
+
+    let mut fs = TempFs::new().unwrap();
+    let text = "héllo, 世界 🌍\r\n";
+
+    fs.write_encoding("utf16.txt", text, Encoding::Utf16Le)
+        .unwrap();
+    fs.write("bom.txt", b"\xEF\xBB\xBFutf-8 with BOM").unwrap();
+
+    assert!(fs.read("utf16.txt").unwrap().starts_with(b"\xFF\xFE"));
+    assert_eq!(
+        text,
+        fs.read_to_string_encoding("utf16.txt", Encoding::Utf16Le)
+            .unwrap()
+    );
+    assert_eq!(
+        "utf-8 with BOM",
+        fs.read_to_string_encoding("bom.txt", Encoding::Utf16Le)
+            .unwrap()
+    );
+}
+
+#[test]
+#[cfg(feature = "hash")]
+fn digest__known_and_identical_contents__expected_and_equal_digests() {
+    let mut fs = TempFs::new().unwrap();
+    fs.write("abc.txt", "abc").unwrap();
+    fs.write("copy.txt", "abc").unwrap();
+
+    let digest = fs.digest("abc.txt").unwrap();
+
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    assert_eq!(
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        hex
+    );
+    assert!(fs.same_contents("abc.txt", "copy.txt").unwrap());
+    assert_eq!(digest, fs.digest("copy.txt").unwrap());
+}
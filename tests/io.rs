@@ -0,0 +1,255 @@
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+
+use std::env;
+use std::io::Read;
+use std::path::Path;
+
+use io_providers::{Env, EnvExt, Fs, Io, SimulatedIo, SimulatedStdStreams, StdStreams};
+
+fn greet(streams: &mut dyn StdStreams) {
+    write!(streams.output(), "hello").unwrap();
+}
+
+fn log_level<I: Io>(io: &I) -> String {
+    io.env()
+        .var("LOG_LEVEL")
+        .unwrap_or_else(|_| "info".to_owned())
+}
+
+#[test]
+fn env__through_shared_reference__reads_var() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    io.env_mut().set_var("LOG_LEVEL", "debug");
+
+    assert_eq!("debug", log_level(&io));
+}
+
+#[test]
+fn std_streams_dyn__passed_to_non_generic_fn__writes_to_provider() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+
+    greet(io.std_streams_dyn());
+
+    assert_eq!(b"hello", io.std_streams().read_output());
+}
+
+#[test]
+fn std_streams_mut__write_through_alias__visible_through_std_streams() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+
+    write!(io.std_streams_mut().output(), "hello").unwrap();
+
+    assert_eq!(b"hello", io.std_streams().read_output());
+}
+
+#[test]
+fn simulated_io__current_dir_not_set__defaults_to_fs_root() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+
+    let cur_dir = io.env().current_dir().unwrap();
+    io.fs_mut()
+        .write(cur_dir.join("file.txt"), "contents")
+        .expect("Failed to write test file");
+
+    assert_eq!(Path::new("/"), cur_dir);
+    assert_eq!("contents", io.fs().read_to_string("file.txt").unwrap());
+}
+
+#[test]
+fn open_input_arg__dash__reads_input_stream() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    io.std_streams().write_input("from stdin".as_bytes());
+    let mut contents = String::new();
+
+    io.open_input_arg("-")
+        .expect("Failed to open input")
+        .read_to_string(&mut contents)
+        .expect("Failed to read input");
+
+    assert_eq!("from stdin", contents);
+}
+
+#[test]
+fn open_input_arg__path__reads_file() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    io.fs_mut()
+        .write("/input.txt", "from file")
+        .expect("Failed to write test file");
+    let mut contents = String::new();
+
+    io.open_input_arg("/input.txt")
+        .expect("Failed to open input")
+        .read_to_string(&mut contents)
+        .expect("Failed to read input");
+
+    assert_eq!("from file", contents);
+}
+
+#[test]
+fn input_source__none__reads_input_stream() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    io.std_streams().write_input("from stdin".as_bytes());
+    let mut contents = String::new();
+
+    io.input_source(None)
+        .expect("Failed to open input")
+        .read_to_string(&mut contents)
+        .expect("Failed to read input");
+
+    assert_eq!("from stdin", contents);
+}
+
+#[test]
+fn input_source__path_of_dash__reads_file() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    io.std_streams().write_input("from stdin".as_bytes());
+    io.fs_mut()
+        .write("-", "from file")
+        .expect("Failed to write test file");
+    let mut contents = String::new();
+
+    io.input_source(Some(Path::new("-")))
+        .expect("Failed to open input")
+        .read_to_string(&mut contents)
+        .expect("Failed to read input");
+
+    assert_eq!("from file", contents);
+}
+
+#[test]
+fn env_reads__vars_read_repeatedly__recorded_once_in_order() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    io.env_mut().set_var("A", "1");
+
+    let _ = io.env().var("A");
+    let _ = io.env().var_os("B");
+    let _ = io.env().var("A");
+
+    assert_eq!(vec!["A", "B"], io.env_reads());
+}
+
+#[test]
+fn seed_file__nested_path__readable_through_fs() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+
+    io.seed_file("/fixtures/data.txt", "fixture")
+        .expect("Failed to seed file");
+
+    assert_eq!(
+        "fixture",
+        io.fs().read_to_string("/fixtures/data.txt").unwrap()
+    );
+    assert!(io.fs_root().join("fixtures/data.txt").is_file());
+}
+
+#[test]
+fn assert_transcript__matching_streams__passes() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    writeln!(io.std_streams().output(), "line 1\nline 2").unwrap();
+    write!(io.std_streams().error(), "warning").unwrap();
+
+    io.assert_transcript("line 1\nline 2\n", "warning");
+}
+
+#[test]
+#[should_panic(expected = "--- output ---\n  \"line 1\\n\"\n- \"line 2\\n\"\n+ \"line two\\n\"\n")]
+fn assert_transcript__mismatched_output__panics_with_diff() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    writeln!(io.std_streams().output(), "line 1\nline two").unwrap();
+
+    io.assert_transcript("line 1\nline 2\n", "");
+}
+
+#[test]
+#[should_panic(
+    expected = "--- output ---\n  \"a\\n\"\n+ \"inserted\\n\"\n  \"b\\n\"\n  \"c\\n\"\n"
+)]
+fn assert_transcript__inserted_output_line__only_insertion_marked() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    writeln!(io.std_streams().output(), "a\ninserted\nb\nc").unwrap();
+
+    io.assert_transcript("a\nb\nc\n", "");
+}
+
+#[test]
+fn which__program_in_later_path_dir__found_in_sandbox() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    let name = if cfg!(windows) { "tool.exe" } else { "tool" };
+    let tool = Path::new("/opt/bin").join(name);
+    io.seed_file(&tool, "#!/bin/sh\n")
+        .expect("Failed to seed file");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        io.fs_mut()
+            .set_permissions(&tool, std::fs::Permissions::from_mode(0o755))
+            .expect("Failed to set permissions");
+    }
+    io.fs_mut()
+        .create_dir_all("/usr/bin")
+        .expect("Failed to create directory");
+    let path = env::join_paths(["/usr/bin", "/opt/bin"]).unwrap();
+    io.env_mut().set_var("PATH", path);
+
+    assert_eq!(Some(tool), io.env().which(io.fs(), "tool"));
+    assert_eq!(None, io.env().which(io.fs(), "missing"));
+}
+
+#[test]
+#[cfg(windows)]
+fn which__program_without_extension__pathext_extensions_tried_in_order() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    io.seed_file("/bin/tool.bat", "@echo off\r\n")
+        .expect("Failed to seed file");
+    io.seed_file("/bin/tool.cmd", "@echo off\r\n")
+        .expect("Failed to seed file");
+    io.env_mut().set_var("PATH", "/bin");
+    io.env_mut().set_var("PATHEXT", ".COM;.EXE;.BAT;.CMD");
+
+    assert_eq!(
+        Some(Path::new("/bin").join("tool.bat")),
+        io.env().which(io.fs(), "tool")
+    );
+    assert_eq!(
+        Some(Path::new("/bin").join("tool.cmd")),
+        io.env().which(io.fs(), "tool.cmd")
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn which__file_not_executable__not_found() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    io.seed_file("/bin/tool", "data")
+        .expect("Failed to seed file");
+    io.fs_mut()
+        .set_permissions("/bin/tool", std::fs::Permissions::from_mode(0o644))
+        .expect("Failed to set permissions");
+    io.env_mut().set_var("PATH", "/bin");
+
+    assert_eq!(None, io.env().which(io.fs(), "tool"));
+}
+
+#[test]
+fn with_std_streams__capturing_streams__used_for_one_call_then_restored() {
+    let mut io = SimulatedIo::new().expect("Failed to create new SimulatedIo");
+    let mut replacement = SimulatedStdStreams::new();
+    replacement.write_input(b"replacement input");
+    write!(io.std_streams().output(), "before ").unwrap();
+
+    let (input, captured) = io.with_std_streams(replacement, |io| {
+        write!(io.std_streams().output(), "during").unwrap();
+        let mut input = String::new();
+        io.std_streams().input().read_to_string(&mut input).unwrap();
+        input
+    });
+    write!(io.std_streams().output(), "after").unwrap();
+
+    assert_eq!("replacement input", input);
+    assert_eq!(b"during", captured.read_output());
+    io.assert_transcript("before after", "");
+}
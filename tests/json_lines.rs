@@ -0,0 +1,49 @@
+#![cfg(feature = "serde")]
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+#[macro_use]
+extern crate serde_derive;
+
+use io_providers::{SimulatedStdStreams, StdStreams};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Event {
+    name: String,
+    count: u32,
+}
+
+fn emit_events(streams: &mut dyn StdStreams) {
+    writeln!(streams.output(), r#"{{"name":"start","count":1}}"#).unwrap();
+    write!(streams.output(), "{{\"name\":\"stop\",\"count\":2}}\r\n\n").unwrap();
+}
+
+#[test]
+fn read_output_json_lines__two_objects__deserialized_in_order() {
+    let mut streams = SimulatedStdStreams::new();
+
+    emit_events(&mut streams);
+
+    assert_eq!(
+        vec![
+            Event {
+                name: "start".to_owned(),
+                count: 1,
+            },
+            Event {
+                name: "stop".to_owned(),
+                count: 2,
+            },
+        ],
+        streams.read_output_json_lines::<Event>().unwrap()
+    );
+}
+
+#[test]
+fn read_output_json_lines__invalid_line__error() {
+    let mut streams = SimulatedStdStreams::new();
+
+    writeln!(streams.output(), "not json").unwrap();
+
+    assert!(streams.read_output_json_lines::<Event>().is_err());
+}
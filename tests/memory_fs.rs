@@ -0,0 +1,539 @@
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+extern crate tempfile;
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use io_providers::fs::{
+    self, FileKind, Fs, FsEvent, FsExt, FsOp, MemoryFs, NativeFs, OpenOptions, TempFs,
+};
+
+#[test]
+fn clone_handle__write_through_one_handle__visible_through_other() {
+    let mut fs_a = MemoryFs::new();
+    let mut fs_b = fs_a.clone_handle();
+
+    fs_a.write("/test.txt", "contents")
+        .expect("Failed to write file");
+    assert_eq!("contents", fs_b.read_to_string("/test.txt").unwrap());
+
+    fs_b.remove_file("/test.txt")
+        .expect("Failed to remove file");
+    assert!(!fs_a.exists("/test.txt"));
+}
+
+#[test]
+fn write__relative_and_absolute_paths__same_file() {
+    let mut fs = MemoryFs::new();
+
+    fs.write("dir/../test.txt", "contents")
+        .expect("Failed to write file");
+
+    assert_eq!("contents", fs.read_to_string("/test.txt").unwrap());
+}
+
+#[test]
+fn write__missing_parent__fails_with_not_found() {
+    let mut fs = MemoryFs::new();
+
+    let result = fs.write("/dir/test.txt", "contents");
+
+    assert_eq!(io::ErrorKind::NotFound, result.unwrap_err().kind());
+}
+
+#[test]
+fn create_dir_all__nested_path__creates_each_dir() {
+    let mut fs = MemoryFs::new();
+
+    fs.create_dir_all("/a/b/c")
+        .expect("Failed to create directories");
+    fs.write("/a/b/c/test.txt", "contents")
+        .expect("Failed to write file");
+
+    assert!(fs.exists("/a/b"));
+    assert_eq!(
+        io::ErrorKind::DirectoryNotEmpty,
+        fs.remove_dir("/a/b").unwrap_err().kind()
+    );
+    fs.remove_dir_all("/a")
+        .expect("Failed to remove directories");
+    assert!(!fs.exists("/a/b/c/test.txt"));
+}
+
+#[test]
+fn create_dir_all__file_further_down_path__fails_without_creating_anything() {
+    let mut fs = MemoryFs::new();
+    fs.create_dir("/a").unwrap();
+    fs.write("/a/file", "contents").unwrap();
+
+    let result = fs.create_dir_all("/a/file/b/c");
+
+    assert_eq!(io::ErrorKind::AlreadyExists, result.unwrap_err().kind());
+    assert!(!fs.exists("/a/file/b"));
+    assert_eq!("contents", fs.read_to_string("/a/file").unwrap());
+}
+
+#[test]
+fn rename__directory__moves_contents() {
+    let mut fs = MemoryFs::new();
+    fs.create_dir("/src").expect("Failed to create directory");
+    fs.write("/src/test.txt", "contents")
+        .expect("Failed to write file");
+
+    fs.rename("/src", "/dst").expect("Failed to rename");
+
+    assert!(!fs.exists("/src"));
+    assert_eq!("contents", fs.read_to_string("/dst/test.txt").unwrap());
+}
+
+#[test]
+fn swap__dirs_beside_file_named_like_temp__only_dirs_exchanged() {
+    let mut fs = MemoryFs::new();
+    fs.create_dir_all("/a/sub")
+        .expect("Failed to create directories");
+    fs.create_dir_all("/b").expect("Failed to create directory");
+    fs.write("/a/sub/file.txt", "first")
+        .expect("Failed to write file");
+    fs.write("/a.swap-tmp", "unrelated")
+        .expect("Failed to write file");
+
+    fs.swap("/a", "/b").expect("Failed to swap directories");
+
+    assert_eq!("first", fs.read_to_string("/b/sub/file.txt").unwrap());
+    assert!(fs.list_dir("/a").unwrap().is_empty());
+    assert_eq!("unrelated", fs.read_to_string("/a.swap-tmp").unwrap());
+}
+
+#[test]
+#[cfg(unix)]
+fn hard_link__write_through_link__both_paths_see_change() {
+    let mut fs = MemoryFs::new();
+    fs.write("/a.txt", "old").expect("Failed to write file");
+
+    fs.hard_link("/a.txt", "/b.txt")
+        .expect("Failed to create link");
+    fs.write("/b.txt", "new").expect("Failed to write file");
+
+    assert_eq!("new", fs.read_to_string("/a.txt").unwrap());
+    assert_eq!(2, fs.nlink("/a.txt").unwrap());
+    fs.remove_file("/a.txt").expect("Failed to remove file");
+    assert_eq!(1, fs.nlink(Path::new("/b.txt")).unwrap());
+}
+
+#[test]
+fn open__any_path__fails_with_unsupported() {
+    let mut fs = MemoryFs::new();
+
+    let result = fs.open("/test.txt", OpenOptions::new().write(true).create(true));
+
+    assert_eq!(io::ErrorKind::Unsupported, result.unwrap_err().kind());
+}
+
+#[test]
+fn write_at__offset_past_end__gap_filled_with_zeros() {
+    let mut fs = MemoryFs::new();
+    fs.write("/test.bin", [1, 2]).expect("Failed to write file");
+
+    fs.write_at("/test.bin", 1, [3])
+        .expect("Failed to patch file");
+    fs.write_at("/test.bin", 4, [4])
+        .expect("Failed to patch file");
+
+    assert_eq!(vec![1, 3, 0, 0, 4], fs.read("/test.bin").unwrap());
+}
+
+#[test]
+fn append_locked__directory_and_missing_parent__fail() {
+    let mut fs = MemoryFs::new();
+    fs.create_dir("/dir").unwrap();
+
+    let dir_result = fs.append_locked("/dir", "contents");
+    let parent_result = fs.append_locked("/missing/log.txt", "contents");
+
+    assert_eq!(io::ErrorKind::IsADirectory, dir_result.unwrap_err().kind());
+    assert_eq!(io::ErrorKind::NotFound, parent_result.unwrap_err().kind());
+}
+
+#[test]
+fn list_dir__nested_tree__only_immediate_children() {
+    let mut fs = MemoryFs::new();
+    fs.create_dir_all("/dir/sub/deeper")
+        .expect("Failed to create directories");
+    fs.write("/dir/b.txt", "").expect("Failed to write file");
+    fs.write("/dir/a.txt", "").expect("Failed to write file");
+
+    let entries = fs.list_dir("dir").expect("Failed to list directory");
+
+    let paths: Vec<&Path> = entries.iter().map(|e| e.path()).collect();
+    assert_eq!(
+        vec![
+            Path::new("dir/a.txt"),
+            Path::new("dir/b.txt"),
+            Path::new("dir/sub")
+        ],
+        paths
+    );
+    assert_eq!(FileKind::Dir, entries[2].file_type().unwrap());
+}
+
+#[test]
+fn stat__same_tree_as_native_fs__same_metadata() {
+    let root = tempfile::tempdir().expect("Failed to create temp dir");
+    let mut native_fs = NativeFs;
+    let mut memory_fs = MemoryFs::new();
+    native_fs
+        .write(root.path().join("file.txt"), "contents")
+        .expect("Failed to write native file");
+    memory_fs
+        .write("/file.txt", "contents")
+        .expect("Failed to write memory file");
+
+    let (native_file, memory_file) = (
+        native_fs.stat(root.path().join("file.txt")).unwrap(),
+        memory_fs.stat("/file.txt").unwrap(),
+    );
+    let (native_dir, memory_dir) = (
+        native_fs.stat(root.path()).unwrap(),
+        memory_fs.stat("/").unwrap(),
+    );
+
+    assert_eq!(native_file.len(), memory_file.len());
+    assert_eq!(native_file.file_type(), memory_file.file_type());
+    assert_eq!(native_file.readonly(), memory_file.readonly());
+    assert!(memory_file.modified().is_some());
+    assert_eq!(native_dir.file_type(), memory_dir.file_type());
+    assert!(memory_dir.is_dir());
+    assert_eq!(
+        io::ErrorKind::NotFound,
+        memory_fs.stat("/missing").unwrap_err().kind()
+    );
+}
+
+#[test]
+fn set_latency__write_delayed__only_affects_own_handle() {
+    let mut fs = MemoryFs::new();
+    let mut other = fs.clone_handle();
+    // Long enough that an undelayed operation won't take as long even on a loaded machine
+    let delay = Duration::from_millis(500);
+
+    fs.set_latency(FsOp::Write, delay);
+    let start = Instant::now();
+    fs.write("/test.txt", "contents")
+        .expect("Failed to write file");
+    assert!(start.elapsed() >= delay);
+
+    let start = Instant::now();
+    other
+        .write("/test.txt", "contents")
+        .expect("Failed to write file");
+    assert!(start.elapsed() < delay);
+}
+
+#[test]
+fn operations__write_rename_remove__recorded_in_order() {
+    let mut fs = MemoryFs::new();
+    fs.create_dir("/dir").unwrap();
+    fs.write("/dir/a.txt", "contents").unwrap();
+    fs.rename("/dir/a.txt", "/dir/b.txt").unwrap();
+    fs.remove_file("/dir/b.txt").unwrap();
+    let _ = fs.remove_file("/dir/missing.txt");
+    let _ = fs.read_to_string("/dir/b.txt");
+
+    assert_eq!(
+        &[
+            FsEvent::CreateDir(PathBuf::from("/dir")),
+            FsEvent::Write(PathBuf::from("/dir/a.txt")),
+            FsEvent::Rename {
+                from: PathBuf::from("/dir/a.txt"),
+                to: PathBuf::from("/dir/b.txt"),
+            },
+            FsEvent::Remove(PathBuf::from("/dir/b.txt")),
+        ][..],
+        fs.operations()
+    );
+}
+
+#[test]
+fn operations__create_dir_all_blocked_by_file__nothing_recorded() {
+    let mut fs = MemoryFs::new();
+    fs.write("/file", "contents").unwrap();
+
+    let _ = fs.create_dir_all("/file/a/b");
+
+    assert_eq!(
+        &[FsEvent::Write(PathBuf::from("/file"))][..],
+        fs.operations()
+    );
+}
+
+#[test]
+fn copy_streaming__cancelled_after_first_chunk__destination_not_written() {
+    let mut fs = MemoryFs::new();
+    fs.write("/src.bin", vec![7; 200 * 1024]).unwrap();
+    let mut calls = 0;
+
+    let result = fs.copy_streaming("/src.bin", "/dst.bin", |_| {
+        calls += 1;
+        false
+    });
+
+    assert_eq!(io::ErrorKind::Interrupted, result.unwrap_err().kind());
+    assert_eq!(1, calls);
+    assert!(!fs.exists("/dst.bin"));
+}
+
+#[test]
+fn truncate__hard_linked_file__emptied_through_both_paths() {
+    let mut fs = MemoryFs::new();
+    fs.write("/a.txt", "contents").unwrap();
+    fs.hard_link("/a.txt", "/b.txt").unwrap();
+
+    fs.truncate("/a.txt").unwrap();
+
+    assert_eq!("", fs.read_to_string("/b.txt").unwrap());
+    assert_eq!(
+        io::ErrorKind::NotFound,
+        fs.truncate("/missing.txt").unwrap_err().kind()
+    );
+}
+
+#[test]
+fn hard_link__directory__invalid_input() {
+    let mut fs = MemoryFs::new();
+    fs.create_dir("/dir").unwrap();
+
+    let result = fs.hard_link("/dir", "/dir_link");
+
+    assert_eq!(io::ErrorKind::InvalidInput, result.unwrap_err().kind());
+}
+
+#[test]
+fn rename__equivalent_paths__no_op_and_not_logged() {
+    let mut fs = MemoryFs::new();
+    fs.create_dir("/dir").unwrap();
+    fs.write("/dir/file.txt", "contents").unwrap();
+
+    fs.rename("/dir", "/dir/.").unwrap();
+    fs.rename("/dir/file.txt", "/dir/../dir/file.txt").unwrap();
+
+    assert_eq!("contents", fs.read_to_string("/dir/file.txt").unwrap());
+    assert!(!fs
+        .operations()
+        .iter()
+        .any(|event| matches!(event, FsEvent::Rename { .. })));
+}
+
+#[test]
+fn kind__file_dir_and_missing__reported() {
+    let mut fs = MemoryFs::new();
+    fs.create_dir("/dir").unwrap();
+    fs.write("/dir/file.txt", "contents").unwrap();
+
+    assert_eq!(Some(FileKind::Dir), fs.kind("/dir").unwrap());
+    assert_eq!(Some(FileKind::File), fs.kind("/dir/file.txt").unwrap());
+    assert_eq!(None, fs.kind("/missing").unwrap());
+}
+
+#[test]
+fn ensure_dir__missing_existing_and_file__true_false_then_error() {
+    let mut fs = MemoryFs::new();
+    fs.write("/file.txt", "contents").unwrap();
+
+    assert!(fs.ensure_dir("/dir").unwrap());
+    assert!(!fs.ensure_dir("/dir").unwrap());
+    assert_eq!(
+        io::ErrorKind::NotADirectory,
+        fs.ensure_dir("/file.txt").unwrap_err().kind()
+    );
+}
+
+#[test]
+fn write_at__offset_within_file__patches_in_place() {
+    let mut fs = MemoryFs::new();
+    fs.write("/test.txt", "AAAA").unwrap();
+
+    fs.write_at("/test.txt", 1, "BB").unwrap();
+
+    assert_eq!("ABBA", fs.read_to_string("/test.txt").unwrap());
+}
+
+#[test]
+fn write_at__end_past_maximum_size__invalid_input_and_unchanged() {
+    let mut fs = MemoryFs::new();
+    fs.write("/test.txt", "AAAA").unwrap();
+
+    let result = fs.write_at("/test.txt", u64::MAX, "BB");
+
+    assert_eq!(io::ErrorKind::InvalidInput, result.unwrap_err().kind());
+    assert_eq!("AAAA", fs.read_to_string("/test.txt").unwrap());
+}
+
+#[test]
+fn copy_streaming__cancelled_over_existing_destination__destination_unchanged() {
+    let mut fs = MemoryFs::new();
+    fs.write("/src.bin", vec![7; 200 * 1024]).unwrap();
+    fs.write("/dst.bin", "original").unwrap();
+
+    let result = fs.copy_streaming("/src.bin", "/dst.bin", |_| false);
+
+    assert_eq!(io::ErrorKind::Interrupted, result.unwrap_err().kind());
+    assert_eq!("original", fs.read_to_string("/dst.bin").unwrap());
+}
+
+#[test]
+fn read_prefix__longer_equal_and_shorter_files__at_most_n_bytes() {
+    let mut fs = MemoryFs::new();
+    fs.write("/longer.bin", b"\x89PNG\r\n").unwrap();
+    fs.write("/equal.bin", b"\x89PNG").unwrap();
+    fs.write("/shorter.bin", b"\x89P").unwrap();
+
+    assert_eq!(b"\x89PNG", &fs.read_prefix("/longer.bin", 4).unwrap()[..]);
+    assert_eq!(b"\x89PNG", &fs.read_prefix("/equal.bin", 4).unwrap()[..]);
+    assert_eq!(b"\x89P", &fs.read_prefix("/shorter.bin", 4).unwrap()[..]);
+}
+
+#[test]
+fn read_lines_limited__normal_and_overlong_lines__error_only_for_overlong() {
+    let mut fs = MemoryFs::new();
+    let long_line = "x".repeat(100 * 1024);
+    let contents = format!("short\r\n{}\nexactly8\nlast", long_line);
+    fs.write("/test.txt", contents).unwrap();
+
+    let lines: Vec<io::Result<String>> = fs.read_lines_limited("/test.txt", 8).unwrap().collect();
+
+    assert_eq!(4, lines.len());
+    assert_eq!("short", lines[0].as_ref().unwrap());
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        lines[1].as_ref().unwrap_err().kind()
+    );
+    assert_eq!("exactly8", lines[2].as_ref().unwrap());
+    assert_eq!("last", lines[3].as_ref().unwrap());
+}
+
+#[test]
+#[cfg(unix)]
+fn transfer_all__link_back_up_the_tree__skipped() {
+    let mut src = TempFs::new().unwrap();
+    src.create_dir_all("/fixtures/nested").unwrap();
+    src.write("/fixtures/nested/a.txt", "aaa").unwrap();
+    std::os::unix::fs::symlink("..", src.path().join("fixtures/nested/parent")).unwrap();
+    let mut dst = MemoryFs::new();
+
+    let copied = fs::transfer_all(&src, &mut dst, "/fixtures", "/copy").unwrap();
+
+    assert_eq!(3, copied);
+    assert_eq!("aaa", dst.read_to_string("/copy/nested/a.txt").unwrap());
+    assert!(!dst.exists("/copy/nested/parent"));
+}
+
+#[test]
+fn append_locked__missing_then_existing__creates_then_appends() {
+    let mut fs = MemoryFs::new();
+
+    fs.append_locked("/log.txt", "first\n").unwrap();
+    fs.append_locked("/log.txt", "second\n").unwrap();
+
+    assert_eq!("first\nsecond\n", fs.read_to_string("/log.txt").unwrap());
+}
+
+#[test]
+fn write_counted__new_and_existing_file__count_equals_length() {
+    let mut fs = MemoryFs::new();
+
+    assert_eq!(8, fs.write_counted("/test.txt", "contents").unwrap());
+    assert_eq!(3, fs.write_counted("/test.txt", "new").unwrap());
+    assert_eq!("new", fs.read_to_string("/test.txt").unwrap());
+}
+
+#[test]
+fn copy_dir_all__nested_tree__copies_everything() {
+    let mut fs = MemoryFs::new();
+    fs.create_dir_all("/src/sub").unwrap();
+    fs.write("/src/a.txt", "a").unwrap();
+    fs.write("/src/sub/b.txt", "b").unwrap();
+
+    fs.copy_dir_all("/src", "/dst").unwrap();
+
+    assert_eq!("a", fs.read_to_string("/dst/a.txt").unwrap());
+    assert_eq!("b", fs.read_to_string("/dst/sub/b.txt").unwrap());
+    assert!(fs.exists("/src/sub/b.txt"));
+}
+
+#[test]
+fn glob__wildcards__returns_sorted_matches() {
+    let mut fs = MemoryFs::new();
+    fs.create_dir_all("/dir/sub").unwrap();
+    fs.write("/dir/b.txt", "").unwrap();
+    fs.write("/dir/a.txt", "").unwrap();
+    fs.write("/dir/a.rs", "").unwrap();
+    fs.write("/dir/sub/c.txt", "").unwrap();
+
+    let flat = fs.glob("/dir/?.txt").unwrap();
+    let recursive = fs.glob("/dir/**/*.txt").unwrap();
+
+    assert_eq!(
+        vec![PathBuf::from("/dir/a.txt"), PathBuf::from("/dir/b.txt")],
+        flat
+    );
+    assert_eq!(
+        vec![
+            PathBuf::from("/dir/a.txt"),
+            PathBuf::from("/dir/b.txt"),
+            PathBuf::from("/dir/sub/c.txt"),
+        ],
+        recursive
+    );
+}
+
+#[test]
+#[cfg(feature = "hash")]
+fn digest__open_unsupported__contents_hashed() {
+    let mut fs = MemoryFs::new();
+    fs.write("/abc.txt", "abc").unwrap();
+
+    let digest = fs.digest("/abc.txt").unwrap();
+
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    assert_eq!(
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        hex
+    );
+}
+
+#[test]
+fn transfer_all__tree_from_temp_fs__same_files_in_memory_fs() {
+    let mut src = TempFs::new().unwrap();
+    src.create_dir_all("/fixtures/nested/empty").unwrap();
+    src.write("/fixtures/a.txt", "aaa").unwrap();
+    src.write("/fixtures/nested/b.bin", [0, 1, 2, 255]).unwrap();
+    let mut dst = MemoryFs::new();
+
+    let copied = fs::transfer_all(&src, &mut dst, "/fixtures", "/copy/of/fixtures").unwrap();
+
+    assert_eq!(7, copied);
+    assert_eq!(
+        snapshot(&src, Path::new("/fixtures")),
+        snapshot(&dst, Path::new("/copy/of/fixtures"))
+    );
+}
+
+/// Returns the relative path and contents (if a file) of everything under `root`.
+fn snapshot<F: Fs>(fs: &F, root: &Path) -> Vec<(PathBuf, Option<Vec<u8>>)> {
+    fs.walk_dir(root)
+        .unwrap()
+        .map(|entry| {
+            let contents = match entry.file_type().unwrap() {
+                FileKind::File => Some(fs.read(entry.path()).unwrap()),
+                _ => None,
+            };
+            (
+                entry.path().strip_prefix(root).unwrap().to_path_buf(),
+                contents,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,489 @@
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+#[cfg(any(unix, windows))]
+extern crate tempfile;
+
+#[cfg(target_os = "linux")]
+use std::io;
+
+#[cfg(target_os = "linux")]
+use io_providers::fs::{Fs, NativeFs};
+
+#[test]
+#[cfg(target_os = "linux")]
+fn read_to_string_limited__size_not_in_metadata__limit_enforced_while_reading() {
+    let fs = NativeFs;
+    let path = "/proc/self/status";
+    assert_eq!(0, fs.metadata(path).unwrap().len());
+
+    let result = fs.read_to_string_limited(path, 16);
+    let contents = fs.read_to_string_limited(path, 1 << 20).unwrap();
+
+    assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+    assert!(contents.starts_with("Name:"));
+}
+
+#[test]
+#[cfg(windows)]
+fn open__no_sharing__second_open_fails() {
+    use io_providers::fs::{Fs, NativeFs, OpenOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.txt");
+    let mut fs = NativeFs;
+    fs.write(&path, "contents").unwrap();
+
+    let _file = fs
+        .open(&path, OpenOptions::new().read(true).share_mode(0))
+        .unwrap();
+    let result = fs.open(&path, OpenOptions::new().read(true));
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[cfg(windows)]
+fn open__read_sharing__second_read_succeeds() {
+    use io_providers::fs::{Fs, NativeFs, OpenOptions};
+
+    // FILE_SHARE_READ
+    const SHARE_READ: u32 = 0x1;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.txt");
+    let mut fs = NativeFs;
+    fs.write(&path, "contents").unwrap();
+
+    let _file = fs
+        .open(&path, OpenOptions::new().read(true).share_mode(SHARE_READ))
+        .unwrap();
+    let read = fs.open(&path, OpenOptions::new().read(true));
+    let write = fs.open(&path, OpenOptions::new().write(true));
+
+    assert!(read.is_ok());
+    assert!(write.is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn metadata_with__symlink_to_file__followed_only_when_asked() {
+    use io_providers::fs::{Fs, FsExt, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs = NativeFs;
+    fs.write(dir.path().join("file.txt"), "contents").unwrap();
+    std::os::unix::fs::symlink("file.txt", dir.path().join("link")).unwrap();
+
+    let followed = fs.metadata_with(dir.path().join("link"), true).unwrap();
+    let not_followed = fs.metadata_with(dir.path().join("link"), false).unwrap();
+
+    assert!(followed.is_file() && !followed.file_type().is_symlink());
+    assert!(!not_followed.is_file() && not_followed.file_type().is_symlink());
+}
+
+#[test]
+#[cfg(unix)]
+fn kind__symlink_to_file__symlink() {
+    use io_providers::fs::{FileKind, Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs = NativeFs;
+    fs.write(dir.path().join("file.txt"), "contents").unwrap();
+    std::os::unix::fs::symlink("file.txt", dir.path().join("link")).unwrap();
+
+    assert_eq!(
+        Some(FileKind::Symlink),
+        fs.kind(dir.path().join("link")).unwrap()
+    );
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn append_locked__two_threads__appends_not_interleaved() {
+    use io_providers::fs::{Fs, NativeFs};
+    use std::thread;
+
+    const LINE_LEN: usize = 64 * 1024;
+    const APPENDS: usize = 20;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("log.txt");
+    let threads: Vec<_> = [b'a', b'b']
+        .iter()
+        .map(|&byte| {
+            let path = path.clone();
+            thread::spawn(move || {
+                let mut line = vec![byte; LINE_LEN];
+                line.push(b'\n');
+                for _ in 0..APPENDS {
+                    NativeFs.append_locked(&path, &line).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let contents = NativeFs.read(&path).unwrap();
+    let lines: Vec<&[u8]> = contents
+        .split(|&b| b == b'\n')
+        .filter(|l| !l.is_empty())
+        .collect();
+    assert_eq!(2 * APPENDS, lines.len());
+    for line in lines {
+        assert_eq!(LINE_LEN, line.len());
+        assert!(line.iter().all(|&b| b == line[0]));
+    }
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn ensure_dir__missing_existing_and_file__true_false_then_error() {
+    use io_providers::fs::{Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs = NativeFs;
+    fs.write(dir.path().join("file.txt"), "contents").unwrap();
+
+    assert!(fs.ensure_dir(dir.path().join("dir")).unwrap());
+    assert!(!fs.ensure_dir(dir.path().join("dir")).unwrap());
+    assert_eq!(
+        std::io::ErrorKind::NotADirectory,
+        fs.ensure_dir(dir.path().join("file.txt"))
+            .unwrap_err()
+            .kind()
+    );
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn write_at__offset_within_file__patches_in_place() {
+    use io_providers::fs::{Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.txt");
+    let mut fs = NativeFs;
+    fs.write(&path, "AAAA").unwrap();
+
+    fs.write_at(&path, 1, "BB").unwrap();
+
+    assert_eq!("ABBA", fs.read_to_string(&path).unwrap());
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn list_dir__mixed_entries__paths_relative_to_given_dir() {
+    use io_providers::fs::{FileKind, Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs = NativeFs;
+    fs.create_dir(dir.path().join("sub")).unwrap();
+    fs.write(dir.path().join("file.txt"), "").unwrap();
+
+    let entries = fs.list_dir(dir.path()).unwrap();
+
+    let paths: Vec<_> = entries.iter().map(|e| e.path().to_path_buf()).collect();
+    assert_eq!(
+        vec![dir.path().join("file.txt"), dir.path().join("sub")],
+        paths
+    );
+    assert_eq!("file.txt", entries[0].file_name());
+    assert_eq!(FileKind::File, entries[0].file_type().unwrap());
+    assert_eq!(FileKind::Dir, entries[1].file_type().unwrap());
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn write_atomic__two_threads__each_write_whole() {
+    use io_providers::fs::{Fs, FsExt, NativeFs};
+    use std::thread;
+
+    const WRITES: usize = 50;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.txt");
+    let threads: Vec<_> = ["first", "second"]
+        .iter()
+        .map(|&contents| {
+            let path = path.clone();
+            thread::spawn(move || {
+                for _ in 0..WRITES {
+                    NativeFs.write_atomic(&path, contents).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let contents = NativeFs.read_to_string(&path).unwrap();
+    assert!(contents == "first" || contents == "second", "{}", contents);
+    assert_eq!(1, std::fs::read_dir(dir.path()).unwrap().count());
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn open_reporting__new_file_via_parent_component__canonical_path() {
+    use io_providers::fs::{Fs, NativeFs, OpenOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs = NativeFs;
+    fs.create_dir(dir.path().join("sub")).unwrap();
+
+    let (path, _) = fs
+        .open_reporting(
+            dir.path().join("sub").join("..").join("new.txt"),
+            OpenOptions::new().write(true).create(true),
+        )
+        .unwrap();
+
+    assert_eq!(dir.path().canonicalize().unwrap().join("new.txt"), path);
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn copy_streaming__multi_chunk_file__reports_progress_or_cancels() {
+    use io_providers::fs::{Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs = NativeFs;
+    let contents = vec![7; 200 * 1024];
+    fs.write(dir.path().join("src.bin"), &contents).unwrap();
+    let mut progress = Vec::new();
+
+    let result = fs.copy_streaming(
+        dir.path().join("src.bin"),
+        dir.path().join("dst.bin"),
+        |copied| {
+            progress.push(copied);
+            true
+        },
+    );
+
+    assert_eq!(contents.len() as u64, result.unwrap());
+    assert_eq!(contents, fs.read(dir.path().join("dst.bin")).unwrap());
+    assert!(progress.len() > 1);
+    assert!(progress.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(Some(&(contents.len() as u64)), progress.last());
+
+    let result = fs.copy_streaming(
+        dir.path().join("src.bin"),
+        dir.path().join("cancelled.bin"),
+        |_| false,
+    );
+
+    assert_eq!(std::io::ErrorKind::Interrupted, result.unwrap_err().kind());
+    assert!(!fs.exists(dir.path().join("cancelled.bin")));
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn copy_streaming__cancelled_over_existing_destination__destination_unchanged() {
+    use io_providers::fs::{Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs = NativeFs;
+    fs.write(dir.path().join("src.bin"), vec![7; 200 * 1024])
+        .unwrap();
+    fs.write(dir.path().join("dst.bin"), "original").unwrap();
+
+    let result = fs.copy_streaming(
+        dir.path().join("src.bin"),
+        dir.path().join("dst.bin"),
+        |_| false,
+    );
+
+    assert_eq!(std::io::ErrorKind::Interrupted, result.unwrap_err().kind());
+    assert_eq!(
+        "original",
+        fs.read_to_string(dir.path().join("dst.bin")).unwrap()
+    );
+    assert_eq!(2, std::fs::read_dir(dir.path()).unwrap().count());
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn truncate__existing_and_missing__emptied_or_not_found() {
+    use io_providers::fs::{Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.txt");
+    let mut fs = NativeFs;
+    fs.write(&path, "contents").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let permissions = std::fs::Permissions::from_mode(0o640);
+        fs.set_permissions(&path, permissions).unwrap();
+    }
+
+    fs.truncate(&path).unwrap();
+
+    assert_eq!("", fs.read_to_string(&path).unwrap());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs.metadata(&path).unwrap().permissions().mode();
+        assert_eq!(0o640, mode & 0o777);
+    }
+    assert_eq!(
+        std::io::ErrorKind::NotFound,
+        fs.truncate(dir.path().join("missing.txt"))
+            .unwrap_err()
+            .kind()
+    );
+    assert!(!fs.exists(dir.path().join("missing.txt")));
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn hard_link__file_and_dir__linked_or_invalid_input() {
+    use io_providers::fs::{Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs = NativeFs;
+    fs.write(dir.path().join("file.txt"), "contents").unwrap();
+    fs.create_dir(dir.path().join("dir")).unwrap();
+
+    fs.hard_link(dir.path().join("file.txt"), dir.path().join("link.txt"))
+        .unwrap();
+    let result = fs.hard_link(dir.path().join("dir"), dir.path().join("dir_link"));
+
+    assert_eq!(
+        "contents",
+        fs.read_to_string(dir.path().join("link.txt")).unwrap()
+    );
+    assert_eq!(std::io::ErrorKind::InvalidInput, result.unwrap_err().kind());
+    assert!(!fs.exists(dir.path().join("dir_link")));
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn rename__same_or_equivalent_path__no_op() {
+    use io_providers::fs::{Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let mut fs = NativeFs;
+    fs.create_dir(root.join("dir")).unwrap();
+    fs.write(root.join("dir/file.txt"), "contents").unwrap();
+
+    fs.rename(root.join("dir/file.txt"), root.join("dir/file.txt"))
+        .unwrap();
+    fs.rename(root.join("dir"), root.join("dir/.")).unwrap();
+    fs.rename(root.join("dir/file.txt"), root.join("dir/../dir/file.txt"))
+        .unwrap();
+    let result = fs.rename(root.join("missing"), root.join("missing"));
+
+    assert_eq!(
+        "contents",
+        fs.read_to_string(root.join("dir/file.txt")).unwrap()
+    );
+    assert_eq!(std::io::ErrorKind::NotFound, result.unwrap_err().kind());
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn read_prefix__longer_equal_and_shorter_files__at_most_n_bytes() {
+    use io_providers::fs::{Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs = NativeFs;
+    fs.write(dir.path().join("longer.bin"), b"\x89PNG\r\n")
+        .unwrap();
+    fs.write(dir.path().join("equal.bin"), b"\x89PNG").unwrap();
+    fs.write(dir.path().join("shorter.bin"), b"\x89P").unwrap();
+
+    assert_eq!(
+        b"\x89PNG",
+        &fs.read_prefix(dir.path().join("longer.bin"), 4).unwrap()[..]
+    );
+    assert_eq!(
+        b"\x89PNG",
+        &fs.read_prefix(dir.path().join("equal.bin"), 4).unwrap()[..]
+    );
+    assert_eq!(
+        b"\x89P",
+        &fs.read_prefix(dir.path().join("shorter.bin"), 4).unwrap()[..]
+    );
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn read_lines_limited__normal_and_overlong_lines__error_only_for_overlong() {
+    use io_providers::fs::{Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs = NativeFs;
+    let long_line = "x".repeat(100 * 1024);
+    let contents = format!("short\r\n{}\nexactly8\nlast", long_line);
+    fs.write(dir.path().join("test.txt"), contents).unwrap();
+
+    let lines: Vec<std::io::Result<String>> = fs
+        .read_lines_limited(dir.path().join("test.txt"), 8)
+        .unwrap()
+        .collect();
+
+    assert_eq!(4, lines.len());
+    assert_eq!("short", lines[0].as_ref().unwrap());
+    assert_eq!(
+        std::io::ErrorKind::InvalidData,
+        lines[1].as_ref().unwrap_err().kind()
+    );
+    assert_eq!("exactly8", lines[2].as_ref().unwrap());
+    assert_eq!("last", lines[3].as_ref().unwrap());
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn kind__file_dir_and_missing__reported() {
+    use io_providers::fs::{FileKind, Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs = NativeFs;
+    fs.create_dir(dir.path().join("dir")).unwrap();
+    fs.write(dir.path().join("dir/file.txt"), "contents")
+        .unwrap();
+
+    assert_eq!(
+        Some(FileKind::Dir),
+        fs.kind(dir.path().join("dir")).unwrap()
+    );
+    assert_eq!(
+        Some(FileKind::File),
+        fs.kind(dir.path().join("dir/file.txt")).unwrap()
+    );
+    assert_eq!(None, fs.kind(dir.path().join("missing")).unwrap());
+    assert_eq!(None, fs.kind(dir.path().join("missing/nested")).unwrap());
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn append_locked__missing_then_existing__creates_then_appends() {
+    use io_providers::fs::{Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("log.txt");
+    let mut fs = NativeFs;
+
+    fs.append_locked(&path, "first\n").unwrap();
+    fs.append_locked(&path, "second\n").unwrap();
+
+    assert_eq!("first\nsecond\n", fs.read_to_string(&path).unwrap());
+}
+
+#[test]
+#[cfg(any(unix, windows))]
+fn write_counted__new_and_existing_file__count_equals_length() {
+    use io_providers::fs::{Fs, NativeFs};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.txt");
+    let mut fs = NativeFs;
+
+    assert_eq!(8, fs.write_counted(&path, "contents").unwrap());
+    assert_eq!(3, fs.write_counted(&path, "new").unwrap());
+    assert_eq!("new", fs.read_to_string(&path).unwrap());
+}
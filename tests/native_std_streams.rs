@@ -0,0 +1,61 @@
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+extern crate tempfile;
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use io_providers::std_streams::{NativeStdStreams, StdStreams};
+
+/// Set in the environment of the child process which runs `read_piped_stdin`, to the path of the
+/// file it writes what it read to.
+const RESULT_VAR: &str = "IO_PROVIDERS_TEST_STDIN_RESULT";
+
+// Reading stdin needs control over what it contains, so this test runs itself in a child process
+// with stdin piped in, and does nothing when run directly.
+#[test]
+fn read_piped_stdin() {
+    let result_path = match env::var_os(RESULT_VAR) {
+        Some(path) => path,
+        None => return,
+    };
+    let mut streams = NativeStdStreams::new();
+    let mut first = String::new();
+    let mut second = String::new();
+    let mut rest = String::new();
+
+    streams.buffered_input().read_line(&mut first).unwrap();
+    streams.buffered_input().read_line(&mut second).unwrap();
+    streams.input().read_to_string(&mut rest).unwrap();
+
+    fs::write(result_path, format!("{:?}", (first, second, rest))).unwrap();
+}
+
+#[test]
+fn buffered_input__piped_lines__read_line_by_line_then_rest_read_directly() {
+    let dir = tempfile::tempdir().unwrap();
+    let result_path = dir.path().join("result.txt");
+    let mut child = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "read_piped_stdin", "--test-threads", "1"])
+        .env(RESULT_VAR, &result_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"one\ntwo\nthree\n")
+        .unwrap();
+
+    assert!(child.wait().unwrap().success());
+    assert_eq!(
+        r#"("one\n", "two\n", "three\n")"#,
+        fs::read_to_string(&result_path).unwrap()
+    );
+}
@@ -0,0 +1,36 @@
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+
+use std::panic;
+
+use io_providers::std_streams::{install_panic_hook, SharedStdStreams, SimulatedStdStreams};
+
+// Panic hooks are global to the process, so this is kept in its own test binary.
+#[test]
+fn install_panic_hook__caught_panic__message_written_to_error_stream() {
+    let streams = SharedStdStreams::new(SimulatedStdStreams::new());
+    install_panic_hook(streams.clone());
+
+    let result = panic::catch_unwind(|| panic!("captured {}", 42));
+    let _ = panic::take_hook();
+
+    assert!(result.is_err());
+    let error = String::from_utf8(streams.lock().read_error().to_vec()).unwrap();
+    assert!(
+        error.starts_with("thread '"),
+        "unexpected message: {}",
+        error
+    );
+    assert!(
+        error.contains("panicked at"),
+        "unexpected message: {}",
+        error
+    );
+    assert!(
+        error.contains("captured 42"),
+        "unexpected message: {}",
+        error
+    );
+    assert!(streams.lock().read_output().is_empty());
+}
@@ -0,0 +1,22 @@
+#![allow(non_snake_case)]
+
+extern crate io_providers;
+
+use std::env;
+
+use io_providers::{EnvExt, NativeEnv};
+
+// Changing the process's environment while other threads may read it isn't safe, so this is kept
+// in its own test binary.
+#[test]
+fn scoped_var__native_env__restored_on_drop() {
+    let key = "IO_PROVIDERS_TEST_SCOPED_VAR";
+    let mut provider = NativeEnv;
+
+    {
+        let _guard = provider.scoped_var(key, "value");
+        assert_eq!(Ok("value".to_owned()), env::var(key));
+    }
+
+    assert_eq!(None, env::var_os(key));
+}
@@ -1,11 +1,17 @@
 #![allow(non_snake_case)]
 
 extern crate io_providers;
+extern crate tempfile;
 
 use std::env;
-use std::path::PathBuf;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use io_providers::fs::{Fs, TempFs};
+#[cfg(unix)]
+use io_providers::fs::FsExt;
+use io_providers::fs::{FileKind, Fs, FsEvent, FsOp, OpenOptions, TempFs};
 
 #[test]
 fn fs__uses_system_temp_dir() {
@@ -41,3 +47,829 @@ fn fs__dropped_from_scope__cleans_up_temp_dir() {
 
     assert!(!temp_dir.exists());
 }
+
+#[test]
+fn swap__two_files__contents_are_exchanged() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("a.txt", "first")
+        .expect("Failed to write test file");
+    fs.write("b.txt", "second")
+        .expect("Failed to write test file");
+
+    fs.swap("a.txt", "b.txt").expect("Failed to swap files");
+
+    assert_eq!("second", fs.read_to_string("a.txt").unwrap());
+    assert_eq!("first", fs.read_to_string("b.txt").unwrap());
+}
+
+#[test]
+fn try_exists__missing_or_outside_sandbox__returns_false() {
+    let fs = TempFs::new().expect("Failed to create new TempFs");
+
+    assert!(!fs.try_exists("missing.txt").unwrap());
+    assert!(!fs.try_exists("missing/child.txt").unwrap());
+    assert!(!fs.try_exists("../outside.txt").unwrap());
+}
+
+#[test]
+fn try_exists__path_through_file__returns_error() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("file.txt", "contents")
+        .expect("Failed to write test file");
+
+    let result = fs.try_exists("file.txt/child.txt");
+
+    assert!(result.is_err());
+    assert!(!fs.exists("file.txt/child.txt"));
+}
+
+#[test]
+#[cfg(unix)]
+fn nlink__hard_link_added_and_removed__count_tracks_links() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("file.txt", "contents")
+        .expect("Failed to write test file");
+
+    fs.hard_link("file.txt", "link.txt")
+        .expect("Failed to create hard link");
+    assert_eq!(2, fs.nlink("file.txt").unwrap());
+
+    fs.remove_file("link.txt").expect("Failed to remove link");
+    assert_eq!(1, fs.nlink("file.txt").unwrap());
+}
+
+#[test]
+#[cfg(unix)]
+fn set_umask__files_and_dirs_created__permissions_are_deterministic() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.set_umask(0o027);
+
+    fs.write("file.txt", "contents")
+        .expect("Failed to write test file");
+    fs.create_dir("dir").expect("Failed to create directory");
+    fs.open(
+        "dir/opened.txt",
+        OpenOptions::new().write(true).create(true),
+    )
+    .expect("Failed to open new file");
+
+    let mode = |path: &str| fs.metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(0o640, mode("file.txt"));
+    assert_eq!(0o750, mode("dir"));
+    assert_eq!(0o640, mode("dir/opened.txt"));
+}
+
+#[test]
+fn read_lines__three_line_file__yields_each_line_without_newline() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("lines.txt", "one\ntwo\r\nthree\n")
+        .expect("Failed to write test file");
+
+    let lines: Vec<String> = fs
+        .read_lines("lines.txt")
+        .expect("Failed to open test file")
+        .collect::<Result<_, _>>()
+        .expect("Failed to read lines");
+
+    assert_eq!(vec!["one", "two", "three"], lines);
+}
+
+#[test]
+fn fs__absolute_path__relative_to_temp_dir() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+
+    fs.write("/test.txt", "contents")
+        .expect("Failed to write test file");
+
+    assert!(fs.path().join("test.txt").exists());
+    assert_eq!("contents", fs.read_to_string("test.txt").unwrap());
+    assert!(!fs.exists("/../test.txt"));
+}
+
+#[test]
+fn create_dir_all__nested_path__creates_each_directory() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+
+    fs.create_dir_all("/a/b/c")
+        .expect("Failed to create directories");
+    fs.create_dir_all("a/b")
+        .expect("Failed to create directories");
+
+    assert!(fs.path().join("a/b/c").is_dir());
+    assert!(fs.create_dir_all("../escaped").is_err());
+}
+
+#[test]
+fn open__create_new_for_new_path__creates_file() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+
+    fs.open("new.txt", OpenOptions::new().write(true).create_new(true))
+        .expect("Failed to create new file");
+
+    assert!(fs.path().join("new.txt").is_file());
+}
+
+#[test]
+fn open__create_new_for_nested_path_with_existing_parent__creates_file() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.create_dir_all("/a/b")
+        .expect("Failed to create directories");
+
+    fs.open(
+        "/a/b/new.txt",
+        OpenOptions::new().write(true).create_new(true),
+    )
+    .expect("Failed to create new file");
+
+    assert!(fs.path().join("a/b/new.txt").is_file());
+}
+
+#[test]
+fn open__create_new_for_existing_path__fails_with_already_exists() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("existing.txt", "contents")
+        .expect("Failed to write test file");
+
+    let result = fs.open(
+        "existing.txt",
+        OpenOptions::new().write(true).create_new(true),
+    );
+
+    assert_eq!(io::ErrorKind::AlreadyExists, result.unwrap_err().kind());
+    assert_eq!("contents", fs.read_to_string("existing.txt").unwrap());
+}
+
+#[test]
+fn set_byte_quota__writes_exceed_quota__fails_with_storage_full() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.set_byte_quota(Some(10));
+
+    fs.write("small.txt", "12345")
+        .expect("Failed to write within quota");
+    fs.copy("small.txt", "copy.txt")
+        .expect("Failed to copy within quota");
+    let result = fs.write("large.txt", "123");
+
+    assert_eq!(io::ErrorKind::StorageFull, result.unwrap_err().kind());
+    assert!(!fs.exists("large.txt"));
+    assert_eq!(
+        io::ErrorKind::StorageFull,
+        fs.copy("small.txt", "copy2.txt").unwrap_err().kind()
+    );
+
+    fs.set_byte_quota(None);
+    fs.write("large.txt", "123")
+        .expect("Failed to write without quota");
+}
+
+#[test]
+fn set_byte_quota__failed_writes_and_copies__quota_not_used_up() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("source.txt", "12345")
+        .expect("Failed to write test file");
+    fs.set_byte_quota(Some(10));
+
+    assert!(fs.write("missing/test.txt", "12345").is_err());
+    assert!(fs.copy("source.txt", "missing/copy.txt").is_err());
+    assert!(fs.append_locked("missing/log.txt", "12345").is_err());
+    assert!(fs.write_at("missing.txt", 0, "12345").is_err());
+
+    fs.write("test.txt", "12345")
+        .expect("Failed to write within quota");
+    assert_eq!(
+        io::ErrorKind::StorageFull,
+        fs.write("more.txt", "1").unwrap_err().kind()
+    );
+}
+
+#[test]
+fn write_counted__quota_exceeded_mid_write__partial_count_in_error() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.set_byte_quota(Some(4));
+
+    let error = fs.write_counted("test.txt", "0123456789").unwrap_err();
+
+    assert_eq!(io::ErrorKind::StorageFull, error.kind());
+    assert_eq!(
+        "test.txt: wrote 4 of 10 bytes: TempFs byte quota exceeded",
+        error.to_string()
+    );
+    assert_eq!("0123", fs.read_to_string("test.txt").unwrap());
+}
+
+#[test]
+fn read__missing_file__os_error_kept_as_source() {
+    let fs = TempFs::new().expect("Failed to create new TempFs");
+
+    let error = fs.read("missing.txt").unwrap_err();
+
+    let source = error.get_ref().and_then(|e| e.source()).unwrap();
+    let source = source.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(io::ErrorKind::NotFound, source.kind());
+    assert!(source.raw_os_error().is_some());
+}
+
+#[test]
+fn read__missing_file__error_names_sandbox_path_not_host_path() {
+    let fs = TempFs::new().expect("Failed to create new TempFs");
+
+    let error = fs.read("/dir/missing.txt").unwrap_err();
+
+    assert_eq!(io::ErrorKind::NotFound, error.kind());
+    let message = error.to_string();
+    assert!(message.starts_with("/dir/missing.txt: "), "{}", message);
+    assert!(
+        !message.contains(&*fs.path().to_string_lossy()),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn read_to_string_limited__within_and_over_limit__ok_then_invalid_data() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("small.txt", "12345")
+        .expect("Failed to write small file");
+    fs.write("large.txt", "123456")
+        .expect("Failed to write large file");
+
+    assert_eq!("12345", fs.read_to_string_limited("small.txt", 5).unwrap());
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        fs.read_to_string_limited("large.txt", 5)
+            .unwrap_err()
+            .kind()
+    );
+}
+
+#[test]
+fn read_to_string_limited__max_limit__whole_file() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("file.txt", "12345")
+        .expect("Failed to write test file");
+
+    assert_eq!(
+        "12345",
+        fs.read_to_string_limited("file.txt", usize::MAX).unwrap()
+    );
+}
+
+#[test]
+fn open_sandboxed__write_through_file__readable_via_provider() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+
+    {
+        let mut file = fs
+            .open_sandboxed("/test.txt", OpenOptions::new().write(true).create(true))
+            .expect("Failed to open file");
+        assert_eq!(Path::new("/test.txt"), file.path());
+        file.write_all(b"contents").expect("Failed to write file");
+        assert_eq!(8, file.metadata().unwrap().len());
+    }
+
+    assert_eq!("contents", fs.read_to_string("test.txt").unwrap());
+}
+
+#[test]
+fn open_sandboxed__write_exceeds_quota__fails_with_storage_full() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.set_byte_quota(Some(4));
+
+    let mut file = fs
+        .open_sandboxed("test.txt", OpenOptions::new().write(true).create(true))
+        .expect("Failed to open file");
+    let error = file.write_all(b"12345").unwrap_err();
+
+    assert_eq!(io::ErrorKind::StorageFull, error.kind());
+    assert!(error.to_string().starts_with("test.txt: "));
+}
+
+#[test]
+fn set_latency__read_delayed_then_cleared__elapsed_time_reflects_delay() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("test.txt", "contents")
+        .expect("Failed to write file");
+    // Long enough that an undelayed operation won't take as long even on a loaded machine
+    let delay = Duration::from_millis(500);
+
+    fs.set_latency(FsOp::Read, delay);
+    let start = Instant::now();
+    fs.read("test.txt").expect("Failed to read file");
+    assert!(start.elapsed() >= delay);
+
+    fs.set_latency(FsOp::Read, Duration::ZERO);
+    let start = Instant::now();
+    fs.read("test.txt").expect("Failed to read file");
+    assert!(start.elapsed() < delay);
+}
+
+#[test]
+fn open_reporting__nested_path__reports_sandbox_path() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.create_dir_all("/dir/sub")
+        .expect("Failed to create directories");
+
+    let (path, _) = fs
+        .open_reporting(
+            "dir/sub/../sub/test.txt",
+            OpenOptions::new().write(true).create(true),
+        )
+        .expect("Failed to open file");
+
+    assert_eq!(Path::new("/dir/sub/test.txt"), path);
+}
+
+#[test]
+#[cfg(unix)]
+fn canonicalize__symlink_within_sandbox__resolves_to_sandbox_path() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.create_dir_all("/dir/target")
+        .expect("Failed to create directories");
+    std::os::unix::fs::symlink("target", fs.path().join("dir/link"))
+        .expect("Failed to create symlink");
+
+    let result = fs.canonicalize("/dir/./link").unwrap();
+
+    assert_eq!(Path::new("/dir/target"), result);
+}
+
+#[test]
+#[cfg(unix)]
+fn remove_dir_all__dir_containing_symlinks__links_removed_not_traversed() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    let outside = tempfile::tempdir().expect("Failed to create temp dir");
+    std::fs::write(outside.path().join("keep.txt"), "outside").unwrap();
+    fs.create_dir_all("/dir/sub").unwrap();
+    fs.create_dir_all("/other").unwrap();
+    fs.write("/dir/sub/file.txt", "").unwrap();
+    fs.write("/other/keep.txt", "inside").unwrap();
+    std::os::unix::fs::symlink("../other", fs.path().join("dir/inside_link")).unwrap();
+    std::os::unix::fs::symlink(outside.path(), fs.path().join("dir/sub/outside_link")).unwrap();
+
+    fs.remove_dir_all("/dir").unwrap();
+
+    assert!(!fs.exists("/dir"));
+    assert_eq!("inside", fs.read_to_string("/other/keep.txt").unwrap());
+    assert!(outside.path().join("keep.txt").exists());
+    assert_eq!(
+        vec![FsEvent::Remove(PathBuf::from("/dir"))],
+        fs.operations()
+            .iter()
+            .filter(|event| matches!(event, FsEvent::Remove(_)))
+            .cloned()
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn remove_dir_all__symlink_to_dir__only_link_removed() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.create_dir_all("/target").unwrap();
+    fs.write("/target/file.txt", "contents").unwrap();
+    std::os::unix::fs::symlink("target", fs.path().join("link")).unwrap();
+
+    fs.remove_dir_all("/link").unwrap();
+
+    assert_eq!(None, fs.kind("/link").unwrap());
+    assert_eq!("contents", fs.read_to_string("/target/file.txt").unwrap());
+}
+
+#[test]
+fn remove_dir_all__regular_file__not_a_directory() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("/file.txt", "contents").unwrap();
+
+    let result = fs.remove_dir_all("/file.txt");
+
+    assert_eq!(io::ErrorKind::NotADirectory, result.unwrap_err().kind());
+    assert!(fs.exists("/file.txt"));
+}
+
+#[test]
+#[cfg(unix)]
+fn walk_dir_with__self_referential_symlink__completes() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.create_dir_all("/dir/sub").unwrap();
+    fs.write("/dir/sub/file.txt", "").unwrap();
+    std::os::unix::fs::symlink("..", fs.path().join("dir/sub/parent")).unwrap();
+    std::os::unix::fs::symlink(".", fs.path().join("dir/self")).unwrap();
+
+    let walked: Vec<PathBuf> = fs
+        .walk_dir_with("/dir", true)
+        .unwrap()
+        .map(|entry| entry.path().to_path_buf())
+        .collect();
+
+    assert_eq!(
+        vec![
+            PathBuf::from("/dir/self"),
+            PathBuf::from("/dir/sub"),
+            PathBuf::from("/dir/sub/file.txt"),
+            PathBuf::from("/dir/sub/parent"),
+        ],
+        walked
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn walk_dir_with__symlink_to_dir__followed_only_when_asked() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.create_dir_all("/dir").unwrap();
+    fs.create_dir_all("/other/nested").unwrap();
+    fs.write("/other/file.txt", "").unwrap();
+    std::os::unix::fs::symlink("../other", fs.path().join("dir/link")).unwrap();
+    std::os::unix::fs::symlink("missing", fs.path().join("dir/broken")).unwrap();
+    let walk = |follow_symlinks| -> Vec<(PathBuf, FileKind)> {
+        fs.walk_dir_with("/dir", follow_symlinks)
+            .unwrap()
+            .map(|entry| (entry.path().to_path_buf(), entry.file_type().unwrap()))
+            .collect()
+    };
+
+    let not_followed = walk(false);
+    let followed = walk(true);
+
+    assert_eq!(
+        vec![
+            (PathBuf::from("/dir/broken"), FileKind::Symlink),
+            (PathBuf::from("/dir/link"), FileKind::Symlink),
+        ],
+        not_followed
+    );
+    assert_eq!(
+        vec![
+            (PathBuf::from("/dir/broken"), FileKind::Symlink),
+            (PathBuf::from("/dir/link"), FileKind::Symlink),
+            (PathBuf::from("/dir/link/file.txt"), FileKind::File),
+            (PathBuf::from("/dir/link/nested"), FileKind::Dir),
+        ],
+        followed
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn canonicalize__symlink_escaping_sandbox__invalid_input() {
+    let fs = TempFs::new().expect("Failed to create new TempFs");
+    let outside = tempfile::tempdir().expect("Failed to create temp dir");
+    std::os::unix::fs::symlink(outside.path(), fs.path().join("link"))
+        .expect("Failed to create symlink");
+
+    let result = fs.canonicalize("/link");
+
+    assert_eq!(io::ErrorKind::InvalidInput, result.unwrap_err().kind());
+}
+
+#[test]
+#[cfg(unix)]
+fn dir_size__symlinks_in_tree__not_followed_or_counted() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    let outside = tempfile::tempdir().expect("Failed to create temp dir");
+    std::fs::write(outside.path().join("big.txt"), vec![0; 1000]).expect("Failed to write file");
+    fs.create_dir("/dir").expect("Failed to create directory");
+    fs.write("/dir/file.txt", "1234")
+        .expect("Failed to write file");
+    std::os::unix::fs::symlink("file.txt", fs.path().join("dir/inner_link"))
+        .expect("Failed to create symlink");
+    std::os::unix::fs::symlink(outside.path(), fs.path().join("dir/outer_link"))
+        .expect("Failed to create symlink");
+
+    let result = fs.dir_size("/dir").unwrap();
+
+    assert_eq!(4, result);
+}
+
+#[test]
+fn operations__write_rename_remove__recorded_in_order() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.create_dir("/dir").unwrap();
+    fs.write("/dir/a.txt", "contents").unwrap();
+    fs.rename("/dir/a.txt", "/dir/b.txt").unwrap();
+    fs.remove_file("/dir/b.txt").unwrap();
+    let _ = fs.remove_file("/dir/missing.txt");
+    let _ = fs.read_to_string("/dir/b.txt");
+
+    assert_eq!(
+        &[
+            FsEvent::CreateDir(PathBuf::from("/dir")),
+            FsEvent::Write(PathBuf::from("/dir/a.txt")),
+            FsEvent::Rename {
+                from: PathBuf::from("/dir/a.txt"),
+                to: PathBuf::from("/dir/b.txt"),
+            },
+            FsEvent::Remove(PathBuf::from("/dir/b.txt")),
+        ][..],
+        fs.operations()
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn read_link__absolute_target_within_sandbox__sandbox_path() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.create_dir("/dir").expect("Failed to create directory");
+    fs.write("/dir/target.txt", "")
+        .expect("Failed to write file");
+    std::os::unix::fs::symlink(fs.path().join("dir/target.txt"), fs.path().join("link"))
+        .expect("Failed to create symlink");
+
+    let result = fs.read_link("/link").unwrap();
+
+    assert_eq!(Path::new("/dir/target.txt"), result);
+    assert!(fs
+        .symlink_metadata("/link")
+        .unwrap()
+        .file_type()
+        .is_symlink());
+}
+
+#[test]
+#[cfg(unix)]
+fn read_link__relative_target__unchanged() {
+    let fs = TempFs::new().expect("Failed to create new TempFs");
+    std::os::unix::fs::symlink("../missing.txt", fs.path().join("link"))
+        .expect("Failed to create symlink");
+
+    let result = fs.read_link("/link").unwrap();
+
+    assert_eq!(Path::new("../missing.txt"), result);
+}
+
+#[test]
+#[cfg(unix)]
+fn read_link__absolute_target_outside_sandbox__invalid_input() {
+    let fs = TempFs::new().expect("Failed to create new TempFs");
+    let outside = tempfile::tempdir().expect("Failed to create temp dir");
+    std::os::unix::fs::symlink(outside.path(), fs.path().join("link"))
+        .expect("Failed to create symlink");
+
+    let result = fs.read_link("/link");
+
+    assert_eq!(io::ErrorKind::InvalidInput, result.unwrap_err().kind());
+}
+
+#[test]
+#[cfg(unix)]
+fn metadata_with__symlink_to_file__followed_only_when_asked() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("/file.txt", "contents")
+        .expect("Failed to write file");
+    std::os::unix::fs::symlink("file.txt", fs.path().join("link"))
+        .expect("Failed to create symlink");
+
+    let followed = fs.metadata_with("/link", true).unwrap();
+    let not_followed = fs.metadata_with("/link", false).unwrap();
+
+    assert!(followed.is_file() && !followed.file_type().is_symlink());
+    assert!(!not_followed.is_file() && not_followed.file_type().is_symlink());
+}
+
+#[test]
+#[cfg(unix)]
+fn kind__symlinks__reported_as_symlinks() {
+    let fs = TempFs::new().expect("Failed to create new TempFs");
+    std::os::unix::fs::symlink("missing", fs.path().join("dangling"))
+        .expect("Failed to create symlink");
+    std::os::unix::fs::symlink("/", fs.path().join("to_root")).expect("Failed to create symlink");
+
+    assert_eq!(Some(FileKind::Symlink), fs.kind("/dangling").unwrap());
+    assert_eq!(Some(FileKind::Symlink), fs.kind("/to_root").unwrap());
+}
+
+#[test]
+fn ensure_dir__missing_existing_and_file__true_false_then_error() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("file.txt", "contents")
+        .expect("Failed to write test file");
+
+    assert!(fs.ensure_dir("dir").unwrap());
+    assert!(!fs.ensure_dir("dir").unwrap());
+    assert_eq!(
+        io::ErrorKind::NotADirectory,
+        fs.ensure_dir("file.txt").unwrap_err().kind()
+    );
+}
+
+#[test]
+fn write_at__offset_within_file__patches_in_place() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("test.txt", "AAAA")
+        .expect("Failed to write test file");
+
+    fs.write_at("test.txt", 1, "BB").unwrap();
+
+    assert_eq!("ABBA", fs.read_to_string("test.txt").unwrap());
+}
+
+#[test]
+fn list_dir__mixed_entries__paths_relative_to_given_dir() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.create_dir_all("/dir/sub")
+        .expect("Failed to create directories");
+    fs.write("/dir/file.txt", "")
+        .expect("Failed to write test file");
+
+    let entries = fs.list_dir("/dir").unwrap();
+
+    let paths: Vec<&Path> = entries.iter().map(|e| e.path()).collect();
+    assert_eq!(
+        vec![Path::new("/dir/file.txt"), Path::new("/dir/sub")],
+        paths
+    );
+    assert_eq!("file.txt", entries[0].file_name());
+    assert_eq!(FileKind::File, entries[0].file_type().unwrap());
+    assert_eq!(FileKind::Dir, entries[1].file_type().unwrap());
+}
+
+#[test]
+fn operations__existing_and_created_files_opened__only_creation_recorded() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("/existing.txt", "contents")
+        .expect("Failed to write test file");
+
+    fs.open("/existing.txt", OpenOptions::new().read(true))
+        .unwrap();
+    fs.open("/new.txt", OpenOptions::new().write(true).create(true))
+        .unwrap();
+
+    assert_eq!(
+        &[
+            FsEvent::Write(PathBuf::from("/existing.txt")),
+            FsEvent::Write(PathBuf::from("/new.txt")),
+        ][..],
+        fs.operations()
+    );
+}
+
+#[test]
+fn copy_streaming__multi_chunk_file__reports_progress_or_cancels() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    let contents = vec![7; 200 * 1024];
+    fs.write("/src.bin", &contents)
+        .expect("Failed to write test file");
+    let mut progress = Vec::new();
+
+    let result = fs.copy_streaming("/src.bin", "/dst.bin", |copied| {
+        progress.push(copied);
+        true
+    });
+
+    assert_eq!(contents.len() as u64, result.unwrap());
+    assert_eq!(contents, fs.read("/dst.bin").unwrap());
+    assert!(progress.len() > 1);
+    assert!(progress.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(Some(&(contents.len() as u64)), progress.last());
+
+    let result = fs.copy_streaming("/src.bin", "/cancelled.bin", |_| false);
+
+    assert_eq!(io::ErrorKind::Interrupted, result.unwrap_err().kind());
+    assert!(!fs.exists("/cancelled.bin"));
+}
+
+#[test]
+fn copy_streaming__cancelled_over_existing_destination__destination_unchanged() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("/src.bin", vec![7; 200 * 1024])
+        .expect("Failed to write test file");
+    fs.write("/dst.bin", "original")
+        .expect("Failed to write test file");
+
+    let result = fs.copy_streaming("/src.bin", "/dst.bin", |_| false);
+
+    assert_eq!(io::ErrorKind::Interrupted, result.unwrap_err().kind());
+    assert_eq!("original", fs.read_to_string("/dst.bin").unwrap());
+    assert_eq!(2, fs.list_dir("/").unwrap().len());
+}
+
+#[test]
+fn truncate__existing_and_missing__emptied_or_not_found() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("/test.txt", "contents")
+        .expect("Failed to write test file");
+
+    fs.truncate("/test.txt").unwrap();
+
+    assert_eq!("", fs.read_to_string("/test.txt").unwrap());
+    assert_eq!(
+        io::ErrorKind::NotFound,
+        fs.truncate("/missing.txt").unwrap_err().kind()
+    );
+    assert!(!fs.exists("/missing.txt"));
+}
+
+#[test]
+fn hard_link__file_and_dir__linked_or_invalid_input() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("/file.txt", "contents")
+        .expect("Failed to write test file");
+    fs.create_dir("/dir").expect("Failed to create directory");
+
+    fs.hard_link("/file.txt", "/link.txt").unwrap();
+    let result = fs.hard_link("/dir", "/dir_link");
+
+    assert_eq!("contents", fs.read_to_string("/link.txt").unwrap());
+    assert_eq!(io::ErrorKind::InvalidInput, result.unwrap_err().kind());
+    assert!(!fs.exists("/dir_link"));
+}
+
+#[test]
+fn rename__same_or_equivalent_path__no_op() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.create_dir("/dir").expect("Failed to create directory");
+    fs.write("/dir/file.txt", "contents")
+        .expect("Failed to write test file");
+
+    fs.rename("/dir/file.txt", "/dir/file.txt").unwrap();
+    fs.rename("/dir", "/dir/.").unwrap();
+    fs.rename("/dir/file.txt", "/dir/../dir/file.txt").unwrap();
+    let result = fs.rename("/missing", "/missing");
+
+    assert_eq!("contents", fs.read_to_string("/dir/file.txt").unwrap());
+    assert_eq!(io::ErrorKind::NotFound, result.unwrap_err().kind());
+}
+
+#[test]
+fn read_prefix__longer_equal_and_shorter_files__at_most_n_bytes() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("/longer.bin", b"\x89PNG\r\n")
+        .expect("Failed to write test file");
+    fs.write("/equal.bin", b"\x89PNG")
+        .expect("Failed to write test file");
+    fs.write("/shorter.bin", b"\x89P")
+        .expect("Failed to write test file");
+
+    assert_eq!(b"\x89PNG", &fs.read_prefix("/longer.bin", 4).unwrap()[..]);
+    assert_eq!(b"\x89PNG", &fs.read_prefix("/equal.bin", 4).unwrap()[..]);
+    assert_eq!(b"\x89P", &fs.read_prefix("/shorter.bin", 4).unwrap()[..]);
+}
+
+#[test]
+fn read_lines_limited__normal_and_overlong_lines__error_only_for_overlong() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    let long_line = "x".repeat(100 * 1024);
+    let contents = format!("short\r\n{}\nexactly8\nlast", long_line);
+    fs.write("/test.txt", contents)
+        .expect("Failed to write test file");
+
+    let lines: Vec<io::Result<String>> = fs.read_lines_limited("/test.txt", 8).unwrap().collect();
+
+    assert_eq!(4, lines.len());
+    assert_eq!("short", lines[0].as_ref().unwrap());
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        lines[1].as_ref().unwrap_err().kind()
+    );
+    assert_eq!("exactly8", lines[2].as_ref().unwrap());
+    assert_eq!("last", lines[3].as_ref().unwrap());
+}
+
+#[test]
+fn read_lines_limited__max_limit__all_lines() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.write("/test.txt", "first\r\nsecond")
+        .expect("Failed to write test file");
+
+    let lines: Vec<String> = fs
+        .read_lines_limited("/test.txt", usize::MAX)
+        .unwrap()
+        .collect::<io::Result<_>>()
+        .unwrap();
+
+    assert_eq!(vec!["first", "second"], lines);
+}
+
+#[test]
+fn kind__file_dir_and_missing__reported() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+    fs.create_dir("/dir").expect("Failed to create directory");
+    fs.write("/dir/file.txt", "contents")
+        .expect("Failed to write test file");
+
+    assert_eq!(Some(FileKind::Dir), fs.kind("/dir").unwrap());
+    assert_eq!(Some(FileKind::File), fs.kind("/dir/file.txt").unwrap());
+    assert_eq!(None, fs.kind("/missing").unwrap());
+    assert_eq!(None, fs.kind("/missing/nested").unwrap());
+}
+
+#[test]
+fn append_locked__missing_then_existing__creates_then_appends() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+
+    fs.append_locked("/log.txt", "first\n").unwrap();
+    fs.append_locked("/log.txt", "second\n").unwrap();
+
+    assert_eq!("first\nsecond\n", fs.read_to_string("/log.txt").unwrap());
+}
+
+#[test]
+fn write_counted__new_and_existing_file__count_equals_length() {
+    let mut fs = TempFs::new().expect("Failed to create new TempFs");
+
+    assert_eq!(8, fs.write_counted("/test.txt", "contents").unwrap());
+    assert_eq!(3, fs.write_counted("/test.txt", "new").unwrap());
+    assert_eq!("new", fs.read_to_string("/test.txt").unwrap());
+}